@@ -0,0 +1,4 @@
+pub mod solutions;
+pub mod util;
+
+pub use crate::solutions::*;
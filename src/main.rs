@@ -1,7 +1,4 @@
-mod solutions;
-mod util;
-
-use crate::solutions::*;
+use advent_of_code_2020::solutions::*;
 
 fn main() {
     day01::print_solution();
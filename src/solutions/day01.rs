@@ -1,57 +1,456 @@
-use std::fs;
+use crate::util::bigint::BigInt;
+use crate::util::puzzle_input;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 pub fn print_solution() {
     let input = puzzle_input();
-    if let Some((a, b)) = find_first_sum_to(&input, 2020) {
-        println!("Day 01 Solution Part 1: {}", a * b);
+    if let Some(summands) = KSum::new(2, 2020).first(&input) {
+        println!(
+            "Day 01 Solution Part 1: {}",
+            summands.iter().product::<BigInt>()
+        );
     }
 
-    if let Some((a, b, c)) = find_3_summands_to_target(&input, 2020) {
-        println!("Day 01 Solution Part 2: {}", a * b * c);
+    if let Some(summands) = KSum::new(3, 2020).first(&input) {
+        println!(
+            "Day 01 Solution Part 2: {}",
+            summands.iter().product::<BigInt>()
+        );
     }
 }
 
-pub fn find_first_sum_to(values: &[i64], target: i64) -> Option<(i64, i64)> {
-    let mut values = Vec::from(values);
-    values.sort();
+/// How pairs summing to a target are found, once the search is down to two values.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Strategy {
+    /// Walks the sorted values from both ends.
+    TwoPointer,
+    /// Looks up the complement of every value in a map of value counts.
+    Hash,
+}
+
+/// Inputs at least this long are searched with `Strategy::Hash`.
+pub const HASH_THRESHOLD: usize = 1_000;
 
-    find_summands_to_target(&values, target)
+impl Strategy {
+    pub fn for_len(len: usize) -> Self {
+        if len < HASH_THRESHOLD {
+            Strategy::TwoPointer
+        } else {
+            Strategy::Hash
+        }
+    }
 }
 
-pub fn find_summands_to_target(values: &[i64], target: i64) -> Option<(i64, i64)> {
+/// A search for `k` entries, at different positions of the input, that sum to `target`.
+///
+/// Solutions are given as the values in ascending order. Two solutions are
+/// the same if they have the same values, so duplicate entries only give
+/// distinct solutions when they are needed more than once, e.g. `[1010, 1010]`.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct KSum {
+    k: usize,
+    target: i64,
+    strategy: Option<Strategy>,
+}
+
+impl KSum {
+    /// The strategy is picked from the length of the input.
+    pub fn new(k: usize, target: i64) -> Self {
+        KSum {
+            k,
+            target,
+            strategy: None,
+        }
+    }
+
+    pub fn with_strategy(self, strategy: Strategy) -> Self {
+        KSum {
+            strategy: Some(strategy),
+            ..self
+        }
+    }
+
+    pub fn first(&self, values: &[i64]) -> Option<Vec<i64>> {
+        let mut first = None;
+        self.search(values, &mut |summands| {
+            first = Some(summands.to_vec());
+            false
+        });
+        first
+    }
+
+    pub fn all(&self, values: &[i64]) -> Vec<Vec<i64>> {
+        let mut all = Vec::new();
+        self.search(values, &mut |summands| {
+            all.push(summands.to_vec());
+            true
+        });
+        all
+    }
+
+    pub fn count(&self, values: &[i64]) -> usize {
+        let mut count = 0;
+        self.search(values, &mut |_| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// Calls `visit` with every distinct solution until it returns `false`.
+    fn search(&self, values: &[i64], visit: &mut dyn FnMut(&[i64]) -> bool) {
+        if values.len() < self.k {
+            return;
+        }
+        let mut values = values.to_vec();
+        values.sort_unstable();
+
+        let strategy = self
+            .strategy
+            .unwrap_or_else(|| Strategy::for_len(values.len()));
+        let mut chosen = Vec::with_capacity(self.k);
+        // the sums are kept as i128 so no target or partial sum can overflow
+        k_sum(
+            &values,
+            self.k,
+            self.target as i128,
+            strategy,
+            &mut chosen,
+            visit,
+        );
+    }
+}
+
+/// Returns `false` if the search was stopped by `visit`.
+fn k_sum(
+    sorted: &[i64],
+    k: usize,
+    target: i128,
+    strategy: Strategy,
+    chosen: &mut Vec<i64>,
+    visit: &mut dyn FnMut(&[i64]) -> bool,
+) -> bool {
+    match k {
+        0 => target != 0 || visit(chosen),
+        1 => match i64::try_from(target) {
+            Ok(value) if sorted.binary_search(&value).is_ok() => {
+                with_chosen(chosen, &[value], visit)
+            }
+            _ => true,
+        },
+        2 => match strategy {
+            Strategy::TwoPointer => two_pointer(sorted, target, chosen, visit),
+            Strategy::Hash => hashed(sorted, target, chosen, visit),
+        },
+        _ => {
+            for idx in 0..sorted.len() {
+                if idx > 0 && sorted[idx] == sorted[idx - 1] {
+                    continue;
+                }
+                chosen.push(sorted[idx]);
+                let go_on = k_sum(
+                    &sorted[idx + 1..],
+                    k - 1,
+                    target - sorted[idx] as i128,
+                    strategy,
+                    chosen,
+                    visit,
+                );
+                chosen.pop();
+                if !go_on {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
+
+fn with_chosen(
+    chosen: &mut Vec<i64>,
+    values: &[i64],
+    visit: &mut dyn FnMut(&[i64]) -> bool,
+) -> bool {
+    let len = chosen.len();
+    chosen.extend_from_slice(values);
+    let go_on = visit(chosen);
+    chosen.truncate(len);
+    go_on
+}
+
+fn two_pointer(
+    sorted: &[i64],
+    target: i128,
+    chosen: &mut Vec<i64>,
+    visit: &mut dyn FnMut(&[i64]) -> bool,
+) -> bool {
+    if sorted.len() < 2 {
+        return true;
+    }
     let mut lower = 0;
-    let mut upper = values.len() - 1;
+    let mut upper = sorted.len() - 1;
 
     while lower < upper {
-        let sum = values[lower] + values[upper];
+        let sum = sorted[lower] as i128 + sorted[upper] as i128;
 
         if sum < target {
             lower += 1;
         } else if sum > target {
             upper -= 1;
         } else {
-            return Some((values[lower], values[upper]));
+            if !with_chosen(chosen, &[sorted[lower], sorted[upper]], visit) {
+                return false;
+            }
+            let (a, b) = (sorted[lower], sorted[upper]);
+            while lower < upper && sorted[lower] == a {
+                lower += 1;
+            }
+            while lower < upper && sorted[upper] == b {
+                upper -= 1;
+            }
         }
     }
 
-    None
+    true
 }
 
-pub fn find_3_summands_to_target(values: &[i64], target: i64) -> Option<(i64, i64, i64)> {
-    let mut values = Vec::from(values);
-    values.sort();
-    for i in 0..values.len() - 2 {
-        if let Some((a, b)) = find_summands_to_target(&values[i + 1..], target - values[i]) {
-            return Some((values[i], a, b));
+fn hashed(
+    sorted: &[i64],
+    target: i128,
+    chosen: &mut Vec<i64>,
+    visit: &mut dyn FnMut(&[i64]) -> bool,
+) -> bool {
+    let mut counts: HashMap<i64, usize> = HashMap::with_capacity(sorted.len());
+    for &value in sorted {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+
+    for (idx, &a) in sorted.iter().enumerate() {
+        if idx > 0 && sorted[idx - 1] == a {
+            continue;
+        }
+        let b = match i64::try_from(target - a as i128) {
+            Ok(b) if b >= a => b,
+            _ => continue,
+        };
+        let needed = if a == b { 2 } else { 1 };
+        if counts.get(&b).copied().unwrap_or(0) >= needed && !with_chosen(chosen, &[a, b], visit) {
+            return false;
         }
     }
-    None
+
+    true
 }
 
-fn puzzle_input() -> Vec<i64> {
-    let contents = fs::read_to_string("input/day01.txt").expect("Could not read file day01.txt");
+/// The largest number of cells, entries times possible sums, of a `SubsetSum` table.
+pub const MAX_TABLE_CELLS: usize = 50_000_000;
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum SubsetSumError {
+    /// The range of sums to track is too large for the table.
+    TooLarge { cells: u128 },
+}
+
+/// Some of the entries of the input, in the order they appear.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Subset {
+    indices: Vec<usize>,
+    values: Vec<i64>,
+}
+
+impl Subset {
+    /// The positions of the entries in the input.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn values(&self) -> &[i64] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn product(&self) -> BigInt {
+        self.values.iter().product()
+    }
+}
+
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Finds the subsets of the input that sum to a target, by dynamic programming
+/// over the reachable sums. Each entry is used at most once; the empty subset
+/// sums to 0.
+///
+/// For every prefix of the input and every sum, the table holds the
+/// fewest and the most entries of the prefix that sum to it, which
+/// is enough to reconstruct a subset with either.
+pub struct SubsetSum {
+    values: Vec<i64>,
+    target: i64,
+    /// The sum of the first column of the tables.
+    lowest: i128,
+    width: usize,
+    fewest: Vec<Vec<u32>>,
+    most: Vec<Vec<u32>>,
+}
+
+impl SubsetSum {
+    pub fn new(values: &[i64], target: i64) -> Result<Self, SubsetSumError> {
+        let negative: i128 = values.iter().filter(|&&v| v < 0).map(|&v| v as i128).sum();
+        let positive: i128 = values.iter().filter(|&&v| v > 0).map(|&v| v as i128).sum();
+        // sums past the target can never come back when all entries have the same sign
+        let lowest = if negative == 0 {
+            0
+        } else if positive == 0 {
+            negative.max(target as i128)
+        } else {
+            negative
+        };
+        let highest = if negative == 0 {
+            positive.min(target as i128)
+        } else if positive == 0 {
+            0
+        } else {
+            positive
+        };
+
+        let width = (highest - lowest + 1).max(0) as u128;
+        let cells = width * (values.len() as u128 + 1);
+        if cells > MAX_TABLE_CELLS as u128 {
+            return Err(SubsetSumError::TooLarge { cells });
+        }
+        let width = width as usize;
+
+        let mut fewest = vec![vec![UNREACHABLE; width]];
+        let mut most = vec![vec![UNREACHABLE; width]];
+        if lowest <= 0 && 0 <= highest {
+            fewest[0][(-lowest) as usize] = 0;
+            most[0][(-lowest) as usize] = 0;
+        }
+
+        for &value in values {
+            let previous = (fewest.last().unwrap(), most.last().unwrap());
+            let mut next = (previous.0.clone(), previous.1.clone());
+            for column in 0..width {
+                if previous.0[column] == UNREACHABLE {
+                    continue;
+                }
+                let sum = lowest + column as i128 + value as i128;
+                if sum < lowest || sum > highest {
+                    continue;
+                }
+                let to = (sum - lowest) as usize;
+                next.0[to] = next.0[to].min(previous.0[column] + 1);
+                if next.1[to] == UNREACHABLE || next.1[to] < previous.1[column] + 1 {
+                    next.1[to] = previous.1[column] + 1;
+                }
+            }
+            fewest.push(next.0);
+            most.push(next.1);
+        }
+
+        Ok(SubsetSum {
+            values: values.to_vec(),
+            target,
+            lowest,
+            width,
+            fewest,
+            most,
+        })
+    }
+
+    fn target_column(&self) -> Option<usize> {
+        let column = self.target as i128 - self.lowest;
+        if column < 0 || column >= self.width as i128 {
+            None
+        } else {
+            Some(column as usize)
+        }
+    }
 
-    contents
+    fn entries(table: &[Vec<u32>], column: Option<usize>) -> Option<usize> {
+        match table.last()?[column?] {
+            UNREACHABLE => None,
+            n => Some(n as usize),
+        }
+    }
+
+    pub fn is_reachable(&self) -> bool {
+        self.fewest_entries().is_some()
+    }
+
+    pub fn fewest_entries(&self) -> Option<usize> {
+        SubsetSum::entries(&self.fewest, self.target_column())
+    }
+
+    pub fn most_entries(&self) -> Option<usize> {
+        SubsetSum::entries(&self.most, self.target_column())
+    }
+
+    /// A subset with the fewest entries that sums to the target.
+    pub fn fewest(&self) -> Option<Subset> {
+        self.reconstruct(&self.fewest)
+    }
+
+    /// A subset with the most entries that sums to the target.
+    pub fn most(&self) -> Option<Subset> {
+        self.reconstruct(&self.most)
+    }
+
+    fn reconstruct(&self, table: &[Vec<u32>]) -> Option<Subset> {
+        let mut column = self.target_column()?;
+        if table[self.values.len()][column] == UNREACHABLE {
+            return None;
+        }
+
+        let mut indices = Vec::new();
+        for idx in (0..self.values.len()).rev() {
+            // either the count is reached without the entry, or the entry is chosen
+            if table[idx][column] != table[idx + 1][column] {
+                indices.push(idx);
+                column = (column as i128 - self.values[idx] as i128) as usize;
+            }
+        }
+        indices.reverse();
+
+        Some(Subset {
+            values: indices.iter().map(|&idx| self.values[idx]).collect(),
+            indices,
+        })
+    }
+}
+
+pub fn find_first_sum_to(values: &[i64], target: i64) -> Option<(i64, i64)> {
+    KSum::new(2, target)
+        .first(values)
+        .map(|summands| (summands[0], summands[1]))
+}
+
+/// Finds two summands of `target` in `values`, which must be sorted.
+pub fn find_summands_to_target(values: &[i64], target: i64) -> Option<(i64, i64)> {
+    let mut found = None;
+    two_pointer(values, target as i128, &mut Vec::new(), &mut |summands| {
+        found = Some((summands[0], summands[1]));
+        false
+    });
+    found
+}
+
+pub fn find_3_summands_to_target(values: &[i64], target: i64) -> Option<(i64, i64, i64)> {
+    KSum::new(3, target)
+        .first(values)
+        .map(|summands| (summands[0], summands[1], summands[2]))
+}
+
+fn puzzle_input() -> Vec<i64> {
+    puzzle_input::read_input("day01")
         .split_whitespace()
         .map(|s| s.parse::<i64>())
         .map(|res| res.unwrap())
@@ -60,7 +459,11 @@ fn puzzle_input() -> Vec<i64> {
 
 #[cfg(test)]
 mod tests {
-    use crate::day01::{find_3_summands_to_target, find_first_sum_to};
+    use crate::day01::{
+        find_3_summands_to_target, find_first_sum_to, find_summands_to_target, KSum, Strategy,
+        SubsetSum, SubsetSumError, MAX_TABLE_CELLS,
+    };
+    use crate::util::puzzle_input;
 
     #[test]
     fn values_1_2_3_and_target_2_gives_0_0() {
@@ -85,4 +488,125 @@ mod tests {
         let values = [1, 100, 213, 14, 121, 13, 11223, 5];
         assert_eq!(find_3_summands_to_target(&values, 20), Some((1, 5, 14)));
     }
+
+    #[test]
+    fn tiny_inputs_give_none() {
+        assert_eq!(find_first_sum_to(&[], 2020), None);
+        assert_eq!(find_first_sum_to(&[2020], 2020), None);
+        assert_eq!(find_summands_to_target(&[], 2020), None);
+        assert_eq!(find_3_summands_to_target(&[1000, 1020], 2020), None);
+        assert_eq!(KSum::new(4, 0).first(&[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn duplicate_values() {
+        assert_eq!(find_first_sum_to(&[1010, 1010], 2020), Some((1010, 1010)));
+        assert_eq!(find_first_sum_to(&[1010, 5], 2020), None);
+
+        let values = [1, 1, 1, 2, 2, 3];
+        assert_eq!(
+            KSum::new(3, 5).all(&values),
+            vec![vec![1, 1, 3], vec![1, 2, 2]]
+        );
+        assert_eq!(KSum::new(2, 2).count(&values), 1);
+    }
+
+    #[test]
+    fn all_and_count_of_k_sums() {
+        let values = [-3, -1, 0, 2, 4, 5, 7];
+        assert_eq!(
+            KSum::new(4, 6).all(&values),
+            vec![vec![-3, 0, 2, 7], vec![-3, 0, 4, 5], vec![-1, 0, 2, 5]]
+        );
+        assert_eq!(KSum::new(3, 6).count(&values), 5);
+        assert_eq!(KSum::new(0, 0).all(&values), vec![Vec::<i64>::new()]);
+    }
+
+    #[test]
+    fn strategies_agree() {
+        let values: Vec<i64> = (0..60).map(|i| (i * 37) % 41 - 20).collect();
+        for k in 1..=4 {
+            for target in -10..10 {
+                let two_pointer = KSum::new(k, target).with_strategy(Strategy::TwoPointer);
+                let hash = KSum::new(k, target).with_strategy(Strategy::Hash);
+                assert_eq!(two_pointer.all(&values), hash.all(&values));
+            }
+        }
+    }
+
+    #[test]
+    fn sums_do_not_overflow() {
+        let values = [i64::MAX, i64::MAX, -1, i64::MIN];
+        assert_eq!(
+            KSum::new(2, -1).all(&values),
+            vec![vec![i64::MIN, i64::MAX]]
+        );
+        assert_eq!(KSum::new(3, i64::MAX - 1).count(&values), 1);
+    }
+
+    #[test]
+    fn example_report() {
+        let values: Vec<i64> = puzzle_input::read_example("day01")
+            .split_whitespace()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(KSum::new(2, 2020).first(&values), Some(vec![299, 1721]));
+        assert_eq!(KSum::new(3, 2020).first(&values), Some(vec![366, 675, 979]));
+    }
+
+    #[test]
+    fn subset_sum_of_example_report() {
+        let values = [1721, 979, 366, 299, 675, 1456];
+        let subsets = SubsetSum::new(&values, 2020).unwrap();
+
+        assert!(subsets.is_reachable());
+        assert_eq!(subsets.fewest_entries(), Some(2));
+        assert_eq!(subsets.most_entries(), Some(3));
+
+        let fewest = subsets.fewest().unwrap();
+        assert_eq!(fewest.indices(), &[0, 3]);
+        assert_eq!(fewest.values(), &[1721, 299]);
+        assert_eq!(fewest.product().to_string(), "514579");
+
+        let most = subsets.most().unwrap();
+        assert_eq!(most.values(), &[979, 366, 675]);
+        assert_eq!(most.product().to_string(), "241861950");
+    }
+
+    #[test]
+    fn unreachable_targets() {
+        let subsets = SubsetSum::new(&[3, 5, 7], 4).unwrap();
+        assert!(!subsets.is_reachable());
+        assert_eq!(subsets.fewest(), None);
+        assert_eq!(subsets.most_entries(), None);
+
+        assert!(!SubsetSum::new(&[3, 5, 7], 100).unwrap().is_reachable());
+        assert!(!SubsetSum::new(&[], 1).unwrap().is_reachable());
+    }
+
+    #[test]
+    fn empty_subset_sums_to_zero() {
+        let subsets = SubsetSum::new(&[4, -4, 2], 0).unwrap();
+        assert_eq!(subsets.fewest().unwrap().len(), 0);
+        assert_eq!(subsets.most().unwrap().values(), &[4, -4]);
+    }
+
+    #[test]
+    fn negative_entries() {
+        let subsets = SubsetSum::new(&[-5, 8, -2, 3, 1], -4).unwrap();
+        assert_eq!(subsets.fewest().unwrap().values(), &[-5, 1]);
+        assert_eq!(subsets.most().unwrap().values(), &[-5, -2, 3]);
+
+        let subsets = SubsetSum::new(&[-1, -2, -3], -5).unwrap();
+        assert_eq!(subsets.fewest().unwrap().values(), &[-2, -3]);
+    }
+
+    #[test]
+    fn table_size_is_limited() {
+        let values = [i64::MAX / 2, -1];
+        match SubsetSum::new(&values, 0) {
+            Err(SubsetSumError::TooLarge { cells }) => assert!(cells > MAX_TABLE_CELLS as u128),
+            _ => panic!("expected the table to be too large"),
+        }
+    }
 }
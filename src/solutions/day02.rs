@@ -1,128 +1,868 @@
+use crate::util::parse::{
+    any_char, literal, map, pair, parse_all, preceded, take_while1, terminated, unsigned, ws,
+    ParseError, Parser,
+};
+use crate::util::pattern::{Pattern, PatternError};
 use crate::util::puzzle_input;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
 
+/// The rule sets to audit the database under, one per part, separated by `|`,
+/// e.g. `count|xor;min-length:8`. See `RuleSet` for the syntax of each.
+pub const POLICIES_VAR: &str = "AOC_DAY02_POLICIES";
+/// The rule sets of the puzzle, used if `AOC_DAY02_POLICIES` is not set.
+pub const DEFAULT_POLICIES: &str = "count|xor";
+
+/// The rule on a line of the password database, e.g. `1-3 a`.
+/// How the letter and the two numbers are used is up to the `Policy`.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct PasswordChecker {
     c: char,
     range: (usize, usize),
 }
 
 impl PasswordChecker {
-    fn new(config: &str) -> PasswordChecker {
-        let conf = to_configuration(config);
+    pub fn new(config: &str) -> Result<PasswordChecker, ParseError> {
+        let conf = to_configuration(config)?;
+        Ok(PasswordChecker::from_configuration(conf))
+    }
+
+    fn from_configuration(conf: (char, usize, usize)) -> PasswordChecker {
         PasswordChecker {
             c: conf.0,
             range: (conf.1, conf.2),
         }
     }
 
-    fn check(&self, password: &str) -> bool {
-        let chars = password.chars().filter(|c| self.c == *c).count();
+    pub fn letter(&self) -> char {
+        self.c
+    }
+
+    pub fn range(&self) -> (usize, usize) {
+        self.range
+    }
 
-        self.range.0 <= chars && chars <= self.range.1
+    fn check(&self, password: &str) -> bool {
+        CountInRange.is_valid(self, password)
     }
 
     fn check_at_positions(&self, password: &str) -> bool {
-        let chars: Vec<char> = password.chars().collect();
+        Positions(PositionMode::ExactlyOne).is_valid(self, password)
+    }
+}
+
+/// A rule passwords must follow. Policies may use the rule on the line
+/// of the password, or ignore it and check the password on its own.
+pub trait Policy {
+    /// A short description of the policy, e.g. for reports.
+    fn describe(&self) -> String;
+
+    /// Checks the password, giving the reason it is invalid.
+    fn check(&self, rule: &PasswordChecker, password: &str) -> Result<(), Reason>;
+
+    fn is_valid(&self, rule: &PasswordChecker, password: &str) -> bool {
+        self.check(rule, password).is_ok()
+    }
+}
+
+/// Why a line of the password database fails an audit.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Reason {
+    TooFew {
+        count: usize,
+        min: usize,
+    },
+    TooMany {
+        count: usize,
+        max: usize,
+    },
+    BothPositions,
+    NoPosition,
+    OnlyOnePosition,
+    /// A position, counting from 1, that is not in the password.
+    OutOfRange {
+        position: usize,
+        length: usize,
+    },
+    ForbiddenChar(char),
+    TooShort {
+        length: usize,
+        min: usize,
+    },
+    NoMatch,
+    Malformed(ParseError),
+    EmptyLine,
+}
+
+impl Reason {
+    /// The kind of failure, without the details of the line.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Reason::TooFew { .. } => "count too low",
+            Reason::TooMany { .. } => "count too high",
+            Reason::BothPositions => "both positions match",
+            Reason::NoPosition => "no position matches",
+            Reason::OnlyOnePosition => "only one position matches",
+            Reason::OutOfRange { .. } => "index out of range",
+            Reason::ForbiddenChar(_) => "forbidden character",
+            Reason::TooShort { .. } => "too short",
+            Reason::NoMatch => "pattern does not match",
+            Reason::Malformed(_) => "malformed line",
+            Reason::EmptyLine => "empty line",
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::TooFew { count, min } => {
+                write!(f, "{}, {} is less than {}", self.kind(), count, min)
+            }
+            Reason::TooMany { count, max } => {
+                write!(f, "{}, {} is more than {}", self.kind(), count, max)
+            }
+            Reason::OutOfRange { position, length } => write!(
+                f,
+                "{}, position {} of {} characters",
+                self.kind(),
+                position,
+                length
+            ),
+            Reason::ForbiddenChar(c) => write!(f, "{} {:?}", self.kind(), c),
+            Reason::TooShort { length, min } => {
+                write!(f, "{}, {} is less than {}", self.kind(), length, min)
+            }
+            Reason::Malformed(error) => write!(f, "{}, {}", self.kind(), error),
+            _ => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+/// The letter occurs a number of times within the range, both inclusive.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct CountInRange;
+
+impl Policy for CountInRange {
+    fn describe(&self) -> String {
+        "count".to_string()
+    }
 
-        chars[self.range.0 - 1] == self.c && chars[self.range.1 - 1] != self.c
-            || chars[self.range.0 - 1] != self.c && chars[self.range.1 - 1] == self.c
+    fn check(&self, rule: &PasswordChecker, password: &str) -> Result<(), Reason> {
+        let count = password.chars().filter(|&c| c == rule.c).count();
+        let (min, max) = rule.range;
+        if count < min {
+            Err(Reason::TooFew { count, min })
+        } else if count > max {
+            Err(Reason::TooMany { count, max })
+        } else {
+            Ok(())
+        }
     }
 }
 
-pub fn to_configuration(config: &str) -> (char, usize, usize) {
-    let parts: Vec<&str> = config.split_ascii_whitespace().collect();
+/// How many of the two positions must hold the letter.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum PositionMode {
+    ExactlyOne,
+    Both,
+    Any,
+}
+
+/// The letter is at the two positions of the range, counting from 1,
+/// as given by the mode. A position outside the password never holds the letter.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Positions(pub PositionMode);
+
+impl Policy for Positions {
+    fn describe(&self) -> String {
+        match self.0 {
+            PositionMode::ExactlyOne => "xor",
+            PositionMode::Both => "and",
+            PositionMode::Any => "or",
+        }
+        .to_string()
+    }
+
+    fn check(&self, rule: &PasswordChecker, password: &str) -> Result<(), Reason> {
+        let length = password.chars().count();
+        let holds_letter = |position: usize| {
+            position
+                .checked_sub(1)
+                .and_then(|idx| password.chars().nth(idx))
+                == Some(rule.c)
+        };
+        let (first, second) = (holds_letter(rule.range.0), holds_letter(rule.range.1));
 
-    let range: Vec<usize> = parts[0]
-        .split("-")
-        .map(|c| c.parse::<usize>())
-        .map(|end| end.unwrap())
-        .collect();
+        let failure = match (self.0, first, second) {
+            (PositionMode::ExactlyOne, true, true) => Reason::BothPositions,
+            (PositionMode::ExactlyOne, false, false) | (PositionMode::Any, false, false) => {
+                Reason::NoPosition
+            }
+            (PositionMode::Both, false, false) => Reason::NoPosition,
+            (PositionMode::Both, true, false) | (PositionMode::Both, false, true) => {
+                Reason::OnlyOnePosition
+            }
+            _ => return Ok(()),
+        };
 
-    (parts[1].chars().next().unwrap(), range[0], range[1])
+        // a position that was missed because it is not in the password is the better reason
+        let missed = [(rule.range.0, first), (rule.range.1, second)];
+        match missed
+            .iter()
+            .find(|&&(position, holds)| !holds && (position == 0 || position > length))
+        {
+            Some(&(position, _)) => Err(Reason::OutOfRange { position, length }),
+            None => Err(failure),
+        }
+    }
 }
 
-pub fn check_password(password: &str) -> bool {
-    let password_split: Vec<&str> = password.split(":").collect();
+/// None of the characters occur in the password.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ForbiddenChars(pub String);
 
-    let checker = PasswordChecker::new(password_split[0]);
+impl Policy for ForbiddenChars {
+    fn describe(&self) -> String {
+        format!("forbid:{}", self.0)
+    }
 
-    checker.check(password_split[1].trim())
+    fn check(&self, _rule: &PasswordChecker, password: &str) -> Result<(), Reason> {
+        match password.chars().find(|&c| self.0.contains(c)) {
+            Some(c) => Err(Reason::ForbiddenChar(c)),
+            None => Ok(()),
+        }
+    }
 }
 
-pub fn test_with_position(password: &str) -> bool {
-    let password_split: Vec<&str> = password.split(":").collect();
+/// The password has at least this many characters.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct MinLength(pub usize);
+
+impl Policy for MinLength {
+    fn describe(&self) -> String {
+        format!("min-length:{}", self.0)
+    }
 
-    let checker = PasswordChecker::new(password_split[0]);
+    fn check(&self, _rule: &PasswordChecker, password: &str) -> Result<(), Reason> {
+        let length = password.chars().count();
+        if length < self.0 {
+            Err(Reason::TooShort {
+                length,
+                min: self.0,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
 
-    checker.check_at_positions(password_split[1].trim())
+/// The password matches the pattern, see `Pattern` for the syntax.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Matches {
+    source: String,
+    pattern: Pattern,
 }
 
-pub fn print_solution() {
-    let passwords = puzzle_input::read_input("day02");
-    let n_valid = passwords
-        .split("\n")
-        .filter(|password| check_password(password))
-        .count();
+impl Matches {
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        Ok(Matches {
+            source: pattern.to_string(),
+            pattern: pattern.parse()?,
+        })
+    }
+}
+
+impl Policy for Matches {
+    fn describe(&self) -> String {
+        format!("pattern:{}", self.source)
+    }
+
+    fn check(&self, _rule: &PasswordChecker, password: &str) -> Result<(), Reason> {
+        if self.pattern.is_match(password) {
+            Ok(())
+        } else {
+            Err(Reason::NoMatch)
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum PolicyError {
+    Unknown(String),
+    MissingArgument(String),
+    BadArgument(String),
+    Pattern(PatternError),
+}
+
+/// The policies a password database is audited under.
+/// A password is valid if it is valid under every policy.
+pub struct RuleSet {
+    policies: Vec<Box<dyn Policy>>,
+}
+
+impl RuleSet {
+    pub fn new(policies: Vec<Box<dyn Policy>>) -> Self {
+        RuleSet { policies }
+    }
+
+    pub fn policies(&self) -> &[Box<dyn Policy>] {
+        &self.policies
+    }
+
+    /// The policies as given by `Policy::describe`, separated by `;`.
+    pub fn describe(&self) -> String {
+        let policies: Vec<String> = self.policies.iter().map(|p| p.describe()).collect();
+        policies.join(";")
+    }
+
+    pub fn is_valid(&self, rule: &PasswordChecker, password: &str) -> bool {
+        self.policies.iter().all(|p| p.is_valid(rule, password))
+    }
+
+    /// Audits every line of the database text. Unlike `parse_lines`,
+    /// malformed and empty lines are reported, not rejected. A line ending
+    /// in `\r\n` is audited without the `\r`, which is kept as a note.
+    pub fn audit<'a>(&self, database: &'a str) -> Vec<Audit<'a>> {
+        let mut lines: Vec<&str> = database.split('\n').collect();
+        if lines.last() == Some(&"") {
+            lines.pop();
+        }
+
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| self.audit_line(idx + 1, line))
+            .collect()
+    }
+
+    fn audit_line<'a>(&self, line_number: usize, line: &'a str) -> Audit<'a> {
+        let mut audit = Audit {
+            line: line_number,
+            password: None,
+            policy: self.describe(),
+            outcome: Ok(()),
+            carriage_return: line.ends_with('\r'),
+        };
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.trim().is_empty() {
+            audit.outcome = Err(Reason::EmptyLine);
+            return audit;
+        }
+
+        let (rule, password) = match parse_all(password_line(), line, line_number) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                audit.outcome = Err(Reason::Malformed(error));
+                return audit;
+            }
+        };
+        audit.password = Some(password);
+        for policy in &self.policies {
+            if let Err(reason) = policy.check(&rule, password) {
+                audit.policy = policy.describe();
+                audit.outcome = Err(reason);
+                break;
+            }
+        }
+        audit
+    }
+
+    /// The number of lines of the database with a valid password.
+    pub fn count_valid(&self, database: &[(PasswordChecker, &str)]) -> usize {
+        database
+            .iter()
+            .filter(|(rule, password)| self.is_valid(rule, password))
+            .count()
+    }
+}
+
+/// The result of auditing a line of the password database.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Audit<'a> {
+    line: usize,
+    password: Option<&'a str>,
+    policy: String,
+    outcome: Result<(), Reason>,
+    carriage_return: bool,
+}
+
+impl<'a> Audit<'a> {
+    /// The line number, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The password, if the line could be parsed.
+    pub fn password(&self) -> Option<&'a str> {
+        self.password
+    }
+
+    /// The policy the password failed, or the entire rule set if it passed.
+    pub fn policy(&self) -> &str {
+        &self.policy
+    }
+
+    pub fn reason(&self) -> Option<&Reason> {
+        self.outcome.as_ref().err()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.outcome.is_ok()
+    }
+
+    /// Whether the line ended in `\r\n`. This does not make it fail.
+    pub fn has_carriage_return(&self) -> bool {
+        self.carriage_return
+    }
+}
+
+impl fmt::Display for Audit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(password) = self.password {
+            write!(f, " {:?}", password)?;
+        }
+        match &self.outcome {
+            Ok(()) => write!(f, ": valid under {}", self.policy),
+            Err(reason) => write!(f, ": fails {}, {}", self.policy, reason),
+        }
+    }
+}
+
+/// The failures of an audit, grouped by the kind of reason.
+#[derive(Eq, PartialEq, Debug)]
+pub struct Summary {
+    n_lines: usize,
+    n_valid: usize,
+    n_carriage_return: usize,
+    failures: BTreeMap<&'static str, Vec<usize>>,
+}
+
+impl Summary {
+    pub fn new(audits: &[Audit]) -> Self {
+        let mut failures: BTreeMap<&'static str, Vec<usize>> = BTreeMap::new();
+        for audit in audits {
+            if let Some(reason) = audit.reason() {
+                failures.entry(reason.kind()).or_default().push(audit.line);
+            }
+        }
+
+        Summary {
+            n_lines: audits.len(),
+            n_valid: audits.iter().filter(|a| a.is_valid()).count(),
+            n_carriage_return: audits.iter().filter(|a| a.has_carriage_return()).count(),
+            failures,
+        }
+    }
+
+    pub fn n_valid(&self) -> usize {
+        self.n_valid
+    }
+
+    /// The line numbers of the failures, by the kind of reason.
+    pub fn failures(&self) -> &BTreeMap<&'static str, Vec<usize>> {
+        &self.failures
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} of {} lines valid", self.n_valid, self.n_lines)?;
+        for (kind, lines) in &self.failures {
+            let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+            writeln!(f, "{}: {} (lines {})", kind, lines.len(), lines.join(", "))?;
+        }
+        if self.n_carriage_return > 0 {
+            writeln!(f, "note: {} lines end in \\r\\n", self.n_carriage_return)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a single policy, as given by `Policy::describe`:
+/// `count`, `xor`, `and`, `or`, `forbid:<chars>`, `min-length:<n>` or `pattern:<pattern>`.
+pub fn parse_policy(spec: &str) -> Result<Box<dyn Policy>, PolicyError> {
+    let (name, argument) = match spec.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (spec, None),
+    };
+    let argument = || argument.ok_or_else(|| PolicyError::MissingArgument(name.to_string()));
+
+    let policy: Box<dyn Policy> = match name {
+        "count" => Box::new(CountInRange),
+        "xor" => Box::new(Positions(PositionMode::ExactlyOne)),
+        "and" => Box::new(Positions(PositionMode::Both)),
+        "or" => Box::new(Positions(PositionMode::Any)),
+        "forbid" => Box::new(ForbiddenChars(argument()?.to_string())),
+        "min-length" => Box::new(MinLength(
+            argument()?
+                .parse()
+                .map_err(|_| PolicyError::BadArgument(spec.to_string()))?,
+        )),
+        "pattern" => Box::new(Matches::new(argument()?).map_err(PolicyError::Pattern)?),
+        _ => return Err(PolicyError::Unknown(name.to_string())),
+    };
+    Ok(policy)
+}
+
+/// Policies separated by `;`, e.g. `count;min-length:8`.
+/// A pattern can therefore not contain `;`.
+impl FromStr for RuleSet {
+    type Err = PolicyError;
+
+    fn from_str(specs: &str) -> Result<Self, Self::Err> {
+        let policies = specs
+            .split(';')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .map(parse_policy)
+            .collect::<Result<_, _>>()?;
+        Ok(RuleSet::new(policies))
+    }
+}
+
+/// `<lower>-<upper> <char>`, e.g. `1-3 a`
+fn configuration<'a>() -> impl Parser<'a, (char, usize, usize)> {
+    map(
+        pair(
+            pair(unsigned(), preceded(literal("-"), unsigned())),
+            preceded(ws(), any_char()),
+        ),
+        |((lower, upper), c)| (c, lower, upper),
+    )
+}
+
+/// `<configuration>: <password>`, e.g. `1-3 a: abcde`
+fn password_line<'a>() -> impl Parser<'a, (PasswordChecker, &'a str)> {
+    pair(
+        map(
+            terminated(configuration(), pair(literal(":"), ws())),
+            PasswordChecker::from_configuration,
+        ),
+        terminated(take_while1(|c| !c.is_whitespace(), "a password"), ws()),
+    )
+}
+
+pub fn to_configuration(config: &str) -> Result<(char, usize, usize), ParseError> {
+    parse_all(configuration(), config.trim(), 1)
+}
+
+pub fn check_password(password: &str) -> Result<bool, ParseError> {
+    let (checker, password) = parse_all(password_line(), password, 1)?;
+
+    Ok(checker.check(password))
+}
+
+pub fn test_with_position(password: &str) -> Result<bool, ParseError> {
+    let (checker, password) = parse_all(password_line(), password, 1)?;
 
-    println!("Day 02 Solution Part 1: {}", n_valid);
+    Ok(checker.check_at_positions(password))
+}
+
+/// Reads rule sets separated by `|`. A pattern can therefore not contain `|`.
+pub fn parse_rule_sets(specs: &str) -> Result<Vec<RuleSet>, PolicyError> {
+    specs.split('|').map(str::parse).collect()
+}
 
-    let n_valid = passwords
-        .split("\n")
-        .filter(|password| test_with_position(password))
-        .count();
+pub fn print_solution() {
+    let input = puzzle_input::read_input("day02");
+    let specs = env::var(POLICIES_VAR).unwrap_or_else(|_| DEFAULT_POLICIES.to_string());
+    let rule_sets =
+        parse_rule_sets(&specs).unwrap_or_else(|e| panic!("bad {}: {:?}", POLICIES_VAR, e));
 
-    println!("Day 02 Solution Part 2: {}", n_valid)
+    for (part, rules) in rule_sets.iter().enumerate() {
+        let audits = rules.audit(&input);
+        println!(
+            "Day 02 Solution Part {}: {}",
+            part + 1,
+            Summary::new(&audits).n_valid()
+        );
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::day02::{check_password, test_with_position, to_configuration, PasswordChecker};
+    use crate::day02::{
+        check_password, parse_policy, parse_rule_sets, password_line, test_with_position,
+        to_configuration, PasswordChecker, PolicyError, Reason, RuleSet, Summary, DEFAULT_POLICIES,
+    };
+    use crate::util::parse::parse_lines;
+    use crate::util::pattern::PatternError;
 
     #[test]
     fn the_config_a_1_2_gives_a_1_2() {
-        assert_eq!(to_configuration("1-2 a"), ('a', 1, 2));
+        assert_eq!(to_configuration("1-2 a"), Ok(('a', 1, 2)));
     }
 
     #[test]
     fn the_config_c_1_2_gives_c_1_2() {
-        assert_eq!(to_configuration("1-2 c"), ('c', 1, 2));
+        assert_eq!(to_configuration("1-2 c"), Ok(('c', 1, 2)));
     }
 
     #[test]
     fn the_config_d_11_20_gives_d_11_20() {
-        assert_eq!(to_configuration("11-20 d"), ('d', 11, 20));
+        assert_eq!(to_configuration("11-20 d"), Ok(('d', 11, 20)));
+    }
+
+    #[test]
+    fn malformed_config_reports_position() {
+        let error = to_configuration("1:2 d").unwrap_err();
+        assert_eq!(error.column(), 2);
+        assert_eq!(error.expected(), "\"-\"");
     }
 
     #[test]
     fn test_aad_matches_0_1_d() {
-        let checker = PasswordChecker::new("0-1 d");
+        let checker = PasswordChecker::new("0-1 d").unwrap();
         assert_eq!(checker.check("aad"), true);
     }
 
     #[test]
     fn test_ccdd_does_not_match_3_4_c() {
-        let checker = PasswordChecker::new("3-4 c");
+        let checker = PasswordChecker::new("3-4 c").unwrap();
         assert_eq!(checker.check("ccdd"), false);
     }
 
     #[test]
     fn test_full_config_of_password() {
-        assert!(check_password("3-4 c: cccdd"));
+        assert_eq!(check_password("3-4 c: cccdd"), Ok(true));
+    }
+
+    #[test]
+    fn missing_colon_is_an_error() {
+        let error = check_password("3-4 c cccdd").unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 6));
+    }
+
+    #[test]
+    fn empty_password_is_an_error() {
+        let error = check_password("1-3 a: ").unwrap_err();
+        assert_eq!((error.column(), error.expected()), (8, "a password"));
     }
 
     #[test]
     fn test_config_with_position_with_invalid_check() {
-        let checker = PasswordChecker::new("1-2 c");
+        let checker = PasswordChecker::new("1-2 c").unwrap();
         assert_eq!(checker.check_at_positions("ccc"), false);
     }
 
     #[test]
     fn test_config_with_position_check_with_valid_password() {
-        let checker = PasswordChecker::new("1-4 b");
+        let checker = PasswordChecker::new("1-4 b").unwrap();
         assert_eq!(checker.check_at_positions("bbbab"), true);
     }
 
     #[test]
     fn part2_example1() {
-        assert_eq!(test_with_position("1-3 a: abcde"), true);
+        assert_eq!(test_with_position("1-3 a: abcde"), Ok(true));
+    }
+
+    #[test]
+    fn positions_outside_the_password_do_not_panic() {
+        let checker = PasswordChecker::new("0-9 a").unwrap();
+        assert!(!checker.check_at_positions("abc"));
+
+        let checker = PasswordChecker::new("1-9 a").unwrap();
+        assert!(checker.check_at_positions("abc"));
+    }
+
+    #[test]
+    fn position_modes() {
+        let rule = PasswordChecker::new("1-3 a").unwrap();
+        let valid =
+            |spec: &str, password: &str| parse_policy(spec).unwrap().is_valid(&rule, password);
+
+        assert!(valid("xor", "abc"));
+        assert!(!valid("xor", "aba"));
+        assert!(valid("and", "aba"));
+        assert!(!valid("and", "abc"));
+        assert!(valid("or", "bba"));
+        assert!(!valid("or", "bbb"));
+    }
+
+    #[test]
+    fn policies_on_the_password_alone() {
+        let rule = PasswordChecker::new("1-3 a").unwrap();
+        let valid =
+            |spec: &str, password: &str| parse_policy(spec).unwrap().is_valid(&rule, password);
+
+        assert!(valid("forbid:xyz", "abc"));
+        assert!(!valid("forbid:xyz", "abcz"));
+        assert!(valid("min-length:3", "abc"));
+        assert!(!valid("min-length:4", "abc"));
+        assert!(valid("pattern:^[a-c]+$", "abc"));
+        assert!(!valid("pattern:^[a-c]+$", "abcd"));
+    }
+
+    #[test]
+    fn malformed_policies() {
+        assert_eq!(
+            parse_policy("length").err(),
+            Some(PolicyError::Unknown("length".to_string()))
+        );
+        assert_eq!(
+            parse_policy("forbid").err(),
+            Some(PolicyError::MissingArgument("forbid".to_string()))
+        );
+        assert_eq!(
+            parse_policy("min-length:x").err(),
+            Some(PolicyError::BadArgument("min-length:x".to_string()))
+        );
+        assert_eq!(
+            parse_policy("pattern:[a").err(),
+            Some(PolicyError::Pattern(PatternError::UnclosedClass(0)))
+        );
+    }
+
+    #[test]
+    fn audit_database_under_rule_sets() {
+        let input = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc";
+        let database = parse_lines(password_line(), input).unwrap();
+
+        let count = |specs: &str| specs.parse::<RuleSet>().unwrap().count_valid(&database);
+        assert_eq!(count("count"), 2);
+        assert_eq!(count("xor"), 1);
+        assert_eq!(count("count; min-length:6"), 1);
+        assert_eq!(count("count;forbid:e"), 1);
+        assert_eq!(count(""), 3);
+        assert_eq!(
+            "count;xor".parse::<RuleSet>().unwrap().policies()[1].describe(),
+            "xor"
+        );
+    }
+
+    #[test]
+    fn reasons_of_failed_policies() {
+        let rule = PasswordChecker::new("2-3 a").unwrap();
+        let check = |spec: &str, password: &str| parse_policy(spec).unwrap().check(&rule, password);
+
+        assert_eq!(
+            check("count", "abc"),
+            Err(Reason::TooFew { count: 1, min: 2 })
+        );
+        assert_eq!(
+            check("count", "aaaa"),
+            Err(Reason::TooMany { count: 4, max: 3 })
+        );
+        assert_eq!(check("xor", "aaa"), Err(Reason::BothPositions));
+        assert_eq!(check("xor", "bbb"), Err(Reason::NoPosition));
+        assert_eq!(check("and", "bab"), Err(Reason::OnlyOnePosition));
+        assert_eq!(
+            check("or", "b"),
+            Err(Reason::OutOfRange {
+                position: 2,
+                length: 1
+            })
+        );
+        assert_eq!(check("forbid:xa", "bab"), Err(Reason::ForbiddenChar('a')));
+        assert_eq!(
+            check("min-length:4", "bab"),
+            Err(Reason::TooShort { length: 3, min: 4 })
+        );
+        assert_eq!(check("pattern:^b", "ab"), Err(Reason::NoMatch));
+    }
+
+    #[test]
+    fn audit_reports_every_line() {
+        let input = "1-3 a: abcde\n1-3 b: cdefg\n\n2-9 c: ccccccccc\r\n1-3 a abc\n";
+        let audits = "count".parse::<RuleSet>().unwrap().audit(input);
+
+        assert_eq!(audits.len(), 5);
+        assert!(audits[0].is_valid());
+        assert_eq!(audits[1].password(), Some("cdefg"));
+        assert_eq!(audits[1].policy(), "count");
+        assert_eq!(
+            audits[1].reason(),
+            Some(&Reason::TooFew { count: 0, min: 1 })
+        );
+        assert_eq!(audits[2].reason(), Some(&Reason::EmptyLine));
+        assert!(audits[3].is_valid());
+        assert_eq!(audits[3].password(), Some("ccccccccc"));
+        assert!(audits[3].has_carriage_return());
+        assert!(!audits[0].has_carriage_return());
+        match audits[4].reason() {
+            Some(Reason::Malformed(error)) => assert_eq!((error.line(), error.column()), (5, 6)),
+            reason => panic!("expected a malformed line, got {:?}", reason),
+        }
+        assert_eq!(
+            audits[1].to_string(),
+            "line 2 \"cdefg\": fails count, count too low, 0 is less than 1"
+        );
+    }
+
+    #[test]
+    fn audit_names_the_failed_policy() {
+        let audits = "count;min-length:6;forbid:e"
+            .parse::<RuleSet>()
+            .unwrap()
+            .audit("1-3 a: abcde\n1-3 a: abcdfg\n1-3 a: abcdefg");
+
+        assert_eq!(audits[0].policy(), "min-length:6");
+        assert!(audits[1].is_valid());
+        assert_eq!(audits[1].policy(), "count;min-length:6;forbid:e");
+        assert_eq!(audits[2].policy(), "forbid:e");
+    }
+
+    #[test]
+    fn summary_groups_failures_by_reason() {
+        let input = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\n\n1-1 z: zz";
+        let audits = "count".parse::<RuleSet>().unwrap().audit(input);
+        let summary = Summary::new(&audits);
+
+        assert_eq!(summary.n_valid(), 2);
+        assert_eq!(summary.failures()["count too low"], vec![2]);
+        assert_eq!(summary.failures()["count too high"], vec![5]);
+        assert_eq!(
+            summary.to_string(),
+            "2 of 5 lines valid\n\
+count too high: 1 (lines 5)\n\
+count too low: 1 (lines 2)\n\
+empty line: 1 (lines 4)\n"
+        );
+    }
+
+    #[test]
+    fn crlf_database_gives_the_same_counts() {
+        let lf = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\n";
+        let crlf = lf.replace('\n', "\r\n");
+
+        for specs in &["count", "xor"] {
+            let rules = specs.parse::<RuleSet>().unwrap();
+            let expected = Summary::new(&rules.audit(lf));
+            let summary = Summary::new(&rules.audit(&crlf));
+            assert_eq!(summary.n_valid(), expected.n_valid());
+            assert_eq!(summary.failures(), expected.failures());
+        }
+
+        let summary = Summary::new(&"count".parse::<RuleSet>().unwrap().audit(&crlf));
+        assert_eq!(
+            summary.to_string(),
+            "2 of 3 lines valid\n\
+count too low: 1 (lines 2)\n\
+note: 3 lines end in \\r\\n\n"
+        );
+    }
+
+    #[test]
+    fn rule_sets_per_part() {
+        let input = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc";
+        let n_valid = |specs: &str| -> Vec<usize> {
+            parse_rule_sets(specs)
+                .unwrap()
+                .iter()
+                .map(|rules| Summary::new(&rules.audit(input)).n_valid())
+                .collect()
+        };
+
+        assert_eq!(n_valid(DEFAULT_POLICIES), vec![2, 1]);
+        assert_eq!(n_valid("count;min-length:6|or|pattern:^c+$"), vec![1, 2, 1]);
+        assert_eq!(
+            parse_rule_sets("count|mystery").err(),
+            Some(PolicyError::Unknown("mystery".to_string()))
+        );
     }
 }
@@ -1,6 +1,8 @@
 use crate::day03::Error::UnknownTerrain;
 use crate::day03::Terrain::{Ground, Tree};
+use crate::util::point::Point2;
 use crate::util::puzzle_input;
+use std::ops::RangeInclusive;
 
 pub struct Map {
     map: Vec<Terrain>,
@@ -30,21 +32,64 @@ impl Map {
         self.map[(x % self.width) + self.width * y]
     }
 
+    /// The terrain at `(x, y)`, where any `x` wraps around the width of the map,
+    /// or `None` below the map.
+    pub fn terrain_at_wrapped(&self, x: i64, y: usize) -> Option<Terrain> {
+        if y >= self.height() {
+            return None;
+        }
+        Some(self.terrain_at(x.rem_euclid(self.width as i64) as usize, y))
+    }
+
     pub fn count_trees_on_route(&self, slope: &Slope) -> u64 {
-        let mut count = 0;
-        let mut x = 0;
+        self.route(slope)
+            .iter()
+            .filter(|p| self.terrain_at(p.x as usize, p.y as usize) == Tree)
+            .count() as u64
+    }
+
+    /// The coordinates visited from the top left corner until the bottom
+    /// of the map is passed. The `x` coordinates are wrapped into the map,
+    /// so the route can be drawn on it.
+    pub fn route(&self, slope: &Slope) -> Vec<Point2<i64>> {
+        if self.map.is_empty() || slope.down == 0 {
+            return Vec::new();
+        }
+        let mut x: i64 = 0;
+        let mut route = Vec::with_capacity(self.height() / slope.down + 1);
 
         for row in (0..self.height()).step_by(slope.down) {
-            if self.terrain_at(x, row) == Tree {
-                count += 1;
-            }
+            route.push(Point2::new(x.rem_euclid(self.width as i64), row as i64));
             x += slope.right;
         }
-        count
+        route
+    }
+
+    /// Draws the map with the route on it, as in the puzzle text:
+    /// `O` where the route crosses open ground and `X` where it hits a tree.
+    pub fn draw_route(&self, slope: &Slope) -> String {
+        let mut lines: Vec<Vec<char>> = (0..self.height())
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| match self.terrain_at(x, y) {
+                        Ground => '.',
+                        Tree => '#',
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for p in self.route(slope) {
+            let c = &mut lines[p.y as usize][p.x as usize];
+            *c = if *c == '#' { 'X' } else { 'O' };
+        }
+
+        let lines: Vec<String> = lines.into_iter().map(|l| l.into_iter().collect()).collect();
+        lines.join("\n")
     }
 
     pub fn height(&self) -> usize {
-        self.map.len() / self.width
+        self.map.len().checked_div(self.width).unwrap_or(0)
     }
 
     pub fn convert_line(str: &str) -> Result<Vec<Terrain>, Error> {
@@ -86,16 +131,66 @@ pub enum Terrain {
     Tree,
 }
 
-#[derive(Debug)]
+/// Moving `right` columns, which may be negative, for every `down` rows.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 pub struct Slope {
-    right: usize,
+    right: i64,
     down: usize,
 }
 
 impl Slope {
-    fn new(right: usize, down: usize) -> Slope {
+    /// A slope with `down` of 0 never reaches the bottom, and has an empty route.
+    pub fn new(right: i64, down: usize) -> Slope {
         Slope { right, down }
     }
+
+    pub fn right(&self) -> i64 {
+        self.right
+    }
+
+    pub fn down(&self) -> usize {
+        self.down
+    }
+}
+
+/// Evaluates every slope within the bounds on a map.
+pub struct SlopeExplorer<'a> {
+    map: &'a Map,
+    right: RangeInclusive<i64>,
+    down: RangeInclusive<usize>,
+}
+
+impl<'a> SlopeExplorer<'a> {
+    /// A `down` of 0 is skipped, since such a slope never reaches the bottom.
+    pub fn new(map: &'a Map, right: RangeInclusive<i64>, down: RangeInclusive<usize>) -> Self {
+        SlopeExplorer { map, right, down }
+    }
+
+    /// Every slope with the number of trees on its route, from the
+    /// fewest trees to the most. Ties are in the order of the slopes.
+    pub fn ranked(&self) -> Vec<(Slope, u64)> {
+        let mut ranked = Vec::new();
+        for down in self.down.clone().filter(|&down| down > 0) {
+            for right in self.right.clone() {
+                let slope = Slope::new(right, down);
+                ranked.push((slope, self.map.count_trees_on_route(&slope)));
+            }
+        }
+        ranked.sort_by_key(|&(slope, trees)| (trees, slope));
+        ranked
+    }
+
+    /// The slope with the fewest trees.
+    pub fn safest(&self) -> Option<(Slope, u64)> {
+        self.ranked().first().copied()
+    }
+
+    /// The slope with the most trees, the first in the order of the slopes on ties.
+    pub fn most_dangerous(&self) -> Option<(Slope, u64)> {
+        self.ranked()
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -107,7 +202,20 @@ pub enum Error {
 mod tests {
     use crate::day03::Error::UnknownTerrain;
     use crate::day03::Terrain::{Ground, Tree};
-    use crate::day03::{Map, Slope};
+    use crate::day03::{Map, Slope, SlopeExplorer};
+    use crate::util::point::Point2;
+
+    const EXAMPLE: &str = "..##.......\n\
+#...#...#..\n\
+.#....#..#.\n\
+..#.#...#.#\n\
+.#...##..#.\n\
+..#.##.....\n\
+.#.#.#....#\n\
+.#........#\n\
+#.##...#...\n\
+#...##....#\n\
+.#..#...#.#";
 
     #[test]
     pub fn configure_map() {
@@ -187,4 +295,69 @@ mod tests {
 
         assert_eq!(map.count_trees_on_route(&slope), 7);
     }
+
+    #[test]
+    fn route_coordinates_wrap_around() {
+        let map = Map::configure("....\n....\n....").unwrap();
+        assert_eq!(
+            map.route(&Slope::new(3, 1)),
+            vec![Point2::new(0, 0), Point2::new(3, 1), Point2::new(2, 2)]
+        );
+        assert_eq!(
+            map.route(&Slope::new(-1, 2)),
+            vec![Point2::new(0, 0), Point2::new(3, 2)]
+        );
+        assert_eq!(map.route(&Slope::new(1, 0)), vec![]);
+    }
+
+    #[test]
+    fn slopes_moving_left() {
+        let map = Map::configure(EXAMPLE).unwrap();
+        assert_eq!(map.terrain_at_wrapped(-1, 2), Some(map.terrain_at(10, 2)));
+        assert_eq!(map.terrain_at_wrapped(-12, 2), Some(map.terrain_at(10, 2)));
+        assert_eq!(map.terrain_at_wrapped(0, 11), None);
+        assert_eq!(map.count_trees_on_route(&Slope::new(-3, 1)), 3);
+    }
+
+    #[test]
+    fn empty_map_has_no_terrain() {
+        let map = Map::configure("").unwrap();
+        assert_eq!(map.height(), 0);
+        assert_eq!(map.terrain_at_wrapped(-1, 0), None);
+        assert_eq!(map.draw_route(&Slope::new(1, 1)), "");
+    }
+
+    #[test]
+    fn draw_route_on_example_map() {
+        let map = Map::configure("..#\n##.\n.#.").unwrap();
+        assert_eq!(map.draw_route(&Slope::new(1, 1)), "O.#\n#X.\n.#O");
+    }
+
+    #[test]
+    fn explore_slopes_of_example_map() {
+        let map = Map::configure(EXAMPLE).unwrap();
+        let explorer = SlopeExplorer::new(&map, 1..=7, 1..=2);
+        let ranked = explorer.ranked();
+
+        assert_eq!(ranked.len(), 14);
+        assert!(ranked.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(ranked.contains(&(Slope::new(3, 1), 7)));
+        assert!(ranked.contains(&(Slope::new(1, 2), 2)));
+
+        let safest = explorer.safest().unwrap();
+        let most_dangerous = explorer.most_dangerous().unwrap();
+        assert_eq!(safest.1, ranked[0].1);
+        assert_eq!(most_dangerous.1, ranked[13].1);
+        assert_eq!(
+            ranked.iter().find(|r| r.1 == most_dangerous.1).unwrap().0,
+            most_dangerous.0
+        );
+    }
+
+    #[test]
+    fn explorer_skips_slopes_going_nowhere() {
+        let map = Map::configure(EXAMPLE).unwrap();
+        assert_eq!(SlopeExplorer::new(&map, -1..=1, 0..=1).ranked().len(), 3);
+        assert_eq!(SlopeExplorer::new(&map, 0..=1, 0..=0).safest(), None);
+    }
 }
@@ -1,175 +1,714 @@
+use crate::util::pattern::Pattern;
 use crate::util::puzzle_input;
 use crate::util::GroupIterator;
-use std::collections::{HashMap, HashSet};
+use crate::util::{csv, json};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
-const VALID_EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+/// The rules of the puzzle, in the format read by `Schema`.
+pub const DEFAULT_SCHEMA: &str = "\
+# field  presence  check
+byr required int 1920..2002
+iyr required int 2010..2020
+eyr required int 2020..2030
+hgt required units 150..193 cm, 59..76 in
+hcl required pattern ^#[0-9a-fA-F]{6}$
+ecl required one-of amb blu brn gry grn hzl oth
+pid required pattern ^[+0-9][0-9]{8}$
+cid optional any
+";
 
+/// The fields of a passport, with the last value of a duplicate key.
+/// Tokens without a `:` are skipped, see `Passport` to find them.
 pub fn process_line(line: &str) -> HashMap<String, String> {
-    let mut map = HashMap::new();
+    Passport::parse(line).to_map()
+}
+
+/// The `key:value` tokens of a passport, in the order they are written.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Passport {
+    fields: Vec<(String, String)>,
+    malformed: Vec<String>,
+}
+
+impl Passport {
+    /// A passport with the fields, without any malformed tokens.
+    pub fn from_fields(fields: Vec<(String, String)>) -> Self {
+        Passport {
+            fields,
+            malformed: Vec::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut passport = Passport::default();
+        for token in text.split_ascii_whitespace() {
+            match token.split_once(':') {
+                Some((key, value)) => passport.fields.push((key.to_string(), value.to_string())),
+                None => passport.malformed.push(token.to_string()),
+            }
+        }
+        passport
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
 
-    for pair in line.split_ascii_whitespace() {
-        let split: Vec<String> = pair.split(":").map(|s| s.to_string()).collect();
+    /// The tokens without a `:`.
+    pub fn malformed_tokens(&self) -> &[String] {
+        &self.malformed
+    }
 
-        map.insert(split[0].to_string(), split[1].to_string());
+    /// The value of the key, the last one if the key is duplicated.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
     }
 
-    map
+    pub fn to_map(&self) -> HashMap<String, String> {
+        self.fields.iter().cloned().collect()
+    }
 }
 
-fn get_mandatory_fields() -> HashSet<String> {
-    vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"]
-        .into_iter()
-        .map(|s| s.to_string())
-        .collect()
+/// How the value of a field is checked.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Check {
+    Any,
+    /// An integer within the range.
+    Int(RangeInclusive<i64>),
+    /// An integer directly followed by one of the units, within the range of the unit.
+    Units(Vec<(String, RangeInclusive<i64>)>),
+    Pattern(Pattern),
+    OneOf(Vec<String>),
 }
 
-pub fn is_valid_passport(passport: &HashMap<String, String>) -> bool {
-    has_valid_birth_year(passport)
-        && has_valid_issue_year(passport)
-        && has_valid_expiration_year(passport)
-        && has_valid_height(passport)
-        && has_valid_hair_color(passport)
-        && has_valid_eye_color(passport)
-        && has_valid_passport_id(passport)
+impl Check {
+    pub fn accepts(&self, value: &str) -> bool {
+        self.validate(value) == FieldStatus::Ok
+    }
+
+    /// A value that does not have the form of the check is malformed, e.g.
+    /// `12` for a height in `cm` or `in`. A value of the right form that
+    /// is not allowed, e.g. `12cm`, is out of range.
+    pub fn validate(&self, value: &str) -> FieldStatus {
+        let in_range = |range: &RangeInclusive<i64>, n: i64| {
+            if range.contains(&n) {
+                FieldStatus::Ok
+            } else {
+                FieldStatus::OutOfRange
+            }
+        };
+
+        match self {
+            Check::Any => FieldStatus::Ok,
+            Check::Int(range) => match value.parse::<i64>() {
+                Ok(n) => in_range(range, n),
+                Err(_) => FieldStatus::Malformed,
+            },
+            Check::Units(units) => {
+                let mut status = FieldStatus::Malformed;
+                for (unit, range) in units {
+                    let n = value
+                        .strip_suffix(unit.as_str())
+                        .and_then(|n| n.parse::<i64>().ok());
+                    if let Some(n) = n {
+                        status = in_range(range, n);
+                        if status == FieldStatus::Ok {
+                            break;
+                        }
+                    }
+                }
+                status
+            }
+            Check::Pattern(pattern) if pattern.is_match(value) => FieldStatus::Ok,
+            Check::Pattern(_) => FieldStatus::Malformed,
+            Check::OneOf(values) if values.iter().any(|v| v == value) => FieldStatus::Ok,
+            Check::OneOf(_) => FieldStatus::OutOfRange,
+        }
+    }
 }
 
-pub fn has_mandatory_fields(passport: &HashMap<String, String>) -> bool {
-    for field in get_mandatory_fields() {
-        if !passport.contains_key(&field) {
-            return false;
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum FieldStatus {
+    Ok,
+    Missing,
+    Malformed,
+    OutOfRange,
+}
+
+impl FieldStatus {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            FieldStatus::Ok => "ok",
+            FieldStatus::Missing => "missing",
+            FieldStatus::Malformed => "malformed",
+            FieldStatus::OutOfRange => "out of range",
+        }
+    }
+}
+
+/// Something wrong with a passport.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Finding {
+    /// A field of the schema that is not ok. Missing optional fields are not findings.
+    Field {
+        name: String,
+        status: FieldStatus,
+    },
+    UnknownKey(String),
+    DuplicateKey(String),
+    /// A token without a `:`.
+    MalformedToken(String),
+}
+
+impl Finding {
+    /// The finding without the values of the passport, for grouping findings.
+    pub fn reason(&self) -> String {
+        match self {
+            Finding::Field { name, status } => format!("{} {}", name, status.describe()),
+            Finding::UnknownKey(_) => "unknown key".to_string(),
+            Finding::DuplicateKey(key) => format!("duplicate {}", key),
+            Finding::MalformedToken(_) => "malformed token".to_string(),
         }
     }
-    true
 }
 
-fn has_valid_birth_year(passport: &HashMap<String, String>) -> bool {
-    match passport.get("byr") {
-        Some(year) => {
-            let year = match year.parse::<i32>() {
-                Ok(year) => year,
-                Err(_) => 0,
-            };
-            1920 <= year && year <= 2002
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Field { name, status } => write!(f, "{}: {}", name, status.describe()),
+            Finding::UnknownKey(key) => write!(f, "unknown key {}", key),
+            Finding::DuplicateKey(key) => write!(f, "duplicate key {}", key),
+            Finding::MalformedToken(token) => write!(f, "malformed token {:?}", token),
         }
-        None => false,
     }
 }
 
-fn has_valid_issue_year(passport: &HashMap<String, String>) -> bool {
-    match passport.get("iyr") {
-        Some(year) => {
-            let year = match year.parse::<i32>() {
-                Ok(year) => year,
-                Err(_) => 0,
-            };
+/// The status of every field of the schema for a passport,
+/// and everything else found wrong with it.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Report {
+    fields: Vec<(String, FieldStatus)>,
+    findings: Vec<Finding>,
+    valid: bool,
+}
+
+impl Report {
+    /// The status of the fields, in the order of the schema.
+    pub fn fields(&self) -> &[(String, FieldStatus)] {
+        &self.fields
+    }
+
+    pub fn status(&self, field: &str) -> Option<FieldStatus> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|&(_, status)| status)
+    }
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Whether the fields are valid under the schema.
+    /// Unknown or duplicate keys and malformed tokens do not make a passport invalid.
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+/// How often each reason is found in a batch of reports.
+#[derive(Eq, PartialEq, Debug, Default)]
+pub struct FailureSummary {
+    n_passports: usize,
+    n_valid: usize,
+    reasons: HashMap<String, usize>,
+}
 
-            2010 <= year && year <= 2020
+impl FailureSummary {
+    pub fn new(reports: &[Report]) -> Self {
+        let mut summary = FailureSummary {
+            n_passports: reports.len(),
+            n_valid: reports.iter().filter(|r| r.is_valid()).count(),
+            ..FailureSummary::default()
+        };
+        for finding in reports.iter().flat_map(|r| r.findings()) {
+            *summary.reasons.entry(finding.reason()).or_insert(0) += 1;
         }
-        None => false,
+        summary
+    }
+
+    pub fn n_valid(&self) -> usize {
+        self.n_valid
+    }
+
+    /// The `n` most common reasons, most common first, ties by name.
+    pub fn most_common(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut reasons: Vec<(&str, usize)> = self
+            .reasons
+            .iter()
+            .map(|(reason, &count)| (reason.as_str(), count))
+            .collect();
+        reasons.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        reasons.truncate(n);
+        reasons
     }
 }
 
-fn has_valid_expiration_year(passport: &HashMap<String, String>) -> bool {
-    match passport.get("eyr") {
-        Some(year) => {
-            let year = match year.parse::<i32>() {
-                Ok(year) => year,
-                Err(_) => 0,
-            };
-            2020 <= year && year <= 2030
+impl fmt::Display for FailureSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} passports valid",
+            self.n_valid, self.n_passports
+        )?;
+        for (reason, count) in self.most_common(self.reasons.len()) {
+            writeln!(f, "{}: {}", reason, count)?;
         }
-        None => false,
+        Ok(())
     }
 }
 
-fn has_valid_height(passport: &HashMap<String, String>) -> bool {
-    match passport.get("hgt") {
-        Some(hgt) => {
-            let height = match hgt[0..hgt.len() - 2].parse::<i32>() {
-                Ok(height) => height,
-                Err(_) => 0,
-            };
-            if hgt[hgt.len() - 2..].starts_with("cm") {
-                150 <= height && height <= 193
-            } else if hgt[hgt.len() - 2..].starts_with("in") {
-                59 <= height && height <= 76
-            } else {
-                false
-            }
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct FieldRule {
+    name: String,
+    required: bool,
+    check: Check,
+}
+
+impl FieldRule {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    pub fn check(&self) -> &Check {
+        &self.check
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct SchemaError {
+    line: usize,
+    message: String,
+}
+
+impl SchemaError {
+    fn new(line: usize, message: &str) -> Self {
+        SchemaError {
+            line,
+            message: message.to_string(),
         }
-        None => false,
     }
 }
 
-fn has_valid_hair_color(passport: &HashMap<String, String>) -> bool {
-    match passport.get("hcl") {
-        Some(hcl) => {
-            if hcl.len() != 7 {
-                return false;
-            }
-            if !hcl.starts_with('#') {
-                return false;
-            }
-            for char in hcl[1..].chars() {
-                if !char.is_ascii_hexdigit() {
-                    return false;
+/// The rules for the fields of a passport, read from lines of the form
+///
+/// ```text
+/// <field> <required|optional> <check>
+/// ```
+///
+/// where the check is one of
+///
+/// ```text
+/// any
+/// int <min>..<max>
+/// units <min>..<max> <unit>, <min>..<max> <unit>, ...
+/// pattern <pattern>
+/// one-of <value> <value> ...
+/// ```
+///
+/// Ranges include both ends, and patterns are those of `Pattern`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Schema {
+    fields: Vec<FieldRule>,
+}
+
+impl Schema {
+    /// The schema of the puzzle, see `DEFAULT_SCHEMA`.
+    pub fn default_schema() -> &'static Schema {
+        static SCHEMA: OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| DEFAULT_SCHEMA.parse().expect("The default schema is valid"))
+    }
+
+    /// Reads a schema from a rules file.
+    pub fn load(path: &Path) -> Result<Schema, SchemaError> {
+        let rules = fs::read_to_string(path)
+            .map_err(|e| SchemaError::new(0, &format!("could not read rules file: {}", e)))?;
+        rules.parse()
+    }
+
+    pub fn fields(&self) -> &[FieldRule] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    pub fn has_required_fields(&self, passport: &HashMap<String, String>) -> bool {
+        self.fields
+            .iter()
+            .filter(|f| f.required)
+            .all(|f| passport.contains_key(&f.name))
+    }
+
+    /// Whether every required field is present, and every field of the schema is valid.
+    pub fn is_valid(&self, passport: &HashMap<String, String>) -> bool {
+        self.fields.iter().all(|f| match passport.get(&f.name) {
+            Some(value) => f.check.accepts(value),
+            None => !f.required,
+        })
+    }
+
+    pub fn report(&self, passport: &Passport) -> Report {
+        let mut findings = Vec::new();
+        let mut valid = true;
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| {
+                let status = match passport.get(&f.name) {
+                    Some(value) => f.check.validate(value),
+                    None => FieldStatus::Missing,
+                };
+                if status != FieldStatus::Ok && (f.required || status != FieldStatus::Missing) {
+                    valid = false;
+                    findings.push(Finding::Field {
+                        name: f.name.clone(),
+                        status,
+                    });
                 }
+                (f.name.clone(), status)
+            })
+            .collect();
+
+        for (idx, (key, _)) in passport.fields().iter().enumerate() {
+            let earlier = &passport.fields()[..idx];
+            if self.field(key).is_none() && !earlier.iter().any(|(k, _)| k == key) {
+                findings.push(Finding::UnknownKey(key.clone()));
+            }
+            if earlier.iter().filter(|(k, _)| k == key).count() == 1 {
+                findings.push(Finding::DuplicateKey(key.clone()));
             }
-            true
         }
-        None => false,
-    }
-}
+        for token in passport.malformed_tokens() {
+            findings.push(Finding::MalformedToken(token.clone()));
+        }
 
-fn has_valid_eye_color(passport: &HashMap<String, String>) -> bool {
-    match passport.get("ecl") {
-        Some(color) => VALID_EYE_COLORS.contains(&color.as_str()),
-        None => false,
+        Report {
+            fields,
+            findings,
+            valid,
+        }
     }
 }
 
-fn has_valid_passport_id(passport: &HashMap<String, String>) -> bool {
-    match passport.get("pid") {
-        Some(pid) => {
-            if pid.len() != 9 {
-                return false;
+impl FromStr for Schema {
+    type Err = SchemaError;
+
+    fn from_str(rules: &str) -> Result<Self, Self::Err> {
+        let mut fields: Vec<FieldRule> = Vec::new();
+
+        for (idx, line) in rules.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
             }
-            match pid.parse::<u64>() {
-                Ok(_) => true,
-                Err(_) => false,
+            let field =
+                parse_field_rule(line).map_err(|message| SchemaError::new(idx + 1, message))?;
+            if fields.iter().any(|f| f.name == field.name) {
+                return Err(SchemaError::new(idx + 1, "field defined twice"));
             }
+            fields.push(field);
         }
-        None => false,
+
+        Ok(Schema { fields })
     }
 }
 
+/// Splits off the first word, returning it and the rest without leading whitespace.
+fn split_word(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some((&s[..end], s[end..].trim_start()))
+}
+
+fn parse_field_rule(line: &str) -> Result<FieldRule, &'static str> {
+    let (name, rest) = split_word(line).ok_or("missing field name")?;
+    let (required, rest) = match split_word(rest) {
+        Some(("required", rest)) => (true, rest),
+        Some(("optional", rest)) => (false, rest),
+        _ => return Err("expected required or optional"),
+    };
+    let (kind, argument) = split_word(rest).ok_or("missing check")?;
+    let argument = argument.trim_end();
+
+    let check = match kind {
+        "any" => Check::Any,
+        "int" => Check::Int(parse_range(argument)?),
+        "units" => Check::Units(
+            argument
+                .split(',')
+                .map(|unit| {
+                    let (range, unit) = unit
+                        .trim()
+                        .split_once(char::is_whitespace)
+                        .ok_or("expected <min>..<max> <unit>")?;
+                    Ok((unit.trim().to_string(), parse_range(range)?))
+                })
+                .collect::<Result<_, &'static str>>()?,
+        ),
+        "pattern" => Check::Pattern(argument.parse().map_err(|_| "malformed pattern")?),
+        "one-of" => Check::OneOf(argument.split_whitespace().map(str::to_string).collect()),
+        _ => return Err("unknown check, expected any, int, units, pattern or one-of"),
+    };
+
+    Ok(FieldRule {
+        name: name.to_string(),
+        required,
+        check,
+    })
+}
+
+fn parse_range(range: &str) -> Result<RangeInclusive<i64>, &'static str> {
+    let (min, max) = range
+        .trim()
+        .split_once("..")
+        .ok_or("expected <min>..<max>")?;
+    let min = min.parse().map_err(|_| "malformed minimum")?;
+    let max = max.parse().map_err(|_| "malformed maximum")?;
+    Ok(min..=max)
+}
+
+pub fn is_valid_passport(passport: &HashMap<String, String>) -> bool {
+    Schema::default_schema().is_valid(passport)
+}
+
+pub fn has_mandatory_fields(passport: &HashMap<String, String>) -> bool {
+    Schema::default_schema().has_required_fields(passport)
+}
+
 pub fn print_solution() {
     let puzzle = puzzle_input::read_input("day04");
 
     println!(
         "Day 04 Solution Part 1: {}",
-        count_with_filter(&puzzle, |passport| has_mandatory_fields(passport))
+        count_with_filter(&puzzle, has_mandatory_fields)
     );
     println!(
         "Day 04 Solution Part 2: {}",
-        count_with_filter(&puzzle, |passport| is_valid_passport(passport))
+        FailureSummary::new(&report_batch(&puzzle, Schema::default_schema())).n_valid()
     );
 }
 
+/// Reports on every passport of a batch, separated by blank lines.
+pub fn report_batch(input: &str, schema: &Schema) -> Vec<Report> {
+    read_batch(input).iter().map(|p| schema.report(p)).collect()
+}
+
+/// The name of the column with the validation status in exports.
+/// It is not read back as a field on import. The key of a field
+/// never contains `:`, so a field can not be mistaken for it.
+pub const VALID_COLUMN: &str = ":valid";
+
+/// The columns of an export of the passports: the fields of the
+/// schema in its order, then any other keys in alphabetical order,
+/// and last the validation status.
+pub fn export_columns(schema: &Schema, passports: &[Passport]) -> Vec<String> {
+    let mut columns: Vec<String> = schema.fields().iter().map(|f| f.name.clone()).collect();
+    let mut others: Vec<&String> = passports
+        .iter()
+        .flat_map(|p| p.fields().iter().map(|(key, _)| key))
+        .filter(|key| schema.field(key).is_none())
+        .collect();
+    others.sort();
+    others.dedup();
+    columns.extend(others.into_iter().cloned());
+    columns.push(VALID_COLUMN.to_string());
+    columns
+}
+
+/// One JSON object per passport and line, with the members in the order of
+/// `export_columns`. Missing fields are left out, and duplicate keys have their last value.
+pub fn to_json_lines(schema: &Schema, passports: &[Passport]) -> String {
+    let columns = export_columns(schema, passports);
+    let mut lines = String::new();
+    for passport in passports {
+        let mut members: Vec<(String, json::Value)> = columns[..columns.len() - 1]
+            .iter()
+            .filter_map(|key| Some((key.clone(), passport.get(key)?.into())))
+            .collect();
+        let valid = schema.report(passport).is_valid();
+        members.push((VALID_COLUMN.to_string(), valid.into()));
+
+        lines.push_str(&json::Value::Object(members).to_string());
+        lines.push('\n');
+    }
+    lines
+}
+
+/// A header with the names of `export_columns`, then a record per passport.
+/// Missing fields are empty, and the status is `true` or `false`.
+/// Fields with an empty value are therefore read back as missing.
+pub fn to_csv(schema: &Schema, passports: &[Passport]) -> String {
+    let columns = export_columns(schema, passports);
+    let mut text = csv::write_record(&columns);
+    for passport in passports {
+        let mut record: Vec<&str> = columns[..columns.len() - 1]
+            .iter()
+            .map(|key| passport.get(key).unwrap_or(""))
+            .collect();
+        let valid = schema.report(passport).is_valid();
+        record.push(if valid { "true" } else { "false" });
+        text.push_str(&csv::write_record(&record));
+    }
+    text
+}
+
+/// Writes the passports in the format of the puzzle input.
+pub fn to_batch(passports: &[Passport]) -> String {
+    let groups: Vec<String> = passports
+        .iter()
+        .map(|p| {
+            let tokens: Vec<String> = p
+                .fields()
+                .iter()
+                .map(|(k, v)| format!("{}:{}", k, v))
+                .collect();
+            tokens.join(" ")
+        })
+        .collect();
+    groups.join("\n\n")
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ImportError {
+    line: usize,
+    message: String,
+}
+
+impl ImportError {
+    fn new(line: usize, message: &str) -> Self {
+        ImportError {
+            line,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Reads passports written by `to_json_lines`. Blank lines are skipped,
+/// and the validation status is ignored.
+pub fn from_json_lines(text: &str) -> Result<Vec<Passport>, ImportError> {
+    let mut passports = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |message: &str| ImportError::new(idx + 1, message);
+        let value: json::Value = line
+            .parse()
+            .map_err(|e: json::JsonError| error(&e.to_string()))?;
+        let members = value
+            .as_object()
+            .ok_or_else(|| error("expected an object"))?;
+
+        let mut fields = Vec::new();
+        for (key, value) in members {
+            if key == VALID_COLUMN {
+                continue;
+            }
+            let value = value
+                .as_str()
+                .ok_or_else(|| error(&format!("expected a string for {}", key)))?;
+            fields.push((key.clone(), value.to_string()));
+        }
+        passports.push(Passport::from_fields(fields));
+    }
+    Ok(passports)
+}
+
+/// Reads passports written by `to_csv`. Empty cells are missing fields,
+/// so a field with an empty value is lost, and the validation status is ignored.
+pub fn from_csv(text: &str) -> Result<Vec<Passport>, ImportError> {
+    let records = csv::read_records(text)
+        .map_err(|e| ImportError::new(e.line, "unclosed or misplaced quote"))?;
+    let mut records = records.into_iter();
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+
+    records
+        .map(|(line, record)| {
+            if record.len() != header.len() {
+                return Err(ImportError::new(
+                    line,
+                    &format!("expected {} cells, found {}", header.len(), record.len()),
+                ));
+            }
+            let fields = header
+                .iter()
+                .zip(record)
+                .filter(|(key, value)| *key != VALID_COLUMN && !value.is_empty())
+                .map(|(key, value)| (key.clone(), value))
+                .collect();
+            Ok(Passport::from_fields(fields))
+        })
+        .collect()
+}
+
+/// The passports of a batch, separated by blank lines.
+pub fn read_batch(input: &str) -> Vec<Passport> {
+    GroupIterator::new(input)
+        .map(|g| Passport::parse(g.as_str()))
+        .collect()
+}
+
 pub fn count_with_filter<T>(input: &str, f: T) -> usize
 where
     T: Fn(&HashMap<String, String>) -> bool,
 {
     GroupIterator::new(input)
         .map(|g| g.as_str())
-        .map(|line| process_line(line))
+        .map(process_line)
         .filter(f)
         .count()
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::redundant_closure)]
 mod tests {
-    use crate::day04::{count_with_filter, has_mandatory_fields, is_valid_passport, process_line};
+    use crate::day04::{
+        count_with_filter, export_columns, from_csv, from_json_lines, has_mandatory_fields,
+        is_valid_passport, process_line, read_batch, report_batch, to_batch, to_csv, to_json_lines,
+        Check, FailureSummary, FieldStatus, Finding, Passport, Schema, SchemaError,
+    };
     use std::collections::HashMap;
 
     #[test]
@@ -391,4 +930,288 @@ iyr:2011 ecl:brn hgt:59in";
             2
         );
     }
+
+    #[test]
+    fn default_schema_has_the_puzzle_fields() {
+        let schema = Schema::default_schema();
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name()).collect();
+        assert_eq!(
+            names,
+            vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"]
+        );
+        assert!(!schema.field("cid").unwrap().is_required());
+        assert_eq!(
+            schema.field("byr").unwrap().check(),
+            &Check::Int(1920..=2002)
+        );
+    }
+
+    #[test]
+    fn checks_of_a_custom_schema() {
+        let schema: Schema = "\
+# a smaller schema
+name required pattern ^[A-Z][a-z]+$
+age  required int 0..150
+size optional units 1..10 m, 100..1000 cm
+pet  optional one-of cat dog"
+            .parse()
+            .unwrap();
+
+        let passport = process_line("name:Ada age:36 size:170cm pet:dog");
+        assert!(schema.is_valid(&passport));
+        assert!(schema.is_valid(&process_line("name:Ada age:36")));
+        assert!(!schema.is_valid(&process_line("name:ada age:36")));
+        assert!(!schema.is_valid(&process_line("name:Ada age:151")));
+        assert!(!schema.is_valid(&process_line("name:Ada age:36 size:11m")));
+        assert!(!schema.is_valid(&process_line("name:Ada age:36 size:cm")));
+        assert!(!schema.is_valid(&process_line("name:Ada age:36 pet:cow")));
+        assert!(!schema.has_required_fields(&process_line("age:36")));
+    }
+
+    #[test]
+    fn malformed_schemas() {
+        let error = |rules: &str| rules.parse::<Schema>().unwrap_err();
+
+        assert_eq!(
+            error("a required any\nb sometimes any"),
+            SchemaError::new(2, "expected required or optional")
+        );
+        assert_eq!(error("a required"), SchemaError::new(1, "missing check"));
+        assert_eq!(
+            error("a required int 1-2"),
+            SchemaError::new(1, "expected <min>..<max>")
+        );
+        assert_eq!(
+            error("a required units 1..2"),
+            SchemaError::new(1, "expected <min>..<max> <unit>")
+        );
+        assert_eq!(
+            error("a required pattern [a"),
+            SchemaError::new(1, "malformed pattern")
+        );
+        assert_eq!(
+            error("a required any\n\na optional any"),
+            SchemaError::new(3, "field defined twice")
+        );
+        assert_eq!(
+            error("a required float 1..2").message,
+            "unknown check, expected any, int, units, pattern or one-of"
+        );
+    }
+
+    #[test]
+    fn load_schema_from_rules_file() {
+        let path = std::env::temp_dir().join(format!("day04-rules-{}.txt", std::process::id()));
+        std::fs::write(&path, "pid required pattern ^[0-9]{3}$\n").unwrap();
+        let schema = Schema::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(schema.is_valid(&process_line("pid:123")));
+        assert!(!schema.is_valid(&process_line("pid:1234")));
+        assert_eq!(Schema::load(&path).unwrap_err().line, 0);
+    }
+
+    #[test]
+    fn height_values_too_short_for_a_unit() {
+        let mut passport =
+            process_line("byr:1920 iyr:2010 eyr:2020 hcl:#ffffff ecl:amb pid:000111222 hgt:1");
+        assert!(!is_valid_passport(&passport));
+        passport.insert("hgt".to_string(), "in".to_string());
+        assert!(!is_valid_passport(&passport));
+    }
+
+    #[test]
+    fn tokens_without_colon_do_not_panic() {
+        let passport = Passport::parse("byr:1920 oops hgt:180cm :x");
+        assert_eq!(passport.malformed_tokens(), &["oops".to_string()]);
+        assert_eq!(passport.get("hgt"), Some("180cm"));
+        assert_eq!(passport.get(""), Some("x"));
+        assert_eq!(process_line("oops").len(), 0);
+    }
+
+    #[test]
+    fn report_status_of_every_field() {
+        let passport =
+            Passport::parse("byr:1919 iyr:20x0 eyr:2020 hgt:5 hcl:#12345 ecl:red pid:000111222");
+        let report = Schema::default_schema().report(&passport);
+
+        assert!(!report.is_valid());
+        assert_eq!(report.status("byr"), Some(FieldStatus::OutOfRange));
+        assert_eq!(report.status("iyr"), Some(FieldStatus::Malformed));
+        assert_eq!(report.status("eyr"), Some(FieldStatus::Ok));
+        assert_eq!(report.status("hgt"), Some(FieldStatus::Malformed));
+        assert_eq!(report.status("hcl"), Some(FieldStatus::Malformed));
+        assert_eq!(report.status("ecl"), Some(FieldStatus::OutOfRange));
+        assert_eq!(report.status("pid"), Some(FieldStatus::Ok));
+        assert_eq!(report.status("cid"), Some(FieldStatus::Missing));
+        assert_eq!(report.findings().len(), 5);
+        assert_eq!(report.findings()[0].to_string(), "byr: out of range");
+    }
+
+    #[test]
+    fn unknown_and_duplicate_keys() {
+        let passport = Passport::parse(
+            "byr:1920 iyr:2010 eyr:2020 hgt:150cm hcl:#ffffff ecl:amb pid:000111222 \
+             byr:1921 byr:1922 xyz:1 xyz:2 junk",
+        );
+        let report = Schema::default_schema().report(&passport);
+
+        assert!(report.is_valid());
+        assert_eq!(
+            report.findings(),
+            &[
+                Finding::DuplicateKey("byr".to_string()),
+                Finding::UnknownKey("xyz".to_string()),
+                Finding::DuplicateKey("xyz".to_string()),
+                Finding::MalformedToken("junk".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_of_most_common_failures() {
+        let input = "byr:1920 hgt:1\n\nbyr:1919 hgt:1 pid:1\n\nhgt:170cm a";
+        let schema: Schema = "byr required int 1920..2002\nhgt required units 150..193 cm"
+            .parse()
+            .unwrap();
+        let reports = report_batch(input, &schema);
+        let summary = FailureSummary::new(&reports);
+
+        assert_eq!(summary.n_valid(), 0);
+        assert_eq!(
+            summary.most_common(3),
+            vec![
+                ("hgt malformed", 2),
+                ("byr missing", 1),
+                ("byr out of range", 1)
+            ]
+        );
+        assert_eq!(
+            summary.to_string(),
+            "0 of 3 passports valid\n\
+hgt malformed: 2\n\
+byr missing: 1\n\
+byr out of range: 1\n\
+malformed token: 1\n\
+unknown key: 1\n"
+        );
+    }
+
+    const BATCH: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\n\
+byr:1937 iyr:2017 cid:147 hgt:183cm\n\
+\n\
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 note:a,\"b\"\n\
+hcl:#cfa07d byr:1929";
+
+    #[test]
+    fn columns_have_a_stable_order() {
+        let passports = read_batch(BATCH);
+        let columns = export_columns(Schema::default_schema(), &passports);
+        assert_eq!(
+            columns,
+            vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid", "note", ":valid"]
+        );
+
+        let mut reversed = passports.clone();
+        reversed.reverse();
+        assert_eq!(export_columns(Schema::default_schema(), &reversed), columns);
+    }
+
+    #[test]
+    fn export_json_lines() {
+        let passports = read_batch(BATCH);
+        let json = to_json_lines(Schema::default_schema(), &passports);
+        let lines: Vec<&str> = json.lines().collect();
+
+        assert_eq!(
+            lines[1],
+            r##"{"byr":"1929","iyr":"2013","eyr":"2023","hcl":"#cfa07d","ecl":"amb","pid":"028048884","cid":"350","note":"a,\"b\"",":valid":false}"##
+        );
+        assert!(lines[0].ends_with(r#""cid":"147",":valid":true}"#));
+    }
+
+    #[test]
+    fn export_csv() {
+        let passports = read_batch(BATCH);
+        let csv = to_csv(Schema::default_schema(), &passports);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,note,:valid");
+        assert_eq!(
+            lines[1],
+            "1937,2017,2020,183cm,#fffffd,gry,860033327,147,,true"
+        );
+        assert_eq!(
+            lines[2],
+            "1929,2013,2023,,#cfa07d,amb,028048884,350,\"a,\"\"b\"\"\",false"
+        );
+    }
+
+    #[test]
+    fn import_what_is_exported() {
+        let passports = read_batch(BATCH);
+        let schema = Schema::default_schema();
+        let by_key = |passports: &[Passport]| -> Vec<Vec<(String, String)>> {
+            passports
+                .iter()
+                .map(|p| {
+                    let mut fields = p.fields().to_vec();
+                    fields.sort();
+                    fields
+                })
+                .collect()
+        };
+
+        let from_json = from_json_lines(&to_json_lines(schema, &passports)).unwrap();
+        let from_csv = from_csv(&to_csv(schema, &passports)).unwrap();
+        assert_eq!(by_key(&from_json), by_key(&passports));
+        assert_eq!(by_key(&from_csv), by_key(&passports));
+        assert_eq!(
+            by_key(&read_batch(&to_batch(&from_csv))),
+            by_key(&passports)
+        );
+    }
+
+    #[test]
+    fn field_named_valid_survives_a_round_trip() {
+        let passports = read_batch("byr:1920 valid:no\n\npid:1 valid:");
+        let schema = Schema::default_schema();
+
+        let from_json = from_json_lines(&to_json_lines(schema, &passports)).unwrap();
+        assert_eq!(from_json, passports);
+        let from_csv = from_csv(&to_csv(schema, &passports)).unwrap();
+        assert_eq!(from_csv[0], passports[0]);
+    }
+
+    #[test]
+    fn empty_values_are_lost_in_csv() {
+        let passports = read_batch("byr:1920 cid:");
+        let schema = Schema::default_schema();
+
+        let from_json = from_json_lines(&to_json_lines(schema, &passports)).unwrap();
+        assert_eq!(from_json[0].get("cid"), Some(""));
+        let from_csv = from_csv(&to_csv(schema, &passports)).unwrap();
+        assert_eq!(from_csv[0].get("cid"), None);
+        assert_eq!(from_csv[0].get("byr"), Some("1920"));
+    }
+
+    #[test]
+    fn malformed_imports() {
+        let error = from_json_lines("{\"byr\":\"1920\"}\n\n{\"byr\":1920}").unwrap_err();
+        assert_eq!(
+            (error.line(), error.message()),
+            (3, "expected a string for byr")
+        );
+
+        let error = from_json_lines("[1]").unwrap_err();
+        assert_eq!(error.message(), "expected an object");
+
+        let error = from_csv("byr,:valid\n1920,true\n1921\n").unwrap_err();
+        assert_eq!(
+            (error.line(), error.message()),
+            (3, "expected 2 cells, found 1")
+        );
+
+        assert_eq!(from_csv("").unwrap(), vec![]);
+    }
 }
@@ -1,93 +1,213 @@
+use crate::util::bsp::{BspCode, BspError, Segment};
 use crate::util::puzzle_input;
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+pub const ROWS: u32 = 128;
+pub const COLUMNS: u32 = 8;
 
 pub fn print_solution() {
     let puzzle = puzzle_input::read_input("day05");
+    let ids = seat_ids(&puzzle).unwrap_or_else(|e| panic!("{:?}", e));
 
-    let potential_max = puzzle
-        .split_ascii_whitespace()
-        .map(|s| determine_seat_id(s))
-        .max();
-    let max_id = match potential_max {
-        Some(id) => id,
-        None => 0,
-    };
+    let max_id = ids.iter().max().copied().unwrap_or_default();
     println!("Day 05 Solution Part 1: {}", max_id);
-    println!("Day 05 Solution Part 2: {}", get_my_seat(&puzzle));
+    println!(
+        "Day 05 Solution Part 2: {}",
+        get_my_seat(&ids).unwrap_or_default()
+    );
 }
 
-/// my seat is a seat not in the list and such
-/// that both seat + 1 and seat - 1 is
-fn get_my_seat(str: &str) -> u32 {
-    let set: HashSet<u32> = str
-        .split_ascii_whitespace()
-        .map(|s| determine_seat_id(s))
-        .collect();
-
-    for seat in &set {
-        if set.contains(&(seat - 2)) && !set.contains(&(seat - 1)) {
-            return seat - 1;
-        }
-        if set.contains(&(seat + 2)) && !set.contains(&(seat + 1)) {
-            return seat + 1;
-        }
-    }
-    0
+pub type PassError = BspError;
+
+pub fn row_segment() -> Segment {
+    Segment::new("row", 'F', 'B', 7)
 }
 
-pub fn determine_row(row_str: &str) -> u32 {
-    let mut row = 0;
-    for c in row_str.chars() {
-        row = row << 1;
-        row += match c {
-            'B' => 1,
-            _ => 0,
-        };
-    }
+pub fn column_segment() -> Segment {
+    Segment::new("column", 'L', 'R', 3)
+}
+
+/// The code of a boarding pass: a row followed by a column.
+pub fn boarding_pass_code() -> &'static BspCode {
+    static CODE: OnceLock<BspCode> = OnceLock::new();
+    CODE.get_or_init(|| {
+        BspCode::new(vec![row_segment(), column_segment()])
+            .expect("a row and a column make a valid code")
+    })
+}
+
+/// The seat ID of a pass of 7 `F` or `B` followed by 3 `L` or `R`.
+pub fn parse_seat_id(pass: &str) -> Result<u32, PassError> {
+    boarding_pass_code().decode_id(pass)
+}
 
-    row
+/// The IDs of the passes, one per line, with the line number of the first invalid pass.
+pub fn seat_ids(passes: &str) -> Result<Vec<u32>, (usize, PassError)> {
+    passes
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| parse_seat_id(line.trim()).map_err(|e| (idx + 1, e)))
+        .collect()
 }
 
-pub fn determine_column(column_str: &str) -> u32 {
-    let mut column = 0;
-    for c in column_str.chars() {
-        column = column << 1;
-        column += match c {
-            'R' => 1,
-            _ => 0,
+/// The pass of the seat, or `None` if there is no such seat on the plane.
+pub fn encode_seat_id(id: u32) -> Option<String> {
+    boarding_pass_code().encode(id)
+}
+
+/// A line per row with its number, and `#` for taken and `.` for free seats.
+/// The aisle is between the fourth and fifth column.
+pub fn seat_map(ids: &[u32]) -> String {
+    let taken: HashSet<u32> = ids.iter().copied().collect();
+    let mut map = String::with_capacity((ROWS * (COLUMNS + 6)) as usize);
+    for row in 0..ROWS {
+        map.push_str(&format!("{:3} ", row));
+        for column in 0..COLUMNS {
+            if column == COLUMNS / 2 {
+                map.push(' ');
+            }
+            let id = row * COLUMNS + column;
+            map.push(if taken.contains(&id) { '#' } else { '.' });
         }
+        map.push('\n');
     }
+    map
+}
+
+/// The runs of free seats between the first and the last taken seat.
+pub fn gaps(ids: &[u32]) -> Vec<RangeInclusive<u32>> {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+
+    ids.windows(2)
+        .filter(|w| w[1] - w[0] > 1)
+        .map(|w| w[0] + 1..=w[1] - 1)
+        .collect()
+}
+
+/// my seat is a seat not in the list and such
+/// that both seat + 1 and seat - 1 is
+pub fn get_my_seat(ids: &[u32]) -> Option<u32> {
+    gaps(ids)
+        .into_iter()
+        .find(|gap| gap.start() == gap.end())
+        .map(|gap| *gap.start())
+}
 
-    column
+/// The row of 7 `F` or `B`.
+pub fn determine_row(row_str: &str) -> Result<u32, PassError> {
+    BspCode::new(vec![row_segment()])?.decode_id(row_str)
 }
 
-pub fn determine_seat_id(boarding_pass: &str) -> u32 {
-    8 * determine_row(&boarding_pass[0..7]) + determine_column(&boarding_pass[7..])
+/// The column of 3 `L` or `R`.
+pub fn determine_column(column_str: &str) -> Result<u32, PassError> {
+    BspCode::new(vec![column_segment()])?.decode_id(column_str)
 }
 
 #[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)]
 mod test {
-    use crate::day05::{determine_column, determine_row, determine_seat_id};
+    use crate::day05::{
+        determine_column, determine_row, encode_seat_id, gaps, get_my_seat, parse_seat_id,
+        seat_ids, seat_map, PassError,
+    };
 
     #[test]
     fn row_bbbbffb_is_row_0b111_100_1() {
-        assert_eq!(determine_row("BBBBFFB"), 0b111_100_1);
+        assert_eq!(determine_row("BBBBFFB"), Ok(0b111_100_1));
     }
 
     #[test]
     fn row_bfffbbf_is_0b1000110() {
-        assert_eq!(determine_row("BFFFBBF"), 0b1000110);
+        assert_eq!(determine_row("BFFFBBF"), Ok(0b1000110));
     }
 
     #[test]
     fn column_rlr_is_0b101() {
-        assert_eq!(determine_column("RLR"), 0b101);
+        assert_eq!(determine_column("RLR"), Ok(0b101));
     }
 
     #[test]
     fn determine_example_seat_ids() {
-        assert_eq!(determine_seat_id("BFFFBBFRRR"), 567);
-        assert_eq!(determine_seat_id("FFFBBBFRRR"), 119);
-        assert_eq!(determine_seat_id("BBFFBBFRLL"), 820);
+        assert_eq!(parse_seat_id("BFFFBBFRRR"), Ok(567));
+        assert_eq!(parse_seat_id("FFFBBBFRRR"), Ok(119));
+        assert_eq!(parse_seat_id("BBFFBBFRLL"), Ok(820));
+    }
+
+    #[test]
+    fn determine_row_rejects_unknown_symbols() {
+        assert_eq!(
+            determine_row("BFFXBBF"),
+            Err(PassError::UnknownSymbol {
+                position: 3,
+                symbol: 'X'
+            })
+        );
+        assert_eq!(determine_column("RL"), Err(PassError::WrongLength(2)));
+    }
+
+    #[test]
+    fn strict_parsing_of_passes() {
+        assert_eq!(parse_seat_id("BFFFBBFRRR"), Ok(567));
+        assert_eq!(parse_seat_id("BFFFBBFRR"), Err(PassError::WrongLength(9)));
+        assert_eq!(parse_seat_id(""), Err(PassError::WrongLength(0)));
+        assert_eq!(
+            parse_seat_id("BFFFBBLRRR"),
+            Err(PassError::UnknownSymbol {
+                position: 6,
+                symbol: 'L'
+            })
+        );
+        assert_eq!(
+            parse_seat_id("BFFFBBFRXR"),
+            Err(PassError::UnknownSymbol {
+                position: 8,
+                symbol: 'X'
+            })
+        );
+        assert_eq!(
+            seat_ids("BFFFBBFRRR\n\nFFFBBBFRR"),
+            Err((3, PassError::WrongLength(9)))
+        );
+    }
+
+    #[test]
+    fn encode_seat_ids() {
+        assert_eq!(encode_seat_id(567), Some("BFFFBBFRRR".to_string()));
+        assert_eq!(encode_seat_id(0), Some("FFFFFFFLLL".to_string()));
+        assert_eq!(encode_seat_id(1023), Some("BBBBBBBRRR".to_string()));
+        assert_eq!(encode_seat_id(1024), None);
+        for id in 0..1024 {
+            assert_eq!(parse_seat_id(&encode_seat_id(id).unwrap()), Ok(id));
+        }
+    }
+
+    #[test]
+    fn draw_seat_map() {
+        let map = seat_map(&[0, 3, 4, 15]);
+        let lines: Vec<&str> = map.lines().collect();
+        assert_eq!(lines.len(), 128);
+        assert_eq!(lines[0], "  0 #..# #...");
+        assert_eq!(lines[1], "  1 .... ...#");
+        assert_eq!(lines[127], "127 .... ....");
+    }
+
+    #[test]
+    fn every_gap_between_taken_seats() {
+        assert_eq!(gaps(&[10, 4, 5, 8, 12, 5]), vec![6..=7, 9..=9, 11..=11]);
+        assert_eq!(gaps(&[]), vec![]);
+        assert_eq!(get_my_seat(&[10, 4, 5, 8, 12]), Some(9));
+        assert_eq!(get_my_seat(&[4, 5, 8]), None);
+    }
+
+    #[test]
+    fn seats_at_the_front_do_not_underflow() {
+        assert_eq!(get_my_seat(&[0, 1, 3]), Some(2));
+        assert_eq!(get_my_seat(&[0, 2]), Some(1));
+        assert_eq!(get_my_seat(&[0]), None);
     }
 }
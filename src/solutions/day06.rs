@@ -1,8 +1,250 @@
+use crate::util::bitset::BitSet;
 use crate::util::puzzle_input;
 use crate::util::{Group, GroupIterator};
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::iter::FromIterator;
 
+/// The questions that appear in a survey, numbered in sorted order.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Alphabet {
+    symbols: Vec<char>,
+}
+
+impl Alphabet {
+    /// Every character of the text except whitespace.
+    pub fn infer(text: &str) -> Self {
+        let mut symbols: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        Alphabet { symbols }
+    }
+
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn index(&self, symbol: char) -> Option<usize> {
+        self.symbols.binary_search(&symbol).ok()
+    }
+
+    /// The set of the symbols that are in the alphabet.
+    pub fn encode(&self, answers: &str) -> BitSet {
+        answers.chars().filter_map(|c| self.index(c)).collect()
+    }
+
+    pub fn decode(&self, set: &BitSet) -> Vec<char> {
+        set.iter().map(|idx| self.symbols[idx]).collect()
+    }
+}
+
+/// How many people of a group must have answered a question.
+/// A question nobody answered never meets a quorum.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Quorum {
+    AtLeast(usize),
+    /// At least `numerator / denominator` of the group, rounded up.
+    Fraction {
+        numerator: usize,
+        denominator: usize,
+    },
+    All,
+}
+
+impl Quorum {
+    /// The number of people needed in a group of `members`, or `None`
+    /// for a fraction with a zero denominator. A quorum too large to
+    /// count is `usize::MAX`, which no group meets.
+    pub fn required(&self, members: usize) -> Option<usize> {
+        let required = match *self {
+            Quorum::AtLeast(k) => k,
+            Quorum::Fraction {
+                numerator,
+                denominator,
+            } => {
+                if denominator == 0 {
+                    return None;
+                }
+                let product = members as u128 * numerator as u128;
+                // rounded up, and a zero product needs one person like any quorum
+                let required = product.saturating_sub(1) / denominator as u128 + 1;
+                usize::try_from(required).unwrap_or(usize::MAX)
+            }
+            Quorum::All => members,
+        };
+        Some(required.max(1))
+    }
+}
+
+/// The answers of every person, one line per person, in groups
+/// separated by blank lines.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Survey {
+    alphabet: Alphabet,
+    groups: Vec<Vec<BitSet>>,
+}
+
+impl Survey {
+    pub fn parse(text: &str) -> Self {
+        let alphabet = Alphabet::infer(text);
+        let groups = GroupIterator::new(text)
+            .map(|group| {
+                group
+                    .as_str()
+                    .split_ascii_whitespace()
+                    .map(|person| alphabet.encode(person))
+                    .collect()
+            })
+            .collect();
+        Survey { alphabet, groups }
+    }
+
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// The answers of each person, per group.
+    pub fn groups(&self) -> &[Vec<BitSet>] {
+        &self.groups
+    }
+
+    /// Per group, the questions answered by anyone in the group.
+    pub fn anyone(&self) -> Vec<BitSet> {
+        self.answered_by(Quorum::AtLeast(1))
+            .expect("a quorum of one is defined")
+    }
+
+    /// Per group, the questions answered by everyone in the group.
+    pub fn everyone(&self) -> Vec<BitSet> {
+        self.answered_by(Quorum::All)
+            .expect("a quorum of everyone is defined")
+    }
+
+    /// Per group, the questions answered by a quorum of the group,
+    /// or `None` if the quorum is not defined.
+    pub fn answered_by(&self, quorum: Quorum) -> Option<Vec<BitSet>> {
+        self.groups
+            .iter()
+            .map(|people| {
+                let required = quorum.required(people.len())?;
+                Some(if required == 1 {
+                    people.iter().fold(BitSet::new(), |mut union, answers| {
+                        union.union_with(answers);
+                        union
+                    })
+                } else if required == people.len() {
+                    people
+                        .iter()
+                        .fold(BitSet::full(self.alphabet.len()), |mut common, answers| {
+                            common.intersect_with(answers);
+                            common
+                        })
+                } else {
+                    self.tally(people)
+                        .into_iter()
+                        .enumerate()
+                        .filter(|&(_, count)| count >= required)
+                        .map(|(question, _)| question)
+                        .collect()
+                })
+            })
+            .collect()
+    }
+
+    /// Per group, how many people answered each question of the alphabet.
+    pub fn group_counts(&self) -> Vec<Vec<usize>> {
+        self.groups
+            .iter()
+            .map(|people| self.tally(people))
+            .collect()
+    }
+
+    /// How many people answered each question of the alphabet.
+    pub fn question_counts(&self) -> Vec<usize> {
+        self.tally(self.groups.iter().flatten())
+    }
+
+    /// The questions answered by the most people, with their count.
+    pub fn most_common(&self) -> Vec<(char, usize)> {
+        self.extreme_questions(|counts| counts.iter().max().copied())
+    }
+
+    /// The questions answered by the fewest people, with their count.
+    pub fn least_common(&self) -> Vec<(char, usize)> {
+        self.extreme_questions(|counts| counts.iter().min().copied())
+    }
+
+    /// The indices of the groups with the most people.
+    pub fn largest_groups(&self) -> Vec<usize> {
+        self.extreme_groups(|sizes| sizes.max())
+    }
+
+    /// The indices of the groups with the fewest people.
+    pub fn smallest_groups(&self) -> Vec<usize> {
+        self.extreme_groups(|sizes| sizes.min())
+    }
+
+    fn tally<'a, I>(&self, people: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = &'a BitSet>,
+    {
+        let mut counts = vec![0; self.alphabet.len()];
+        for answers in people {
+            for question in answers.iter() {
+                counts[question] += 1;
+            }
+        }
+        counts
+    }
+
+    fn extreme_questions<F>(&self, pick: F) -> Vec<(char, usize)>
+    where
+        F: Fn(&[usize]) -> Option<usize>,
+    {
+        let counts = self.question_counts();
+        match pick(&counts) {
+            None => Vec::new(),
+            Some(extreme) => self
+                .alphabet
+                .symbols()
+                .iter()
+                .zip(counts)
+                .filter(|&(_, count)| count == extreme)
+                .map(|(&symbol, count)| (symbol, count))
+                .collect(),
+        }
+    }
+
+    fn extreme_groups<F>(&self, pick: F) -> Vec<usize>
+    where
+        F: Fn(&mut dyn Iterator<Item = usize>) -> Option<usize>,
+    {
+        let extreme = pick(&mut self.groups.iter().map(Vec::len));
+        (0..self.groups.len())
+            .filter(|&idx| Some(self.groups[idx].len()) == extreme)
+            .collect()
+    }
+
+    /// The sum over the groups of the number of questions in each set.
+    pub fn count(sets: &[BitSet]) -> usize {
+        sets.iter().map(BitSet::len).sum()
+    }
+
+    fn to_char_sets(&self, sets: &[BitSet]) -> Vec<HashSet<char>> {
+        sets.iter()
+            .map(|set| self.alphabet.decode(set).into_iter().collect())
+            .collect()
+    }
+}
+
 pub fn get_answer_from_group(group: &Group) -> Vec<HashSet<char>> {
     let mut vec = Vec::new();
     for line in group.as_str().split_ascii_whitespace() {
@@ -13,43 +255,27 @@ pub fn get_answer_from_group(group: &Group) -> Vec<HashSet<char>> {
 }
 
 pub fn get_combined_answers_of_groups(str: &str) -> Vec<HashSet<char>> {
-    let mut result = Vec::new();
-    for group in GroupIterator::new(str) {
-        let answers = get_answer_from_group(&group);
-
-        let mut common: HashSet<char> = HashSet::from_iter("".chars());
-        for set in answers {
-            common = HashSet::from_iter(set.union(&common).map(|c| *c));
-        }
-        result.push(common)
-    }
-    result
+    let survey = Survey::parse(str);
+    survey.to_char_sets(&survey.anyone())
 }
 
 pub fn get_common_answer_of_groups(str: &str) -> Vec<HashSet<char>> {
-    let mut result = Vec::new();
-    for group in GroupIterator::new(str) {
-        let answers = get_answer_from_group(&group);
-
-        let mut common: HashSet<char> = HashSet::from_iter("abcdefghijklmnopqrstuvwxyz".chars());
-        for set in answers {
-            common = HashSet::from_iter(set.intersection(&common).map(|c| *c));
-        }
-        result.push(common)
-    }
-    result
+    let survey = Survey::parse(str);
+    survey.to_char_sets(&survey.everyone())
 }
 
 pub fn print_solution() {
     let puzzle = puzzle_input::read_input("day06");
+    let survey = Survey::parse(&puzzle);
 
     println!(
         "Day 06 Solution Part 1: {}",
-        sum_answers(&get_combined_answers_of_groups(&puzzle))
+        Survey::count(&survey.anyone())
+    );
+    println!(
+        "Day 06 Solution Part 2: {}",
+        Survey::count(&survey.everyone())
     );
-
-    let vec = get_common_answer_of_groups(&puzzle);
-    println!("Day 06 Solution Part 2: {}", sum_answers(&vec));
 }
 
 pub fn sum_answers(answers: &[HashSet<char>]) -> usize {
@@ -65,7 +291,7 @@ pub fn sum_answers(answers: &[HashSet<char>]) -> usize {
 mod tests {
     use crate::day06::{
         get_answer_from_group, get_combined_answers_of_groups, get_common_answer_of_groups,
-        sum_answers,
+        sum_answers, Alphabet, Quorum, Survey,
     };
     use crate::util::Group;
     use std::collections::HashSet;
@@ -128,4 +354,122 @@ mod tests {
         );
         assert_eq!(sum_answers(&common), 6)
     }
+
+    #[test]
+    fn alphabet_is_inferred_from_the_input() {
+        let alphabet = Alphabet::infer("ab\r\nZ9\n\nbé");
+        assert_eq!(alphabet.symbols(), &['9', 'Z', 'a', 'b', 'é']);
+        assert_eq!(alphabet.index('b'), Some(3));
+        assert_eq!(alphabet.index('c'), None);
+        assert_eq!(alphabet.decode(&alphabet.encode("éaxa")), vec!['a', 'é']);
+    }
+
+    #[test]
+    fn questions_beyond_a_to_z() {
+        let text = "aZ9\r\n9aZ\r\n\r\nXY\nY";
+        assert_eq!(
+            get_common_answer_of_groups(text),
+            vec![
+                HashSet::from_iter(vec!['a', 'Z', '9']),
+                HashSet::from_iter(vec!['Y'])
+            ]
+        );
+
+        let survey = Survey::parse(text);
+        assert_eq!(Survey::count(&survey.anyone()), 5);
+        assert_eq!(Survey::count(&survey.everyone()), 4);
+    }
+
+    #[test]
+    fn more_questions_than_bits_in_a_word() {
+        let everything: String = (0..100)
+            .map(|n| char::from_u32(0x100 + n).unwrap())
+            .collect();
+        let text = format!("{}\n{}x", everything, everything);
+        let survey = Survey::parse(&text);
+        assert_eq!(survey.alphabet().len(), 101);
+        assert_eq!(Survey::count(&survey.anyone()), 101);
+        assert_eq!(Survey::count(&survey.everyone()), 100);
+    }
+
+    #[test]
+    fn quorum_sizes() {
+        assert_eq!(Quorum::AtLeast(2).required(5), Some(2));
+        assert_eq!(Quorum::AtLeast(0).required(5), Some(1));
+        assert_eq!(Quorum::All.required(5), Some(5));
+        let half = Quorum::Fraction {
+            numerator: 1,
+            denominator: 2,
+        };
+        assert_eq!(half.required(5), Some(3));
+        assert_eq!(half.required(4), Some(2));
+    }
+
+    #[test]
+    fn fractions_do_not_overflow() {
+        let all_but_one = Quorum::Fraction {
+            numerator: usize::MAX - 1,
+            denominator: usize::MAX,
+        };
+        assert_eq!(all_but_one.required(usize::MAX), Some(usize::MAX - 1));
+        let too_many = Quorum::Fraction {
+            numerator: usize::MAX,
+            denominator: 1,
+        };
+        assert_eq!(too_many.required(2), Some(usize::MAX));
+        let undefined = Quorum::Fraction {
+            numerator: 1,
+            denominator: 0,
+        };
+        assert_eq!(undefined.required(5), None);
+        assert_eq!(Survey::parse("a\nb").answered_by(undefined), None);
+    }
+
+    #[test]
+    fn questions_answered_by_a_quorum() {
+        let survey = Survey::parse("abc\nab\nb\nbd\n\nx\nxy");
+        let chars = |sets: Option<Vec<_>>| -> Vec<Vec<char>> {
+            sets.unwrap()
+                .iter()
+                .map(|s| survey.alphabet().decode(s))
+                .collect()
+        };
+
+        assert_eq!(
+            chars(survey.answered_by(Quorum::AtLeast(2))),
+            vec![vec!['a', 'b'], vec!['x']]
+        );
+        assert_eq!(
+            chars(survey.answered_by(Quorum::Fraction {
+                numerator: 3,
+                denominator: 4
+            })),
+            vec![vec!['b'], vec!['x']]
+        );
+        assert_eq!(
+            survey.answered_by(Quorum::AtLeast(1)),
+            Some(survey.anyone())
+        );
+        assert_eq!(survey.answered_by(Quorum::All), Some(survey.everyone()));
+        assert_eq!(
+            chars(survey.answered_by(Quorum::AtLeast(5))),
+            vec![vec![], vec![]]
+        );
+    }
+
+    #[test]
+    fn survey_statistics() {
+        let survey = Survey::parse("abc\nab\nb\nbd\n\nx\nxy\n\nc");
+        assert_eq!(survey.alphabet().symbols(), &['a', 'b', 'c', 'd', 'x', 'y']);
+        assert_eq!(survey.question_counts(), vec![2, 4, 2, 1, 2, 1]);
+        assert_eq!(survey.group_counts()[1], vec![0, 0, 0, 0, 2, 1]);
+        assert_eq!(survey.most_common(), vec![('b', 4)]);
+        assert_eq!(survey.least_common(), vec![('d', 1), ('y', 1)]);
+        assert_eq!(survey.largest_groups(), vec![0]);
+        assert_eq!(survey.smallest_groups(), vec![2]);
+
+        let empty = Survey::parse("");
+        assert_eq!(empty.most_common(), vec![]);
+        assert_eq!(empty.largest_groups(), Vec::<usize>::new());
+    }
 }
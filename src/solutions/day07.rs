@@ -1,168 +1,531 @@
+use crate::util::json::Value;
+use crate::util::parse::{
+    alt, literal, map, pair, parse_all, parse_lines, preceded, recognize, separated_list1,
+    terminated, unsigned, word, ParseError, Parser,
+};
 use crate::util::puzzle_input;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::iter::FromIterator;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
 use std::str::FromStr;
 
-fn split_str_into_container_and_contained_color(str: &str) -> (&str, &str) {
-    let test: Vec<&str> = str.split(" bags contain ").collect();
-    (test[0], test[1])
+pub const MY_BAG: &str = "shiny gold";
+
+/// A color is two words, e.g. `shiny gold`
+fn color<'a>() -> impl Parser<'a, &'a str> {
+    recognize(pair(word(), preceded(literal(" "), word())))
+}
+
+/// `<n> <color> bag` or `<n> <color> bags`
+fn bag_val<'a>() -> impl Parser<'a, BagVal> {
+    map(
+        terminated(
+            pair(unsigned(), preceded(literal(" "), color())),
+            preceded(literal(" "), alt(literal("bags"), literal("bag"))),
+        ),
+        |(number, color)| BagVal {
+            color: color.to_string(),
+            number,
+        },
+    )
+}
+
+/// The contained bags of a rule, including the final `.`
+fn contents<'a>() -> impl Parser<'a, Vec<BagVal>> {
+    terminated(
+        alt(
+            map(literal("no other bags"), |_| Vec::new()),
+            separated_list1(bag_val(), literal(", ")),
+        ),
+        literal("."),
+    )
+}
+
+/// `<color> bags contain <contents>`
+fn rule<'a>() -> impl Parser<'a, (&'a str, Vec<BagVal>)> {
+    pair(terminated(color(), literal(" bags contain ")), contents())
 }
 
-fn split_input_string(str: &str) -> Vec<(&str, Vec<BagVal>)> {
-    let mut vec = Vec::new();
-    for line in str.lines() {
-        let (color, colors) = split_str_into_container_and_contained_color(line);
-        vec.push((color, derive_colors(colors)));
+fn split_input_string(str: &str) -> Result<Vec<(&str, Vec<BagVal>)>, ParseError> {
+    parse_lines(rule(), str)
+}
+
+/// The lines count from 1.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum RuleError {
+    Syntax(ParseError),
+    /// A second rule for a color, with the line of the first rule.
+    DuplicateRule {
+        color: String,
+        first: usize,
+        line: usize,
+    },
+    /// A color listed twice in the contents of a rule.
+    DuplicateContent {
+        color: String,
+        line: usize,
+    },
+}
+
+impl RuleError {
+    pub fn line(&self) -> usize {
+        match self {
+            RuleError::Syntax(e) => e.line(),
+            RuleError::DuplicateRule { line, .. } | RuleError::DuplicateContent { line, .. } => {
+                *line
+            }
+        }
     }
-    vec
+}
+
+impl From<ParseError> for RuleError {
+    fn from(e: ParseError) -> Self {
+        RuleError::Syntax(e)
+    }
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Syntax(e) => write!(f, "{}", e),
+            RuleError::DuplicateRule { color, first, line } => write!(
+                f,
+                "line {}: second rule for {} bags, the first is on line {}",
+                line, color, first
+            ),
+            RuleError::DuplicateContent { color, line } => {
+                write!(f, "line {}: {} bags are listed twice", line, color)
+            }
+        }
+    }
+}
+
+/// Parses the rules, one per line, and checks that every color has
+/// at most one rule and is listed at most once in a rule.
+pub fn parse_rules(input: &str) -> Result<Vec<(&str, Vec<BagVal>)>, RuleError> {
+    let rules = split_input_string(input)?;
+
+    let mut lines: HashMap<&str, usize> = HashMap::new();
+    for (idx, (color, contents)) in rules.iter().enumerate() {
+        let line = idx + 1;
+        if let Some(&first) = lines.get(color) {
+            return Err(RuleError::DuplicateRule {
+                color: color.to_string(),
+                first,
+                line,
+            });
+        }
+        lines.insert(color, line);
+
+        for (n, bag) in contents.iter().enumerate() {
+            if contents[..n].iter().any(|other| other.color == bag.color) {
+                return Err(RuleError::DuplicateContent {
+                    color: bag.color.clone(),
+                    line,
+                });
+            }
+        }
+    }
+    Ok(rules)
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
-struct BagVal {
+pub struct BagVal {
     color: String,
     number: u64,
 }
 
+impl BagVal {
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+}
+
 impl FromStr for BagVal {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let end = if s.ends_with("s") { 5 } else { 4 };
-
-        let number = s[0..1].parse::<u64>().expect("Always a number");
-        let color = s[2..s.len() - end].to_string();
-        Ok(BagVal { color, number })
+        parse_all(bag_val(), s, 1)
     }
 }
 
-fn derive_colors(str: &str) -> Vec<BagVal> {
-    if "no other bags.".eq(str) {
-        return vec![];
-    }
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum GraphError {
+    UnknownColor(String),
+    /// The colors of a cycle, each containing the next and the last containing the first.
+    Cycle(Vec<String>),
+    /// The number of bags in the color does not fit in a `u64`.
+    Overflow(String),
+}
 
-    let res = str[..str.len() - 1]
-        .split(", ")
-        // assume we start with "\d " and ends in " bags" or " bag"
-        .map(|str| str.parse().unwrap())
-        .collect();
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::UnknownColor(color) => write!(f, "unknown color {}", color),
+            GraphError::Cycle(colors) => {
+                write!(f, "cycle of bags: {}", colors.join(" -> "))?;
+                match colors.first() {
+                    Some(first) => write!(f, " -> {}", first),
+                    None => Ok(()),
+                }
+            }
+            GraphError::Overflow(color) => write!(f, "too many bags in {}", color),
+        }
+    }
+}
 
-    res
+/// The rules as a graph from each color to the colors it contains.
+/// Colors that are only ever contained have no contents.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct BagGraph {
+    colors: Vec<String>,
+    index: HashMap<String, usize>,
+    /// Per color, the contained colors with their number.
+    contents: Vec<Vec<(usize, u64)>>,
+    /// Per color, the colors that contain it directly.
+    containers: Vec<Vec<usize>>,
 }
 
-fn build_graph_contained_to_containers<'a>(
-    nodes: &'a [(&str, Vec<BagVal>)],
-) -> HashMap<&'a str, HashSet<&'a str>> {
-    let mut map = HashMap::new();
-    for node in nodes.iter() {
-        for contained in node.1.iter() {
-            let color = contained.color.as_str();
-            let n = map.get(color);
-            if n == None {
-                map.insert(color, HashSet::new());
+impl BagGraph {
+    pub fn from_rules(rules: &[(&str, Vec<BagVal>)]) -> Self {
+        let mut graph = BagGraph::default();
+        for (color, contents) in rules {
+            let container = graph.add_color(color);
+            for bag in contents {
+                let contained = graph.add_color(&bag.color);
+                graph.contents[container].push((contained, bag.number));
+                graph.containers[contained].push(container);
             }
-            let n = map.get_mut(color).expect("Have ensured value is present");
-            n.insert(node.0);
         }
+        graph
     }
 
-    map
-}
+    fn add_color(&mut self, color: &str) -> usize {
+        if let Some(&idx) = self.index.get(color) {
+            return idx;
+        }
+        let idx = self.colors.len();
+        self.colors.push(color.to_string());
+        self.index.insert(color.to_string(), idx);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        idx
+    }
 
-fn build_graph_from_container_to_contains<'a>(
-    nodes: &'a [(&str, Vec<BagVal>)],
-) -> HashMap<&'a str, Vec<&'a BagVal>> {
-    let mut map = HashMap::new();
+    fn idx(&self, color: &str) -> Result<usize, GraphError> {
+        self.index
+            .get(color)
+            .copied()
+            .ok_or_else(|| GraphError::UnknownColor(color.to_string()))
+    }
 
-    for node in nodes.iter() {
-        map.insert(node.0, Vec::from_iter(node.1.iter()));
+    /// The colors in the order they first appear in the rules.
+    pub fn colors(&self) -> Vec<&str> {
+        self.colors.iter().map(String::as_str).collect()
     }
 
-    map
-}
+    pub fn has_color(&self, color: &str) -> bool {
+        self.index.contains_key(color)
+    }
+
+    /// The colors directly inside a bag of the color, with their number.
+    pub fn contents(&self, color: &str) -> Result<Vec<(&str, u64)>, GraphError> {
+        let idx = self.idx(color)?;
+        Ok(self.contents[idx]
+            .iter()
+            .map(|&(contained, n)| (self.colors[contained].as_str(), n))
+            .collect())
+    }
 
-fn count_bags(graph: &HashMap<&str, HashSet<&str>>) -> u64 {
-    let mut visited = HashSet::new();
-    visited.insert("shiny gold");
-    let mut queue = VecDeque::new();
-    queue.push_back("shiny gold");
+    /// The colors of the bags that directly contain the color.
+    pub fn containers(&self, color: &str) -> Result<Vec<&str>, GraphError> {
+        let idx = self.idx(color)?;
+        Ok(self.names(self.containers[idx].iter().copied()))
+    }
 
-    while let Some(color) = queue.pop_front() {
-        let containers = graph.get(color);
-        match containers {
-            None => {
+    /// Every color that eventually contains the color, sorted.
+    pub fn all_containers(&self, color: &str) -> Result<Vec<&str>, GraphError> {
+        let start = self.idx(color)?;
+        Ok(self.reachable(start, |idx| self.containers[idx].clone()))
+    }
+
+    /// Every color eventually inside the color, sorted.
+    pub fn all_contents(&self, color: &str) -> Result<Vec<&str>, GraphError> {
+        let start = self.idx(color)?;
+        Ok(self.reachable(start, |idx| {
+            self.contents[idx].iter().map(|&(c, _)| c).collect()
+        }))
+    }
+
+    /// The colors reachable from `start` by following `next`, without `start`
+    /// itself unless it is on a cycle.
+    fn reachable<F>(&self, start: usize, next: F) -> Vec<&str>
+    where
+        F: Fn(usize) -> Vec<usize>,
+    {
+        let mut visited = vec![false; self.colors.len()];
+        let mut queue: VecDeque<usize> = next(start).into_iter().collect();
+        while let Some(idx) = queue.pop_front() {
+            if !visited[idx] {
+                visited[idx] = true;
+                queue.extend(next(idx));
+            }
+        }
+        let mut names: Vec<&str> = self.names((0..self.colors.len()).filter(|&idx| visited[idx]));
+        names.sort_unstable();
+        names
+    }
+
+    fn names<I: Iterator<Item = usize>>(&self, indices: I) -> Vec<&str> {
+        indices.map(|idx| self.colors[idx].as_str()).collect()
+    }
+
+    /// The number of bags inside a bag of the color.
+    pub fn count_contained(&self, color: &str) -> Result<u64, GraphError> {
+        let start = self.idx(color)?;
+        let order = self.postorder(&[start])?;
+
+        let mut totals: HashMap<usize, u64> = HashMap::new();
+        for idx in order {
+            let mut total: u64 = 0;
+            for &(contained, n) in &self.contents[idx] {
+                total = totals[&contained]
+                    .checked_add(1)
+                    .and_then(|bags| bags.checked_mul(n))
+                    .and_then(|bags| bags.checked_add(total))
+                    .ok_or_else(|| GraphError::Overflow(self.colors[idx].clone()))?;
+            }
+            totals.insert(idx, total);
+        }
+        Ok(totals[&start])
+    }
+
+    /// A cycle of colors that contain themselves, if there is one.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        match self.topological_order() {
+            Err(GraphError::Cycle(cycle)) => Some(cycle),
+            _ => None,
+        }
+    }
+
+    /// Every color, each before the colors it contains.
+    pub fn topological_order(&self) -> Result<Vec<&str>, GraphError> {
+        let all: Vec<usize> = (0..self.colors.len()).collect();
+        let order = self.postorder(&all)?;
+        Ok(self.names(order.into_iter().rev()))
+    }
+
+    /// The colors reachable from the starts, each after the colors it contains.
+    /// Works without recursion so deep rules cannot overflow the stack.
+    fn postorder(&self, starts: &[usize]) -> Result<Vec<usize>, GraphError> {
+        const NEW: u8 = 0;
+        const OPEN: u8 = 1;
+        const DONE: u8 = 2;
+        let mut state = vec![NEW; self.colors.len()];
+        let mut order = Vec::new();
+
+        for &start in starts {
+            if state[start] != NEW {
                 continue;
             }
-            Some(set) => {
-                for &color in set.iter() {
-                    if !visited.contains(color) {
-                        visited.insert(color);
-                        queue.push_back(color);
+            // the colors being visited, with the number of their contents seen so far
+            let mut stack = vec![(start, 0)];
+            state[start] = OPEN;
+            while let Some(&mut (idx, ref mut seen)) = stack.last_mut() {
+                match self.contents[idx].get(*seen) {
+                    Some(&(contained, _)) => {
+                        *seen += 1;
+                        match state[contained] {
+                            NEW => {
+                                state[contained] = OPEN;
+                                stack.push((contained, 0));
+                            }
+                            OPEN => {
+                                let from = stack.iter().position(|&(c, _)| c == contained);
+                                let cycle = stack[from.unwrap_or(0)..]
+                                    .iter()
+                                    .map(|&(c, _)| self.colors[c].clone())
+                                    .collect();
+                                return Err(GraphError::Cycle(cycle));
+                            }
+                            _ => {}
+                        }
+                    }
+                    None => {
+                        state[idx] = DONE;
+                        order.push(idx);
+                        stack.pop();
                     }
                 }
             }
         }
+        Ok(order)
     }
-    if visited.len() == 0 {
-        0
-    } else {
-        visited.len() as u64 - 1
+
+    /// The color with every color inside it, and the rules between them.
+    pub fn subgraph_from(&self, color: &str) -> Result<BagGraph, GraphError> {
+        let mut colors = self.all_contents(color)?;
+        colors.push(color);
+        Ok(self.subgraph(&colors))
+    }
+
+    /// The color with every color that contains it, and the rules between them.
+    pub fn subgraph_to(&self, color: &str) -> Result<BagGraph, GraphError> {
+        let mut colors = self.all_containers(color)?;
+        colors.push(color);
+        Ok(self.subgraph(&colors))
+    }
+
+    /// The graph of the colors, in the order of this graph.
+    fn subgraph(&self, colors: &[&str]) -> BagGraph {
+        let mut keep = vec![false; self.colors.len()];
+        for color in colors {
+            keep[self.index[*color]] = true;
+        }
+
+        let mut graph = BagGraph::default();
+        let kept: Vec<usize> = (0..self.colors.len()).filter(|&idx| keep[idx]).collect();
+        for &idx in &kept {
+            graph.add_color(&self.colors[idx]);
+        }
+        for &idx in &kept {
+            let container = graph.index[&self.colors[idx]];
+            for &(contained, n) in &self.contents[idx] {
+                if keep[contained] {
+                    let contained = graph.index[&self.colors[contained]];
+                    graph.contents[container].push((contained, n));
+                    graph.containers[contained].push(container);
+                }
+            }
+        }
+        graph
     }
-}
 
-fn count_n_contained_bags(graph: &HashMap<&str, Vec<&BagVal>>, start: &str) -> u64 {
-    let mut sum = 0;
-    let bags = graph.get(start).expect("Should be present");
+    /// The graph in the Graphviz DOT language, with the counts as edge labels.
+    pub fn to_dot(&self) -> String {
+        let quote = |color: &str| format!("\"{}\"", color.replace('"', "\\\""));
+        let mut dot = String::from("digraph bags {\n");
+        for color in &self.colors {
+            dot.push_str(&format!("    {};\n", quote(color)));
+        }
+        for (idx, contents) in self.contents.iter().enumerate() {
+            for &(contained, n) in contents {
+                dot.push_str(&format!(
+                    "    {} -> {} [label=\"{}\"];\n",
+                    quote(&self.colors[idx]),
+                    quote(&self.colors[contained]),
+                    n
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// An object from each color to the list of its contents, e.g.
+    /// `{"bright white":[{"color":"shiny gold","count":1}]}`.
+    /// Counts beyond the range of `i64` are written as strings.
+    pub fn to_json(&self) -> Value {
+        let members = self
+            .colors
+            .iter()
+            .zip(&self.contents)
+            .map(|(color, contents)| {
+                let contents = contents
+                    .iter()
+                    .map(|&(contained, n)| {
+                        let count = i64::try_from(n)
+                            .map(Value::from)
+                            .unwrap_or_else(|_| Value::from(n.to_string()));
+                        Value::Object(vec![
+                            ("color".to_string(), self.colors[contained].as_str().into()),
+                            ("count".to_string(), count),
+                        ])
+                    })
+                    .collect();
+                (color.clone(), Value::Array(contents))
+            })
+            .collect();
+        Value::Object(members)
+    }
+
+    /// The colors that no other bag contains.
+    pub fn outermost(&self) -> Vec<&str> {
+        self.names((0..self.colors.len()).filter(|&idx| self.containers[idx].is_empty()))
+    }
 
-    for &bag in bags {
-        // count bags themselves and those they contain
-        sum += bag.number * (1 + count_n_contained_bags(graph, bag.color.as_str()));
+    /// The colors that contain no other bags.
+    pub fn innermost(&self) -> Vec<&str> {
+        self.names((0..self.colors.len()).filter(|&idx| self.contents[idx].is_empty()))
     }
+}
+
+impl FromStr for BagGraph {
+    type Err = RuleError;
 
-    sum
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(BagGraph::from_rules(&parse_rules(s)?))
+    }
 }
 
 pub fn print_solution() {
     let input = puzzle_input::read_input("day07");
+    let graph: BagGraph = input.parse().unwrap_or_else(|e| panic!("{}", e));
 
-    let vec = split_input_string(&input);
-    let graph = build_graph_contained_to_containers(&vec);
-    println!("Day07 Solution Part 1: {}", count_bags(&graph));
-
-    let graph = build_graph_from_container_to_contains(&vec);
+    let containers = graph
+        .all_containers(MY_BAG)
+        .unwrap_or_else(|e| panic!("{}", e));
+    println!("Day07 Solution Part 1: {}", containers.len());
     println!(
         "Day07 Solution Part 2: {}",
-        count_n_contained_bags(&graph, "shiny gold")
+        graph
+            .count_contained(MY_BAG)
+            .unwrap_or_else(|e| panic!("{}", e))
     );
 }
 
 #[cfg(test)]
 mod tests {
+
     use crate::day07::{
-        build_graph_contained_to_containers, build_graph_from_container_to_contains, count_bags,
-        count_n_contained_bags, derive_colors, split_input_string,
-        split_str_into_container_and_contained_color, BagVal,
+        contents, parse_rules, rule, split_input_string, BagGraph, BagVal, GraphError, RuleError,
+        MY_BAG,
     };
+    use crate::util::json::Value;
+    use crate::util::parse::parse_all;
 
     #[test]
     fn test_split_str() {
-        let colors = split_str_into_container_and_contained_color(
+        let colors = parse_all(
+            rule(),
             "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
+            1,
         );
-        let expected_colors = ("dark orange", "3 bright white bags, 4 muted yellow bags.");
+        let expected_colors = Ok((
+            "dark orange",
+            vec![
+                "3 bright white bags".parse::<BagVal>().unwrap(),
+                "4 muted yellow bags".parse::<BagVal>().unwrap(),
+            ],
+        ));
         assert_eq!(colors, expected_colors);
     }
 
     #[test]
     fn test_derive_colors() {
-        let colors = derive_colors("3 bright white bags, 1 muted yellow bag.");
+        let colors = parse_all(contents(), "3 bright white bags, 1 muted yellow bag.", 1);
         assert_eq!(
             colors,
-            vec![
+            Ok(vec![
                 "3 bright white bags".parse::<BagVal>().unwrap(),
                 "1 muted yellow bag".parse::<BagVal>().unwrap()
-            ]
+            ])
         );
-        assert_eq!(derive_colors("no other bags."), Vec::new() as Vec<BagVal>)
+        assert_eq!(parse_all(contents(), "no other bags.", 1), Ok(Vec::new()))
     }
 
     #[test]
@@ -177,7 +540,7 @@ vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags.";
 
-        let vec = split_input_string(input);
+        let vec = split_input_string(input).unwrap();
 
         let expected = vec![
             (
@@ -245,10 +608,9 @@ vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags.";
 
-        let vec = split_input_string(input);
-        let graph = build_graph_contained_to_containers(&vec);
+        let graph: BagGraph = input.parse().unwrap();
 
-        assert_eq!(count_bags(&graph), 4);
+        assert_eq!(graph.all_containers(MY_BAG).unwrap().len(), 4);
     }
 
     #[test]
@@ -262,9 +624,289 @@ dark olive bags contain 3 faded blue bags, 4 dotted black bags.
 vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags.";
-        let vec = split_input_string(input);
-        let graph = build_graph_from_container_to_contains(&vec);
+        let graph: BagGraph = input.parse().unwrap();
+
+        assert_eq!(graph.count_contained(MY_BAG), Ok(32));
+    }
+
+    #[test]
+    fn multi_digit_counts() {
+        assert_eq!(
+            "12 bright white bags".parse::<BagVal>(),
+            Ok(BagVal {
+                color: "bright white".to_string(),
+                number: 12
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_rule_reports_position() {
+        let error = split_input_string(
+            "faded blue bags contain no other bags.\nfaded blue bags hold 2 red bags.",
+        )
+        .unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 11));
+    }
+
+    const EXAMPLE: &str = r"light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+
+    #[test]
+    fn queries_in_both_directions() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+        assert_eq!(
+            graph.all_containers("muted yellow"),
+            Ok(vec!["dark orange", "light red"])
+        );
+        assert_eq!(
+            graph.all_contents("dark olive"),
+            Ok(vec!["dotted black", "faded blue"])
+        );
+        assert_eq!(graph.contents("bright white"), Ok(vec![("shiny gold", 1)]));
+        assert_eq!(
+            graph.containers("bright white"),
+            Ok(vec!["light red", "dark orange"])
+        );
+        assert_eq!(graph.count_contained("faded blue"), Ok(0));
+        assert_eq!(graph.count_contained("dark olive"), Ok(7));
+        assert_eq!(
+            graph.count_contained("pink"),
+            Err(GraphError::UnknownColor("pink".to_string()))
+        );
+    }
+
+    #[test]
+    fn outermost_and_innermost_colors() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+        assert_eq!(graph.outermost(), vec!["light red", "dark orange"]);
+        assert_eq!(graph.innermost(), vec!["faded blue", "dotted black"]);
+    }
+
+    #[test]
+    fn containers_come_before_contents() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+        let order = graph.topological_order().unwrap();
+        assert_eq!(order.len(), 9);
+        let position = |color| order.iter().position(|&c| c == color).unwrap();
+        for color in graph.colors() {
+            for (contained, _) in graph.contents(color).unwrap() {
+                assert!(position(color) < position(contained));
+            }
+        }
+        assert_eq!(graph.find_cycle(), None);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let graph: BagGraph = "a red bags contain 1 a blue bag.
+a blue bags contain 2 a green bags, 1 b black bag.
+a green bags contain 1 a red bag.
+b white bags contain 1 b black bag.
+b black bags contain no other bags."
+            .parse()
+            .unwrap();
+        let cycle = vec![
+            "a red".to_string(),
+            "a blue".to_string(),
+            "a green".to_string(),
+        ];
+        assert_eq!(graph.find_cycle(), Some(cycle.clone()));
+        assert_eq!(
+            graph.count_contained("a green"),
+            Err(GraphError::Cycle(vec![
+                "a green".to_string(),
+                "a red".to_string(),
+                "a blue".to_string(),
+            ]))
+        );
+        assert_eq!(graph.topological_order(), Err(GraphError::Cycle(cycle)));
+        assert_eq!(graph.count_contained("b white"), Ok(1));
+        assert_eq!(
+            GraphError::Cycle(vec!["x y".to_string(), "z w".to_string()]).to_string(),
+            "cycle of bags: x y -> z w -> x y"
+        );
+    }
+
+    #[test]
+    fn deep_and_shared_rules() {
+        // colors are two words of letters, so the digits of a level become letters
+        let level = |n: usize| -> String {
+            n.to_string()
+                .bytes()
+                .map(|d| (b'a' + d - b'0') as char)
+                .collect()
+        };
+        // every level holds two bags that both hold the next level
+        let mut rules = String::new();
+        for n in 0..10_000 {
+            let (this, next) = (level(n), level(n + 1));
+            rules.push_str(&format!(
+                "c {} bags contain 1 a {} bag, 1 b {} bag.\n",
+                this, next, next
+            ));
+            rules.push_str(&format!("a {} bags contain 1 c {} bag.\n", next, next));
+            rules.push_str(&format!("b {} bags contain 1 c {} bag.\n", next, next));
+        }
+        let graph: BagGraph = rules.parse().unwrap();
+
+        // with k levels below there are 4 * (2^k - 1) bags
+        let c = |n| format!("c {}", level(n));
+        assert_eq!(graph.count_contained(&c(9990)), Ok(4 * 1023));
+        assert_eq!(graph.count_contained(&c(10_000 - 62)), Ok(u64::MAX - 3));
+        assert_eq!(
+            graph.count_contained(&c(0)),
+            Err(GraphError::Overflow(c(10_000 - 63)))
+        );
+        assert_eq!(graph.topological_order().unwrap().len(), 30_001);
+    }
+
+    #[test]
+    fn singular_and_plural_bags() {
+        assert_eq!(
+            "1 dark olive bag".parse::<BagVal>(),
+            Ok(BagVal {
+                color: "dark olive".to_string(),
+                number: 1
+            })
+        );
+        assert_eq!(
+            "105 dark olive bags".parse::<BagVal>().map(|b| b.number()),
+            Ok(105)
+        );
+        assert!("dark olive bags".parse::<BagVal>().is_err());
+        assert!("2 dark olive".parse::<BagVal>().is_err());
+        assert!("2 dark olive bagz".parse::<BagVal>().is_err());
+        assert!("2 dark bags".parse::<BagVal>().is_err());
+    }
+
+    #[test]
+    fn malformed_rules_do_not_panic() {
+        for line in &[
+            "faded blue bags",
+            "faded blue bags contain",
+            "faded blue bags contain no other bags",
+            "faded blue bags contain 2 red bags.",
+            "faded blue bags contain 2 dark red bags",
+            "faded blue bags contain 2 dark red bags,.",
+            "faded blue bags contain x dark red bags.",
+            "faded blue bags contain 99999999999999999999 dark red bags.",
+        ] {
+            let error = parse_rules(line).unwrap_err();
+            assert_eq!(error.line(), 1, "{}", line);
+        }
+    }
+
+    #[test]
+    fn errors_carry_line_numbers() {
+        let error =
+            parse_rules("a b bags contain no other bags.\n\nc d bags contain no other bags.")
+                .unwrap_err();
+        assert_eq!(error.line(), 2);
+        assert!(error.to_string().starts_with("line 2, column 1"));
+    }
+
+    #[test]
+    fn duplicate_colors() {
+        assert_eq!(
+            parse_rules(
+                "a b bags contain 1 c d bag.\nc d bags contain no other bags.\na b bags contain 2 c d bags."
+            ),
+            Err(RuleError::DuplicateRule {
+                color: "a b".to_string(),
+                first: 1,
+                line: 3
+            })
+        );
+        let error =
+            "c d bags contain no other bags.\na b bags contain 1 c d bag, 2 e f bags, 3 c d bags."
+                .parse::<BagGraph>()
+                .unwrap_err();
+        assert_eq!(
+            error,
+            RuleError::DuplicateContent {
+                color: "c d".to_string(),
+                line: 2
+            }
+        );
+        assert_eq!(error.to_string(), "line 2: c d bags are listed twice");
+    }
+
+    #[test]
+    fn subgraphs_of_a_color() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+
+        let inside = graph.subgraph_from("shiny gold").unwrap();
+        assert_eq!(
+            inside.colors(),
+            vec![
+                "shiny gold",
+                "faded blue",
+                "dark olive",
+                "vibrant plum",
+                "dotted black"
+            ]
+        );
+        assert_eq!(inside.count_contained("shiny gold"), Ok(32));
+
+        let outside = graph.subgraph_to("shiny gold").unwrap();
+        assert_eq!(
+            outside.colors(),
+            vec![
+                "light red",
+                "bright white",
+                "muted yellow",
+                "dark orange",
+                "shiny gold"
+            ]
+        );
+        assert_eq!(
+            outside.contents("muted yellow"),
+            Ok(vec![("shiny gold", 2)])
+        );
+        assert_eq!(outside.innermost(), vec!["shiny gold"]);
+        assert!(graph.subgraph_to("pink").is_err());
+    }
+
+    #[test]
+    fn export_as_dot() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+        let dot = graph.subgraph_from("dark olive").unwrap().to_dot();
+        assert_eq!(
+            dot,
+            r#"digraph bags {
+    "faded blue";
+    "dark olive";
+    "dotted black";
+    "dark olive" -> "faded blue" [label="3"];
+    "dark olive" -> "dotted black" [label="4"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn export_as_json() {
+        let graph: BagGraph = EXAMPLE.parse().unwrap();
+        let json = graph.subgraph_to("bright white").unwrap().to_json();
+        assert_eq!(
+            json.to_string(),
+            r#"{"light red":[{"color":"bright white","count":1}],"bright white":[],"dark orange":[{"color":"bright white","count":3}]}"#
+        );
 
-        assert_eq!(count_n_contained_bags(&graph, "shiny gold"), 32);
+        let parsed: Value = graph.to_json().to_string().parse().unwrap();
+        assert_eq!(parsed, graph.to_json());
+        let plum = parsed
+            .get("vibrant plum")
+            .and_then(Value::as_array)
+            .unwrap();
+        assert_eq!(plum[1].get("count").and_then(Value::as_i64), Some(6));
     }
 }
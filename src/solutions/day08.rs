@@ -1,221 +1,136 @@
-use crate::day08::Operation::{Acc, Jmp, NoOp};
 use crate::util::puzzle_input;
-use std::collections::HashSet;
+use crate::util::vm::asm::assemble;
+use crate::util::vm::cfg::ControlFlowGraph;
+use crate::util::vm::isa::Instruction;
+use crate::util::vm::repair::{repairs, RepairError};
+use crate::util::vm::Machine;
+use std::env;
 
-#[derive(PartialOrd, PartialEq, Debug)]
-enum Operation {
-    NoOp(i64),
-    Acc(i64),
-    Jmp(i64),
-}
-
-impl Operation {
-    fn to_op(instruction: &str) -> Operation {
-        let (op, val) = instruction.split_at(4);
-        let val = val.trim_start_matches("+").parse::<i64>().unwrap();
-        match op {
-            "nop " => NoOp(val),
-            "acc " => Acc(val),
-            "jmp " => Jmp(val),
-            _ => panic!("Unknown op"),
-        }
-    }
-}
-
-struct Computer<'a> {
-    instructions: &'a [Operation],
-    instr_ptr: i64,
-    accumulator: i64,
-    executed_steps: HashSet<usize>,
-    halted: bool,
-}
-
-impl<'a> Computer<'a> {
-    fn parse_program(instructions: &str) -> Vec<Operation> {
-        instructions.lines().map(Operation::to_op).collect()
-    }
-
-    fn init(instructions: &'a [Operation]) -> Self {
-        Computer {
-            instructions,
-            instr_ptr: 0,
-            accumulator: 0,
-            executed_steps: HashSet::new(),
-            halted: false,
-        }
-    }
+/// If set, the problems found in the program are printed before it runs.
+pub const LINT_VAR: &str = "AOC_DAY08_LINT";
 
-    fn execute_step(&mut self) {
-        match self.instructions[self.instr_ptr as usize] {
-            Acc(val) => {
-                self.accumulator += val;
-                self.instr_ptr += 1;
-            }
-            NoOp(_) => {
-                self.instr_ptr += 1;
-            }
-            Jmp(offset) => {
-                self.instr_ptr += offset;
-            }
-        }
+pub fn print_solution() {
+    let puzzle = puzzle_input::read_input("day08");
+    let program = assemble(&puzzle).unwrap_or_else(|e| panic!("{}", e));
+    if env::var_os(LINT_VAR).is_some() {
+        print!("{}", lint_report(&program));
     }
+    let mut machine = Machine::new(program.clone());
+    machine.run();
 
-    fn accumulated(&self) -> i64 {
-        self.accumulator
-    }
-
-    fn execute_until_end(&mut self) {
-        while !self.halted {
-            if self.instr_ptr as usize >= self.instructions.len() {
-                self.halted = true;
-                return;
-            }
-            match self.executed_steps.contains(&(self.instr_ptr as usize)) {
-                true => {
-                    self.halted = true;
-                }
-                false => {
-                    self.executed_steps.insert(self.instr_ptr as usize);
-                    self.execute_step();
-                }
-            }
-        }
-    }
-
-    fn ended_without_loop(&self) -> Option<bool> {
-        if !self.halted {
-            return None;
-        }
-        Some(self.instr_ptr as usize >= self.instructions.len())
+    println!("Day 08 Solution Part 1: {}", machine.accumulator());
+    match find_acc_of_non_loop_machine(&program) {
+        Ok(acc) => println!("Day 08 Solution Part 2: {}", acc),
+        Err(e) => println!("Day 08 Solution Part 2: {}", e),
     }
 }
 
-pub fn print_solution() {
-    let puzzle = puzzle_input::read_input("day08");
-    let instrs = Computer::parse_program(&puzzle);
-    let mut comp = Computer::init(&instrs);
-    comp.execute_until_end();
-
-    println!("Day 08 Solution Part 1: {}", comp.accumulated());
-    println!(
-        "Day 08 Solution Part 2: {}",
-        find_acc_of_non_loop_machine(&puzzle)
-    );
+/// A line per problem found in the program without running it.
+pub fn lint_report(program: &[Instruction]) -> String {
+    ControlFlowGraph::new(program)
+        .lint()
+        .iter()
+        .map(|lint| format!("Day 08 lint: {}\n", lint))
+        .collect()
 }
 
-fn find_acc_of_non_loop_machine(puzzle: &str) -> i64 {
-    let mut instr = Computer::parse_program(puzzle);
-    for i in 0..instr.len() {
-        match instr[i] {
-            NoOp(val) => {
-                instr[i] = Jmp(val);
-                let mut comp = Computer::init(&instr);
-                comp.execute_until_end();
-                if Some(true) == comp.ended_without_loop() {
-                    return comp.accumulator;
-                }
-                instr[i] = NoOp(val);
-            }
-            Jmp(val) => {
-                instr[i] = NoOp(val);
-                let mut comp = Computer::init(&instr);
-                comp.execute_until_end();
-                if Some(true) == comp.ended_without_loop() {
-                    return comp.accumulator;
-                }
-                instr[i] = Jmp(val);
-            }
-            _ => {}
-        }
-    }
-
-    0
+/// The accumulator at the end of the program after the first repair
+/// that makes it terminate.
+fn find_acc_of_non_loop_machine(program: &[Instruction]) -> Result<i64, RepairError> {
+    repairs(program).map(|found| found[0].accumulator())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day08::Operation::{Acc, Jmp, NoOp};
-    use crate::day08::{Computer, Operation};
+    use crate::day08::{find_acc_of_non_loop_machine, lint_report};
+    use crate::util::vm::asm::assemble;
+    use crate::util::vm::isa::{Instruction, Opcode};
+    use crate::util::vm::repair::RepairError;
+    use crate::util::vm::{Halt, Machine};
+
+    fn machine(program: &str) -> Machine {
+        Machine::new(assemble(program).unwrap())
+    }
 
     #[test]
     fn translating_nop_op() {
-        assert_eq!(Operation::to_op("nop +0"), NoOp(0));
+        assert_eq!(
+            "nop +0".parse::<Instruction>(),
+            Ok(Instruction::new(Opcode::Nop, 0))
+        );
     }
 
     #[test]
     fn translating_acc_op() {
-        assert_eq!(Operation::to_op("acc +2"), Acc(2));
+        assert_eq!(
+            "acc +2".parse::<Instruction>(),
+            Ok(Instruction::new(Opcode::Acc, 2))
+        );
     }
 
     #[test]
     fn translating_jmp_acc() {
-        assert_eq!(Operation::to_op("jmp -42"), Jmp(-42));
+        assert_eq!(
+            "jmp -42".parse::<Instruction>(),
+            Ok(Instruction::new(Opcode::Jmp, -42))
+        );
     }
 
     #[test]
     fn freshly_initialized_computer() {
-        let comp = Computer::parse_program("nop +0");
-        let comp = Computer::init(&comp);
-        assert_eq!(comp.accumulated(), 0);
+        let comp = machine("nop +0");
+        assert_eq!(comp.accumulator(), 0);
     }
 
     #[test]
     fn execute_nop_step() {
-        let comp = Computer::parse_program("nop +0");
-        let mut comp = Computer::init(&comp);
-        comp.execute_step();
-        assert_eq!(comp.accumulated(), 0);
+        let mut comp = machine("nop +0");
+        comp.step().unwrap();
+        assert_eq!(comp.accumulator(), 0);
     }
 
     #[test]
     fn execute_acc_step() {
-        let comp = Computer::parse_program("acc +4");
-        let mut comp = Computer::init(&comp);
-        comp.execute_step();
-        assert_eq!(comp.accumulated(), 4);
-        assert_eq!(comp.ended_without_loop(), None);
+        let mut comp = machine("acc +4");
+        comp.step().unwrap();
+        assert_eq!(comp.accumulator(), 4);
+        assert_eq!(comp.halt(), None);
     }
 
     #[test]
     fn execute_multiple_steps() {
-        let comp = Computer::parse_program("acc +4\nnop +123\nacc -3\nnop +0\nnop +12\nacc +1");
-        let mut comp = Computer::init(&comp);
+        let mut comp = machine("acc +4\nnop +123\nacc -3\nnop +0\nnop +12\nacc +1");
         for _ in 0..6 {
-            comp.execute_step();
+            comp.step().unwrap();
         }
 
-        assert_eq!(comp.accumulated(), 2);
+        assert_eq!(comp.accumulator(), 2);
     }
 
     #[test]
     fn add_jmp_steps() {
-        let comp = Computer::parse_program("jmp +2\nacc +1\nnop +0");
-        let mut comp = Computer::init(&comp);
-        comp.execute_step();
-        assert_eq!(comp.accumulated(), 0);
+        let mut comp = machine("jmp +2\nacc +1\nnop +0");
+        comp.step().unwrap();
+        assert_eq!(comp.accumulator(), 0);
     }
 
     #[test]
     fn execute_until_loop_ended() {
-        let comp = Computer::parse_program("jmp +2\nacc +1\nnop +0\njmp -2");
-        let mut comp = Computer::init(&comp);
-        comp.execute_until_end();
-        assert_eq!(comp.accumulated(), 1);
-        assert_eq!(comp.ended_without_loop(), Some(false))
+        let mut comp = machine("jmp +2\nacc +1\nnop +0\njmp -2");
+        assert_eq!(comp.run(), Halt::Loop(2));
+        assert_eq!(comp.accumulator(), 1);
     }
 
     #[test]
     fn execute_until_end_for_non_loop_ends() {
-        let comp = Computer::parse_program("jmp +2\nacc +1\nnop +0\nacc +5");
-        let mut comp = Computer::init(&comp);
-        comp.execute_until_end();
-        assert_eq!(comp.accumulated(), 5);
-        assert_eq!(comp.ended_without_loop(), Some(true));
+        let mut comp = machine("jmp +2\nacc +1\nnop +0\nacc +5");
+        assert_eq!(comp.run(), Halt::End);
+        assert_eq!(comp.accumulator(), 5);
     }
 
     #[test]
     fn execute_test_example() {
-        let comp = Computer::parse_program(
+        let mut comp = machine(
             "nop +0\n\
 acc +1\n\
 jmp +4\n\
@@ -226,8 +141,54 @@ acc +1\n\
 jmp -4\n\
 acc +6",
         );
-        let mut comp = Computer::init(&comp);
-        comp.execute_until_end();
-        assert_eq!(comp.accumulated(), 5);
+        comp.run();
+        assert_eq!(comp.accumulator(), 5);
+        assert_eq!(find_acc_of_non_loop_machine(comp.program()), Ok(8));
+    }
+
+    #[test]
+    fn unknown_operation_is_an_error() {
+        let error = assemble("nop +0\nmov +2").unwrap_err();
+        assert_eq!(error.line(), 2);
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 1: unknown instruction mov"
+        );
+
+        let error = "acc 2x".parse::<Instruction>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 6: expected a space, a comment or the end of the line, found \"x\""
+        );
+    }
+
+    #[test]
+    fn no_repair_is_an_error() {
+        let program = assemble("jmp +0\njmp -1").unwrap();
+        assert_eq!(
+            find_acc_of_non_loop_machine(&program),
+            Err(RepairError::NoRepair)
+        );
+    }
+
+    #[test]
+    fn jumps_before_the_start_halt_safely() {
+        let mut comp = machine("acc +1\njmp -5");
+        assert_eq!(comp.run(), Halt::OutOfRange(-4));
+        assert_eq!(find_acc_of_non_loop_machine(comp.program()), Ok(1));
+    }
+
+    #[test]
+    fn lint_before_running() {
+        let program =
+            assemble("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6")
+                .unwrap();
+        assert_eq!(
+            lint_report(&program),
+            "Day 08 lint: the program loops at 1\n\
+Day 08 lint: instruction 5 is never reached\n\
+Day 08 lint: instruction 8 is never reached\n"
+        );
+        assert_eq!(lint_report(&assemble("acc +1\nnop +0").unwrap()), "");
     }
 }
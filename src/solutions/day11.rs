@@ -1,6 +1,7 @@
 use crate::day11::Type::*;
-use crate::util::map::{Map, Terrain, TerrainErr};
+use crate::util::map::{Map, Terrain, TerrainErr, NEIGHBOUR_OFFSETS};
 use crate::util::puzzle_input;
+use crate::util::render::{Colour, Image, Palette};
 
 pub fn print_solution() {
     let map = Map::<Type>::configure(&puzzle_input::read_input("day11")).unwrap();
@@ -78,9 +79,11 @@ impl Map<Type> {
                         .filter(|c| *c.terrain() == Occupied)
                         .count();
 
-                    let new_terrain = if count > 0 { Empty } else { Occupied };
-
-                    new_terrain
+                    if count > 0 {
+                        Empty
+                    } else {
+                        Occupied
+                    }
                 }
             };
 
@@ -93,20 +96,9 @@ impl Map<Type> {
     pub fn next_star(&self) -> Self {
         let mut vec = Vec::with_capacity(self.size());
         for point in self.iter() {
-            let offsets = [
-                (-1, -1),
-                (0, -1),
-                (1, -1),
-                (-1, 0),
-                (1, 0),
-                (-1, 1),
-                (0, 1),
-                (1, 1),
-            ];
             let mut neighbours = Vec::with_capacity(8);
-            for offset in &offsets {
-                if let Some(c) =
-                    self.step_until((point.x(), point.y()), *offset, |c| *c.terrain() != Ground)
+            for &offset in &NEIGHBOUR_OFFSETS {
+                if let Some(c) = self.step_until(point.point(), offset, |c| *c.terrain() != Ground)
                 {
                     neighbours.push(c);
                 }
@@ -131,9 +123,11 @@ impl Map<Type> {
                         .filter(|c| *c.terrain() == Occupied)
                         .count();
 
-                    let new_terrain = if count > 0 { Empty } else { Occupied };
-
-                    new_terrain
+                    if count > 0 {
+                        Empty
+                    } else {
+                        Occupied
+                    }
                 }
             };
 
@@ -169,11 +163,44 @@ impl Map<Type> {
     pub fn find_end(&self, round_limit: usize) -> Option<Map<Type>> {
         Self::to_end(self, Self::next, round_limit)
     }
+
+    /// Colours free seats green, occupied seats red and the floor grey.
+    pub fn palette() -> Palette {
+        Palette::new(Colour::rgb(128, 128, 128))
+            .with(Empty.to_char(), Colour::rgb(0, 160, 0))
+            .with(Occupied.to_char(), Colour::rgb(200, 0, 0))
+    }
+
+    /// An image of every seating round, starting with this map and
+    /// ending with the first round where the seating no longer changes.
+    pub fn frames<F>(&self, next: F, round_limit: usize, scale: usize) -> Vec<Image>
+    where
+        F: Fn(&Self) -> Self,
+    {
+        let palette = Self::palette();
+        let mut frames = vec![Image::from_map(self, &palette, scale)];
+
+        let mut cur = next(self);
+        if cur.eq(self) {
+            return frames;
+        }
+        for _ in 0..round_limit {
+            frames.push(Image::from_map(&cur, &palette, scale));
+            let following = next(&cur);
+            if following.eq(&cur) {
+                break;
+            }
+            cur = following;
+        }
+
+        frames
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::day11::{Map, Type};
+    use crate::util::render::Colour;
 
     #[test]
     fn test_simple_seat() {
@@ -324,4 +351,15 @@ LLL###LLL#\n\
 
         assert_eq!(m.end_star(15), Some(expected));
     }
+
+    #[test]
+    fn frames_until_seating_is_stable() {
+        let map = Map::<Type>::configure("L.\nLL").unwrap();
+        let frames = map.frames(Map::next, 10, 1);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].pixel(0, 0), Some(Colour::rgb(0, 160, 0)));
+        assert_eq!(frames[1].pixel(0, 0), Some(Colour::rgb(200, 0, 0)));
+        assert_eq!(frames[1].pixel(1, 0), Some(Colour::rgb(128, 128, 128)));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::util::point::{Point2, Vector2};
 use crate::util::puzzle_input;
 use std::str::FromStr;
 
@@ -33,12 +34,12 @@ pub enum Direction {
 
 pub fn waypoint_simulation(movement: &[Direction]) -> u64 {
     let mut ship = Ship::default();
-    let mut waypoint = Ship::new((10, 1), 0);
+    let mut waypoint = Ship::new(Point2::new(10, 1), 0);
 
     for direction in movement {
         match direction {
             Direction::Forward(steps) => {
-                ship.move_towards(waypoint.position, *steps);
+                ship.move_towards(waypoint.position.to_vector(), *steps);
             }
             Direction::Left(degree) => {
                 waypoint.rotate_around_orig_clockwise(360 - degree);
@@ -55,6 +56,21 @@ pub fn waypoint_simulation(movement: &[Direction]) -> u64 {
     ship.distance()
 }
 
+/// The positions of the ship, starting at the origin,
+/// after each of the movements.
+pub fn route(movement: &[Direction]) -> Vec<Point2<i64>> {
+    let mut ship = Ship::default();
+    let mut route = Vec::with_capacity(movement.len() + 1);
+    route.push(ship.position);
+
+    for direction in movement {
+        ship.move_ship(direction);
+        route.push(ship.position);
+    }
+
+    route
+}
+
 impl FromStr for Direction {
     type Err = ();
 
@@ -85,12 +101,12 @@ impl FromStr for Direction {
 
 #[derive(Debug, PartialEq, Eq)]
 struct Ship {
-    position: (i64, i64),
+    position: Point2<i64>,
     direction: i64,
 }
 
 impl Ship {
-    pub fn new(position: (i64, i64), direction: i64) -> Self {
+    pub fn new(position: Point2<i64>, direction: i64) -> Self {
         Ship {
             position,
             direction,
@@ -98,41 +114,27 @@ impl Ship {
     }
 
     pub fn distance(&self) -> u64 {
-        (self.position.0.abs() + self.position.1.abs()) as u64
+        self.position.to_vector().manhattan_length() as u64
     }
 
-    pub fn move_towards(&mut self, step_size: (i64, i64), n_steps: i64) {
-        self.position = (
-            self.position.0 + step_size.0 * n_steps,
-            self.position.1 + step_size.1 * n_steps,
-        );
+    pub fn move_towards(&mut self, step_size: Vector2<i64>, n_steps: i64) {
+        self.position += step_size * n_steps;
     }
 
     /// Done clockwise
     pub fn rotate_around_orig_clockwise(&mut self, degree: i64) {
-        if degree == 90 {
-            self.position = (self.position.1, -self.position.0)
-        } else if degree == 180 {
-            self.position = (-self.position.0, -self.position.1)
-        } else if degree == 270 {
-            self.position = (-self.position.1, self.position.0)
+        if degree % 90 == 0 {
+            let rotated = self.position.to_vector().rotate_clockwise(degree / 90);
+            self.position = Point2::new(rotated.x, rotated.y);
         }
     }
 
     pub fn move_ship(&mut self, direction: &Direction) {
         match direction {
-            Direction::North(magnitude) => {
-                self.position = (self.position.0, self.position.1 + magnitude)
-            }
-            Direction::East(magnitude) => {
-                self.position = (self.position.0 + magnitude, self.position.1)
-            }
-            Direction::South(magnitude) => {
-                self.position = (self.position.0, self.position.1 - magnitude)
-            }
-            Direction::West(magnitude) => {
-                self.position = (self.position.0 - magnitude, self.position.1)
-            }
+            Direction::North(magnitude) => self.move_towards(Vector2::new(0, 1), *magnitude),
+            Direction::East(magnitude) => self.move_towards(Vector2::new(1, 0), *magnitude),
+            Direction::South(magnitude) => self.move_towards(Vector2::new(0, -1), *magnitude),
+            Direction::West(magnitude) => self.move_towards(Vector2::new(-1, 0), *magnitude),
             Direction::Left(magnitude) => {
                 self.direction -= magnitude;
                 if self.direction < 0 {
@@ -146,20 +148,9 @@ impl Ship {
                 }
             }
             Direction::Forward(magnitude) => {
-                let pos = if self.direction == 0 {
-                    (0, 1)
-                } else if self.direction == 90 {
-                    (1, 0)
-                } else if self.direction == 180 {
-                    (0, -1)
-                } else {
-                    (-1, 0)
-                };
-
-                self.position = (
-                    self.position.0 + pos.0 * magnitude,
-                    self.position.1 + pos.1 * magnitude,
-                );
+                // a direction of 0 degrees is north
+                let heading = Vector2::new(0, 1).rotate_clockwise(self.direction / 90);
+                self.move_towards(heading, *magnitude);
             }
         }
     }
@@ -168,7 +159,7 @@ impl Ship {
 impl Default for Ship {
     fn default() -> Self {
         Ship {
-            position: (0, 0),
+            position: Point2::new(0, 0),
             direction: 90,
         }
     }
@@ -176,7 +167,8 @@ impl Default for Ship {
 
 #[cfg(test)]
 mod tests {
-    use crate::day12::{waypoint_simulation, Direction, Ship};
+    use crate::day12::{route, waypoint_simulation, Direction, Ship};
+    use crate::util::point::Point2;
 
     #[test]
     fn derive_direction_from_string() {
@@ -187,48 +179,48 @@ mod tests {
     fn move_north() {
         let mut ship = Ship::default();
         ship.move_ship(&Direction::North(12));
-        let expected_ship = Ship::new((0, 12), 90);
+        let expected_ship = Ship::new(Point2::new(0, 12), 90);
         assert_eq!(ship, expected_ship);
     }
 
     #[test]
     fn move_left() {
-        let mut ship = Ship::new((0, 0), 0);
+        let mut ship = Ship::new(Point2::new(0, 0), 0);
         ship.move_ship(&Direction::Left(90));
 
-        let expected = Ship::new((0, 0), 270);
+        let expected = Ship::new(Point2::new(0, 0), 270);
         assert_eq!(ship, expected);
     }
 
     #[test]
     fn move_right() {
-        let mut ship = Ship::new((0, 0), 270);
+        let mut ship = Ship::new(Point2::new(0, 0), 270);
         ship.move_ship(&Direction::Right(90));
 
-        let expected = Ship::new((0, 0), 0);
+        let expected = Ship::new(Point2::new(0, 0), 0);
         assert_eq!(ship, expected);
     }
 
     #[test]
     fn move_forward() {
-        let mut ship = Ship::new((0, 0), 0);
+        let mut ship = Ship::new(Point2::new(0, 0), 0);
         ship.move_ship(&Direction::Forward(2));
-        let expected = Ship::new((0, 2), 0);
+        let expected = Ship::new(Point2::new(0, 2), 0);
         assert_eq!(ship, expected);
 
-        let mut ship = Ship::new((0, 0), 90);
+        let mut ship = Ship::new(Point2::new(0, 0), 90);
         ship.move_ship(&Direction::Forward(4));
-        let expected = Ship::new((4, 0), 90);
+        let expected = Ship::new(Point2::new(4, 0), 90);
         assert_eq!(ship, expected);
 
-        let mut ship = Ship::new((0, 0), 180);
+        let mut ship = Ship::new(Point2::new(0, 0), 180);
         ship.move_ship(&Direction::Forward(12));
-        let expected = Ship::new((0, -12), 180);
+        let expected = Ship::new(Point2::new(0, -12), 180);
         assert_eq!(ship, expected);
 
-        let mut ship = Ship::new((0, 0), 270);
+        let mut ship = Ship::new(Point2::new(0, 0), 270);
         ship.move_ship(&Direction::Forward(5));
-        let expected = Ship::new((-5, 0), 270);
+        let expected = Ship::new(Point2::new(-5, 0), 270);
         assert_eq!(ship, expected);
     }
 
@@ -246,4 +238,25 @@ mod tests {
 
         assert_eq!(distance, 286)
     }
+
+    #[test]
+    fn route_of_ship() {
+        let directions = vec![
+            Direction::Forward(10),
+            Direction::North(3),
+            Direction::Right(90),
+            Direction::Forward(2),
+        ];
+
+        assert_eq!(
+            route(&directions),
+            vec![
+                Point2::new(0, 0),
+                Point2::new(10, 0),
+                Point2::new(10, 3),
+                Point2::new(10, 3),
+                Point2::new(10, 1)
+            ]
+        );
+    }
 }
@@ -44,7 +44,7 @@ fn earliest_depart(arrival: i64, busses: &[(i64, i64)]) -> (i64, i64) {
 fn calc_chinese_remainder(equations: &[(i64, i64)]) -> i64 {
     let mut m = 1;
     for eq in equations.iter() {
-        m *= eq.1 as i64;
+        m *= eq.1;
     }
 
     let mut res: i64 = 0;
@@ -54,12 +54,14 @@ fn calc_chinese_remainder(equations: &[(i64, i64)]) -> i64 {
 
         res += (-eq.0) * factor * inv;
 
-        res = res % m;
+        res %= m;
     }
 
-    let res = if res < 0 { res + m } else { res };
-
-    res as i64
+    if res < 0 {
+        res + m
+    } else {
+        res
+    }
 }
 
 fn extended_euclid(m: i64, n: i64) -> (i64, i64) {
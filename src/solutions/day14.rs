@@ -1,56 +1,83 @@
+use crate::util::parse::{
+    alt, delimited, literal, map, map_res, pair, parse_lines, preceded, take_while1, unsigned,
+    ParseError, Parser,
+};
 use crate::util::puzzle_input;
 use std::collections::HashMap;
 use std::str::FromStr;
 
 pub fn print_solution() {
     let input = puzzle_input::read_input("day14");
+    let program = parse_program(&input).unwrap_or_else(|e| panic!("{}", e));
 
-    let map = run_program(&input);
+    let map = run_program(&program);
 
     let answer: u64 = map.values().sum();
 
     println!("Day 14 Solution Part 1: {}", answer);
 
-    let state = run_mem_program(&input);
+    let state = run_mem_program(&program);
     let answer: u64 = state.values().sum();
 
     println!("Day 14 Solution Part 2: {}", answer);
 }
 
-fn run_program(str: &str) -> HashMap<u64, u64> {
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum Instruction {
+    SetMask(Mask),
+    Write { address: u64, value: u64 },
+}
+
+/// `mask = <mask>` or `mem[<address>] = <value>`
+fn instruction<'a>() -> impl Parser<'a, Instruction> {
+    let mask = preceded(
+        literal("mask = "),
+        map_res(
+            take_while1(|c| c == '0' || c == '1' || c == 'X', "a mask"),
+            |mask: &str| {
+                mask.parse::<Mask>()
+                    .map_err(|_| "a mask of at most 64 bits")
+            },
+        ),
+    );
+    let write = map(
+        pair(
+            delimited(literal("mem["), unsigned::<u64>(), literal("]")),
+            preceded(literal(" = "), unsigned::<u64>()),
+        ),
+        |(address, value)| Instruction::Write { address, value },
+    );
+    alt(map(mask, Instruction::SetMask), write)
+}
+
+fn parse_program(str: &str) -> Result<Vec<Instruction>, ParseError> {
+    parse_lines(instruction(), str)
+}
+
+fn run_program(program: &[Instruction]) -> HashMap<u64, u64> {
     let mut current_mask = Mask::default();
     let mut state = HashMap::new();
-    for line in str.lines() {
-        let split: Vec<&str> = line.split(" = ").collect();
-        let (left, right) = (split[0], split[1]);
-        if left.starts_with("mask") {
-            current_mask = right.parse::<Mask>().expect("Is a mask");
-        } else {
-            let address = left["mem[".len()..left.len() - 1]
-                .parse::<u64>()
-                .expect("Is an int");
-            let mem_val = right.parse::<u64>().expect("Is an int");
-            state.insert(address, current_mask.apply(mem_val));
+    for instruction in program {
+        match instruction {
+            Instruction::SetMask(mask) => current_mask = mask.clone(),
+            Instruction::Write { address, value } => {
+                state.insert(*address, current_mask.apply(*value));
+            }
         }
     }
     state
 }
 
-fn run_mem_program(str: &str) -> HashMap<u64, u64> {
+fn run_mem_program(program: &[Instruction]) -> HashMap<u64, u64> {
     let mut current_mask = Mask::default();
     let mut state = HashMap::new();
-    for line in str.lines() {
-        let split: Vec<&str> = line.split(" = ").collect();
-        let (left, right) = (split[0], split[1]);
-        if left.starts_with("mask") {
-            current_mask = right.parse::<Mask>().expect("Is a mask");
-        } else {
-            let address = left["mem[".len()..left.len() - 1]
-                .parse::<u64>()
-                .expect("Is an int");
-            let mem_val = right.parse::<u64>().expect("Is an int");
-            for mask_addresses in current_mask.mask_memory_addresses(address) {
-                state.insert(mask_addresses, mem_val);
+    for instruction in program {
+        match instruction {
+            Instruction::SetMask(mask) => current_mask = mask.clone(),
+            Instruction::Write { address, value } => {
+                for mask_addresses in current_mask.mask_memory_addresses(*address) {
+                    state.insert(mask_addresses, *value);
+                }
             }
         }
     }
@@ -64,22 +91,16 @@ pub enum MaskBit {
     WildCard,
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
 struct Mask {
     mask: Vec<(usize, MaskBit)>,
 }
 
-impl Default for Mask {
-    fn default() -> Self {
-        Self { mask: Vec::new() }
-    }
-}
-
 impl Mask {
     fn apply(&self, bits: u64) -> u64 {
         let mut result = 0;
         for i in 0..64 {
-            result = result << 1;
+            result <<= 1;
             let n = match self
                 .mask
                 .iter()
@@ -93,7 +114,7 @@ impl Mask {
                     }
                 }
                 None => {
-                    if bits & (1 << 63 - i) != 0 {
+                    if bits & (1 << (63 - i)) != 0 {
                         1
                     } else {
                         0
@@ -140,8 +161,7 @@ impl Mask {
 
     /// most significant bit is at idx 0
     fn set_bit_to_one(bits: u64, idx: usize) -> u64 {
-        let bits = bits | (1 << (63 - idx));
-        bits
+        bits | (1 << (63 - idx))
     }
 
     fn set_bit_to_zero(bits: u64, idx: usize) -> u64 {
@@ -158,6 +178,9 @@ impl FromStr for Mask {
         let mut mask = Vec::new();
         // assume the string represents the last part of 64 bit number
         // but the mask is for the entire 64 bit.
+        if s.len() > 64 {
+            return Err(());
+        }
         let offset = 64 - s.len();
 
         for (idx, c) in s.chars().enumerate() {
@@ -177,7 +200,7 @@ impl FromStr for Mask {
 
 #[cfg(test)]
 mod tests {
-    use crate::solutions::day14::Mask;
+    use crate::solutions::day14::{parse_program, run_mem_program, run_program, Mask};
 
     #[test]
     fn apply_simple_mask() {
@@ -219,4 +242,34 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn run_example_programs() {
+        let program = parse_program(
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X\n\
+mem[8] = 11\n\
+mem[7] = 101\n\
+mem[8] = 0",
+        )
+        .unwrap();
+        assert_eq!(run_program(&program).values().sum::<u64>(), 165);
+
+        let program = parse_program(
+            "mask = 000000000000000000000000000000X1001X\n\
+mem[42] = 100\n\
+mask = 00000000000000000000000000000000X0XX\n\
+mem[26] = 1",
+        )
+        .unwrap();
+        assert_eq!(run_mem_program(&program).values().sum::<u64>(), 208);
+    }
+
+    #[test]
+    fn malformed_instructions_report_position() {
+        let error = parse_program("mem[8] = 11\nmem[x] = 3").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 5));
+
+        let error = parse_program(&format!("mask = {}", "X".repeat(65))).unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 8));
+    }
 }
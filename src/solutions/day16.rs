@@ -1,35 +1,66 @@
-use crate::util::{puzzle_input, GroupIterator};
+use crate::util::parse::{integer, literal, separated_list1, Parser};
+use crate::util::puzzle_input;
+use crate::util::sections::{SectionError, Sections};
 use std::str::FromStr;
 
 type Ticket = [i64];
 
 pub fn print_solution() {
     let input = puzzle_input::read_input("day16");
-    println!("Day 16 Solution Part 1: {}", n_ticket_error_rate(&input));
+    let error_rate = n_ticket_error_rate(&input).unwrap_or_else(|e| panic!("{}", e));
+    println!("Day 16 Solution Part 1: {}", error_rate);
 }
 
-fn n_ticket_error_rate(input: &str) -> i64 {
-    let mut iterator = GroupIterator::new(input);
-    let next = iterator.next();
-    let validator = next
-        .expect("first group is ticket")
-        .as_str()
-        .parse::<TicketValidator>()
-        .expect("Valid ticket rules");
-
-    iterator.next();
-    let tickets = iterator.next().expect("Tickets").as_str();
-
-    let mut error_rate = 0;
-    for ticket in tickets.lines().skip(1) {
-        let ticket: Vec<i64> = ticket
-            .split(",")
-            .map(|s| s.parse::<i64>().expect("An int"))
-            .collect();
-        error_rate += validator.error_rate(&ticket);
+const RULES: &str = "rules";
+const YOUR_TICKET: &str = "your ticket";
+const NEARBY_TICKETS: &str = "nearby tickets";
+
+/// The rules, your ticket and the nearby tickets.
+pub struct TicketNotes {
+    validator: TicketValidator,
+    your_ticket: Vec<i64>,
+    nearby_tickets: Vec<Vec<i64>>,
+}
+
+/// Comma separated values, e.g. `7,1,14`
+fn ticket<'a>() -> impl Parser<'a, Vec<i64>> {
+    separated_list1(integer(), literal(","))
+}
+
+impl TicketNotes {
+    pub fn parse(input: &str) -> Result<Self, SectionError> {
+        let sections = Sections::new(input, RULES)?;
+        sections.expect_exactly(&[RULES, YOUR_TICKET, NEARBY_TICKETS])?;
+
+        let validator = sections.get(RULES)?.parse::<TicketValidator>()?;
+        let mut your_ticket = sections.get(YOUR_TICKET)?.records(ticket())?;
+        if your_ticket.len() != 1 {
+            return Err(SectionError::Malformed(YOUR_TICKET.to_string()));
+        }
+        let nearby_tickets = sections.get(NEARBY_TICKETS)?.records(ticket())?;
+
+        Ok(TicketNotes {
+            validator,
+            your_ticket: your_ticket.remove(0),
+            nearby_tickets,
+        })
     }
 
-    error_rate
+    pub fn your_ticket(&self) -> &Ticket {
+        &self.your_ticket
+    }
+}
+
+fn n_ticket_error_rate(input: &str) -> Result<i64, SectionError> {
+    let notes = TicketNotes::parse(input)?;
+
+    let error_rate = notes
+        .nearby_tickets
+        .iter()
+        .map(|ticket| notes.validator.error_rate(ticket))
+        .sum();
+
+    Ok(error_rate)
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -43,9 +74,9 @@ impl FromStr for Range {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut split = s.split("-");
-        let lower = split.next().ok_or_else(|| ())?;
+        let lower = split.next().ok_or(())?;
         let lower = lower.parse::<i64>().expect("Is an int");
-        let upper = split.next().ok_or_else(|| ())?;
+        let upper = split.next().ok_or(())?;
         let upper = upper.parse::<i64>().expect("Is an int");
 
         Ok(Self { lower, upper })
@@ -108,7 +139,8 @@ impl TicketValidator {
 
 #[cfg(test)]
 mod tests {
-    use crate::solutions::day16::{n_ticket_error_rate, TicketValidator};
+    use crate::solutions::day16::{n_ticket_error_rate, TicketNotes, TicketValidator};
+    use crate::util::sections::SectionError;
     use std::str::FromStr;
 
     #[test]
@@ -143,6 +175,60 @@ nearby tickets:
 38,6,12",
         );
 
-        assert_eq!(error_rate, 71);
+        assert_eq!(error_rate, Ok(71));
+    }
+
+    #[test]
+    fn sections_of_notes_are_validated() {
+        let notes = TicketNotes::parse(
+            r"class: 1-3 or 5-7
+
+nearby tickets:
+7,3,47",
+        );
+        assert_eq!(
+            notes.err(),
+            Some(SectionError::Missing("your ticket".to_string()))
+        );
+
+        let notes = TicketNotes::parse(
+            r"class: 1-3 or 5-7
+
+your ticket:
+7,1,14
+
+nearby tickets:
+7,3,47
+
+other tickets:
+1,2,3",
+        );
+        assert_eq!(
+            notes.err(),
+            Some(SectionError::Unexpected("other tickets".to_string()))
+        );
+    }
+
+    #[test]
+    fn your_ticket_is_read_by_name() {
+        let notes = TicketNotes::parse(
+            r"nearby tickets:
+7,3,47
+
+class: 1-3 or 5-7
+your ticket:
+7,1,14",
+        );
+        assert!(notes.is_err());
+
+        let notes = TicketNotes::parse(
+            r"class: 1-3 or 5-7
+nearby tickets:
+7,3,47
+your ticket:
+7,1,14",
+        )
+        .unwrap();
+        assert_eq!(notes.your_ticket(), &[7, 1, 14]);
     }
 }
@@ -1,3 +1,4 @@
+use crate::util::point::{Point2, Point3, Point4, PointN};
 use crate::util::puzzle_input;
 use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
@@ -16,16 +17,16 @@ pub fn print_solution() {
     )
 }
 
-fn append_dimension(start: &Vec<(i64, i64, i64)>) -> Vec<(i64, i64, i64, i64)> {
-    start.iter().map(|p| (p.0, p.1, p.2, 0)).collect()
+fn append_dimension(start: &[Point3]) -> Vec<Point4> {
+    start.iter().map(|p| p.extend(0)).collect()
 }
 
-fn get_space(input: &str) -> Vec<(i64, i64, i64)> {
+fn get_space(input: &str) -> Vec<Point3> {
     let mut vec = Vec::new();
     for (y, line) in input.lines().enumerate() {
         for (x, c) in line.chars().enumerate() {
             if c == '#' {
-                vec.push((x as i64, y as i64, 0));
+                vec.push(PointN::new([x as i64, y as i64, 0]));
             }
         }
     }
@@ -34,65 +35,39 @@ fn get_space(input: &str) -> Vec<(i64, i64, i64)> {
 }
 
 #[derive(Eq, PartialEq, Debug)]
-struct Space {
-    active_cells: HashSet<(i64, i64, i64)>,
+pub struct Space {
+    active_cells: HashSet<Point3>,
 }
 
 impl Space {
-    fn new(active_cells: &[(i64, i64, i64)]) -> Self {
-        let s = HashSet::from_iter(active_cells.iter().map(|c| *c));
+    pub fn new(active_cells: &[Point3]) -> Self {
+        let s = HashSet::from_iter(active_cells.iter().copied());
 
         Self { active_cells: s }
     }
 
-    fn advance_time(&mut self, rounds: usize) {
-        let mut cells = HashSet::from_iter(self.active_cells.iter().map(|&c| c));
+    pub fn advance_time(&mut self, rounds: usize) {
+        let cells: Vec<Point3> = self.active_cells.iter().copied().collect();
 
-        for _ in 0..rounds {
-            let mut map = HashMap::new();
-
-            for &cell in &cells {
-                map.insert(cell, 0);
-            }
-
-            for cell in &cells {
-                for x in -1..=1 {
-                    for y in -1..=1 {
-                        for z in -1..=1 {
-                            if x == 0 && y == 0 && z == 0 {
-                                continue;
-                            }
-                            let p = (cell.0 + x, cell.1 + y, cell.2 + z);
-                            let value = map.get(&p);
-                            let new_value = match value {
-                                None => 1,
-                                Some(v) => v + 1,
-                            };
-
-                            map.insert(p, new_value);
-                        }
-                    }
-                }
-            }
-
-            let mut active_cells = HashSet::new();
-
-            for (k, &v) in map.iter() {
-                if v == 3 {
-                    active_cells.insert(*k);
-                } else if v == 2 && cells.contains(k) {
-                    active_cells.insert(*k);
-                }
-            }
-            cells = active_cells;
-        }
+        self.active_cells = HashSet::from_iter(run_simulation(&cells, rounds as i64));
+    }
 
-        self.active_cells = cells;
+    /// The active cells of the plane at height `z`, e.g. for rendering.
+    pub fn slice(&self, z: i64) -> Vec<Point2<i64>> {
+        let mut slice: Vec<Point2<i64>> = self
+            .active_cells
+            .iter()
+            .map(PointN::coordinates)
+            .filter(|c| c[2] == z)
+            .map(|c| Point2::new(c[0], c[1]))
+            .collect();
+        slice.sort_unstable();
+        slice
     }
 }
 
-fn run_simulation(start: &[(i64, i64, i64, i64)], rounds: i64) -> Vec<(i64, i64, i64, i64)> {
-    let mut cells = HashSet::from_iter(start.iter().map(|&c| c));
+fn run_simulation<const N: usize>(start: &[PointN<N>], rounds: i64) -> Vec<PointN<N>> {
+    let mut cells = HashSet::from_iter(start.iter().copied());
 
     for _ in 0..rounds {
         let mut map = HashMap::new();
@@ -102,69 +77,65 @@ fn run_simulation(start: &[(i64, i64, i64, i64)], rounds: i64) -> Vec<(i64, i64,
         }
 
         for cell in &cells {
-            for x in -1..=1 {
-                for y in -1..=1 {
-                    for z in -1..=1 {
-                        for w in -1..=1 {
-                            if x == 0 && y == 0 && z == 0 && w == 0 {
-                                continue;
-                            }
-                            let p = (cell.0 + x, cell.1 + y, cell.2 + z, cell.3 + w);
-                            let value = map.get(&p);
-                            let new_value = match value {
-                                None => 1,
-                                Some(v) => v + 1,
-                            };
-
-                            map.insert(p, new_value);
-                        }
-                    }
-                }
+            for p in cell.neighbours() {
+                *map.entry(p).or_insert(0) += 1;
             }
         }
 
         let mut active_cells = HashSet::new();
 
         for (k, &v) in map.iter() {
-            if v == 3 {
-                active_cells.insert(*k);
-            } else if v == 2 && cells.contains(k) {
+            if v == 3 || (v == 2 && cells.contains(k)) {
                 active_cells.insert(*k);
             }
         }
         cells = active_cells;
     }
 
-    cells.iter().map(|c| c.clone()).collect()
+    cells.into_iter().collect()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::day17::Space;
+    use crate::util::point::{Point2, Point3, PointN};
+
+    fn points(cells: &[[i64; 3]]) -> Vec<Point3> {
+        cells.iter().map(|&c| PointN::new(c)).collect()
+    }
 
     #[test]
     fn first_iteration() {
-        let active_cells = [(1, 0, 0), (2, 1, 0), (0, 2, 0), (1, 2, 0), (2, 2, 0)];
+        let active_cells = [[1, 0, 0], [2, 1, 0], [0, 2, 0], [1, 2, 0], [2, 2, 0]];
 
-        let mut first_space = Space::new(&active_cells);
+        let mut first_space = Space::new(&points(&active_cells));
 
         first_space.advance_time(1);
 
         let next_active_cells = [
-            (0, 1, 1),
-            (2, 2, 1),
-            (1, 3, 1),
-            (0, 1, 0),
-            (2, 1, 0),
-            (1, 2, 0),
-            (2, 2, 0),
-            (1, 3, 0),
-            (0, 1, -1),
-            (2, 2, -1),
-            (1, 3, -1),
+            [0, 1, 1],
+            [2, 2, 1],
+            [1, 3, 1],
+            [0, 1, 0],
+            [2, 1, 0],
+            [1, 2, 0],
+            [2, 2, 0],
+            [1, 3, 0],
+            [0, 1, -1],
+            [2, 2, -1],
+            [1, 3, -1],
         ];
-        let secon_space = Space::new(&next_active_cells);
+        let secon_space = Space::new(&points(&next_active_cells));
 
         assert_eq!(first_space, secon_space);
     }
+
+    #[test]
+    fn slice_of_space() {
+        let space = Space::new(&points(&[[1, 0, 0], [2, 1, -1], [0, 2, 0]]));
+
+        assert_eq!(space.slice(0), vec![Point2::new(0, 2), Point2::new(1, 0)]);
+        assert_eq!(space.slice(-1), vec![Point2::new(2, 1)]);
+        assert_eq!(space.slice(1), vec![]);
+    }
 }
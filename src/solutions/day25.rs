@@ -26,7 +26,7 @@ fn find_discrete_log(base: u64, h: u64, modulo: u64) -> u64 {
     // invariant: tmp = base.pow(loop_count) % modulo
     while tmp != h {
         tmp *= base;
-        tmp = tmp % modulo;
+        tmp %= modulo;
 
         loop_count += 1;
     }
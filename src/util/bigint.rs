@@ -0,0 +1,173 @@
+use std::fmt;
+use std::iter::Product;
+use std::ops::Mul;
+
+const BASE: u128 = 1_000_000_000;
+
+/// A signed integer of any size, e.g. for products that overflow `i64`.
+/// Only what is needed for products is supported.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct BigInt {
+    negative: bool,
+    /// Base 10^9 digits, least significant first, without leading zeros.
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt {
+            negative: false,
+            digits: Vec::new(),
+        }
+    }
+
+    pub fn one() -> Self {
+        BigInt::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The value as an `i64`, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: i128 = 0;
+        for &digit in self.digits.iter().rev() {
+            magnitude = magnitude
+                .checked_mul(BASE as i128)?
+                .checked_add(digit as i128)?;
+        }
+        let value = if self.negative { -magnitude } else { magnitude };
+        if value < i64::MIN as i128 || value > i64::MAX as i128 {
+            None
+        } else {
+            Some(value as i64)
+        }
+    }
+
+    fn mul_magnitude(&self, factor: u64) -> Vec<u32> {
+        if factor == 0 {
+            return Vec::new();
+        }
+        let mut digits = Vec::with_capacity(self.digits.len() + 3);
+        let mut carry: u128 = 0;
+        for &digit in &self.digits {
+            let product = digit as u128 * factor as u128 + carry;
+            digits.push((product % BASE) as u32);
+            carry = product / BASE;
+        }
+        while carry > 0 {
+            digits.push((carry % BASE) as u32);
+            carry /= BASE;
+        }
+        digits
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let mut digits = Vec::new();
+        let mut magnitude = value.unsigned_abs();
+        while magnitude > 0 {
+            digits.push((magnitude % BASE as u64) as u32);
+            magnitude /= BASE as u64;
+        }
+        BigInt {
+            negative: value < 0,
+            digits,
+        }
+    }
+}
+
+impl Mul<i64> for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        let digits = self.mul_magnitude(rhs.unsigned_abs());
+        BigInt {
+            negative: !digits.is_empty() && (self.negative != (rhs < 0)),
+            digits,
+        }
+    }
+}
+
+impl Mul<i64> for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        &self * rhs
+    }
+}
+
+impl Product<i64> for BigInt {
+    fn product<I: Iterator<Item = i64>>(iter: I) -> Self {
+        iter.fold(BigInt::one(), |product, value| product * value)
+    }
+}
+
+impl<'a> Product<&'a i64> for BigInt {
+    fn product<I: Iterator<Item = &'a i64>>(iter: I) -> Self {
+        iter.copied().product()
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = self.digits.iter().rev();
+        match digits.next() {
+            None => write!(f, "0"),
+            Some(most_significant) => {
+                if self.negative {
+                    write!(f, "-")?;
+                }
+                write!(f, "{}", most_significant)?;
+                for digit in digits {
+                    write!(f, "{:09}", digit)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::bigint::BigInt;
+
+    #[test]
+    fn small_products() {
+        let product: BigInt = [1721, 299].iter().product();
+        assert_eq!(product.to_i64(), Some(514579));
+        assert_eq!(product.to_string(), "514579");
+
+        let product: BigInt = vec![-3, 4, 5].into_iter().product();
+        assert_eq!(product.to_string(), "-60");
+        assert_eq!([-7, 0].iter().product::<BigInt>(), BigInt::zero());
+        assert_eq!(Vec::<i64>::new().iter().product::<BigInt>(), BigInt::one());
+    }
+
+    #[test]
+    fn products_beyond_i64() {
+        let product: BigInt = [i64::MAX, i64::MAX].iter().product();
+        assert_eq!(
+            product.to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(product.to_i64(), None);
+
+        let product: BigInt = [i64::MIN, -1].iter().product();
+        assert_eq!(product.to_string(), "9223372036854775808");
+        assert!(!product.is_negative());
+        assert_eq!((BigInt::from(i64::MIN) * 1).to_i64(), Some(i64::MIN));
+    }
+
+    #[test]
+    fn digits_are_zero_padded() {
+        let product: BigInt = [1_000_000_007, 1_000_000_000].iter().product();
+        assert_eq!(product.to_string(), "1000000007000000000");
+    }
+}
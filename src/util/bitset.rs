@@ -0,0 +1,157 @@
+use std::iter::FromIterator;
+
+const WORD_BITS: usize = 64;
+
+/// A set of small non-negative integers, stored as bits in 64-bit words.
+/// Union and intersection work a word at a time.
+#[derive(Eq, PartialEq, Debug, Clone, Default, Hash)]
+pub struct BitSet {
+    /// Never ends in a zero word, so equal sets have equal words.
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        BitSet::default()
+    }
+
+    /// The set of `0..n`.
+    pub fn full(n: usize) -> Self {
+        let mut words = vec![u64::MAX; n / WORD_BITS];
+        if n % WORD_BITS != 0 {
+            words.push((1 << (n % WORD_BITS)) - 1);
+        }
+        BitSet { words }
+    }
+
+    pub fn insert(&mut self, value: usize) {
+        let word = value / WORD_BITS;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (value % WORD_BITS);
+    }
+
+    pub fn remove(&mut self, value: usize) {
+        if let Some(word) = self.words.get_mut(value / WORD_BITS) {
+            *word &= !(1 << (value % WORD_BITS));
+            self.trim();
+        }
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        self.words
+            .get(value / WORD_BITS)
+            .is_some_and(|word| word >> (value % WORD_BITS) & 1 == 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    pub fn intersect_with(&mut self, other: &BitSet) {
+        self.words.truncate(other.words.len());
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word &= other;
+        }
+        self.trim();
+    }
+
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        let mut union = self.clone();
+        union.union_with(other);
+        union
+    }
+
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        let mut intersection = self.clone();
+        intersection.intersect_with(other);
+        intersection
+    }
+
+    /// The values in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(idx, &word)| {
+            (0..WORD_BITS)
+                .filter(move |bit| word >> bit & 1 == 1)
+                .map(move |bit| idx * WORD_BITS + bit)
+        })
+    }
+
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::bitset::BitSet;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut set = BitSet::new();
+        set.insert(3);
+        set.insert(130);
+        set.insert(3);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(130));
+        assert!(!set.contains(4));
+        assert!(!set.contains(1000));
+
+        set.remove(130);
+        assert_eq!(set, vec![3].into_iter().collect());
+        set.remove(3);
+        assert!(set.is_empty());
+        assert_eq!(set, BitSet::new());
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a: BitSet = vec![1, 2, 70, 200].into_iter().collect();
+        let b: BitSet = vec![2, 70, 71].into_iter().collect();
+        assert_eq!(
+            a.union(&b).iter().collect::<Vec<_>>(),
+            vec![1, 2, 70, 71, 200]
+        );
+        assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), vec![2, 70]);
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+
+        let c: BitSet = vec![200].into_iter().collect();
+        assert_eq!(b.intersection(&c), BitSet::new());
+    }
+
+    #[test]
+    fn full_sets() {
+        assert_eq!(BitSet::full(0), BitSet::new());
+        assert_eq!(BitSet::full(64).len(), 64);
+        assert_eq!(BitSet::full(65).iter().last(), Some(64));
+    }
+}
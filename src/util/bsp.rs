@@ -0,0 +1,315 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+/// One dimension of a binary space partitioning code. Every symbol halves
+/// the remaining range: `low` keeps the lower half and `high` the upper half.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Segment {
+    name: String,
+    low: char,
+    high: char,
+    length: usize,
+}
+
+impl Segment {
+    pub fn new(name: &str, low: char, high: char, length: usize) -> Self {
+        Segment {
+            name: name.to_string(),
+            low,
+            high,
+            length,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn low(&self) -> char {
+        self.low
+    }
+
+    pub fn high(&self) -> char {
+        self.high
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The number of coordinates in the dimension, if it fits in a `u64`.
+    pub fn size(&self) -> Option<u64> {
+        u32::try_from(self.length)
+            .ok()
+            .and_then(|length| 1u64.checked_shl(length))
+    }
+}
+
+/// The positions count from 0 in the whole code.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum BspError {
+    WrongLength(usize),
+    UnknownSymbol {
+        position: usize,
+        symbol: char,
+    },
+    /// A code needs at least one segment.
+    NoSegments,
+    /// The named segment uses the same symbol for both halves.
+    SameSymbols(String),
+    /// The code has more than `MAX_CODE_LENGTH` symbols, so its IDs do not fit in 32 bits.
+    TooLong(usize),
+}
+
+/// The most symbols a code can have.
+pub const MAX_CODE_LENGTH: usize = 32;
+
+impl fmt::Display for BspError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BspError::WrongLength(length) => write!(f, "wrong length {}", length),
+            BspError::UnknownSymbol { position, symbol } => {
+                write!(f, "unknown symbol '{}' at {}", symbol, position)
+            }
+            BspError::NoSegments => write!(f, "a code needs at least one segment"),
+            BspError::SameSymbols(name) => {
+                write!(f, "the halves of segment {} have the same symbol", name)
+            }
+            BspError::TooLong(length) => write!(
+                f,
+                "a code of {} symbols is longer than {}",
+                length, MAX_CODE_LENGTH
+            ),
+        }
+    }
+}
+
+/// A code of one or more segments, e.g. a row followed by a column.
+///
+/// The ID of a location numbers the locations in order of the segments,
+/// so with a row and a column segment it is `row * columns + column`.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct BspCode {
+    segments: Vec<Segment>,
+}
+
+impl BspCode {
+    pub fn new(segments: Vec<Segment>) -> Result<Self, BspError> {
+        if segments.is_empty() {
+            return Err(BspError::NoSegments);
+        }
+        if let Some(segment) = segments.iter().find(|s| s.low == s.high) {
+            return Err(BspError::SameSymbols(segment.name.clone()));
+        }
+        let length = segments
+            .iter()
+            .fold(0, |total: usize, s| total.saturating_add(s.length));
+        if length > MAX_CODE_LENGTH {
+            return Err(BspError::TooLong(length));
+        }
+        Ok(BspCode { segments })
+    }
+
+    /// The size of a segment of the code, which the length limit keeps small.
+    fn size(segment: &Segment) -> u64 {
+        segment.size().expect("the segments of a code are short")
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The number of symbols in a code.
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(Segment::length).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of locations, which is one more than the highest ID.
+    pub fn capacity(&self) -> u64 {
+        self.segments.iter().map(BspCode::size).product()
+    }
+
+    /// The coordinates of the code, one per segment.
+    pub fn decode(&self, code: &str) -> Result<Vec<u32>, BspError> {
+        let length = code.chars().count();
+        if length != self.len() {
+            return Err(BspError::WrongLength(length));
+        }
+
+        let mut symbols = code.chars().enumerate();
+        let mut coordinates = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            let mut coordinate = 0;
+            for (position, symbol) in symbols.by_ref().take(segment.length) {
+                let bit = if symbol == segment.low {
+                    0
+                } else if symbol == segment.high {
+                    1
+                } else {
+                    return Err(BspError::UnknownSymbol { position, symbol });
+                };
+                coordinate = coordinate << 1 | bit;
+            }
+            coordinates.push(coordinate);
+        }
+        Ok(coordinates)
+    }
+
+    pub fn decode_id(&self, code: &str) -> Result<u32, BspError> {
+        self.decode(code).map(|coordinates| self.id(&coordinates))
+    }
+
+    /// Panics if there is not one coordinate per segment.
+    /// The coordinates are expected to be within their segments.
+    pub fn id(&self, coordinates: &[u32]) -> u32 {
+        assert_eq!(coordinates.len(), self.segments.len());
+        let id = self
+            .segments
+            .iter()
+            .zip(coordinates)
+            .fold(0, |id, (segment, &coordinate)| {
+                id * BspCode::size(segment) + coordinate as u64
+            });
+        id as u32
+    }
+
+    /// The coordinates of the location with the ID, if there is one.
+    pub fn coordinates(&self, id: u32) -> Option<Vec<u32>> {
+        let mut rest = id as u64;
+        if rest >= self.capacity() {
+            return None;
+        }
+        let mut coordinates: Vec<u32> = self
+            .segments
+            .iter()
+            .rev()
+            .map(|segment| {
+                let coordinate = rest % BspCode::size(segment);
+                rest /= BspCode::size(segment);
+                coordinate as u32
+            })
+            .collect();
+        coordinates.reverse();
+        Some(coordinates)
+    }
+
+    /// The code of the location with the ID, if there is one.
+    pub fn encode(&self, id: u32) -> Option<String> {
+        let coordinates = self.coordinates(id)?;
+        let mut code = String::with_capacity(self.len());
+        for (segment, coordinate) in self.segments.iter().zip(coordinates) {
+            for bit in (0..segment.length).rev() {
+                code.push(if coordinate >> bit & 1 == 0 {
+                    segment.low
+                } else {
+                    segment.high
+                });
+            }
+        }
+        Some(code)
+    }
+
+    /// The formula of the ID in terms of the segment names, e.g. `row * 8 + column`.
+    pub fn formula(&self) -> String {
+        let mut factor = 1;
+        let mut terms: Vec<String> = self
+            .segments
+            .iter()
+            .rev()
+            .map(|segment| {
+                let term = if factor == 1 {
+                    segment.name.clone()
+                } else {
+                    format!("{} * {}", segment.name, factor)
+                };
+                factor *= BspCode::size(segment);
+                term
+            })
+            .collect();
+        terms.reverse();
+        terms.join(" + ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::bsp::{BspCode, BspError, Segment, MAX_CODE_LENGTH};
+
+    fn boarding_pass() -> BspCode {
+        BspCode::new(vec![
+            Segment::new("row", 'F', 'B', 7),
+            Segment::new("column", 'L', 'R', 3),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn decode_boarding_passes() {
+        let code = boarding_pass();
+        assert_eq!(code.len(), 10);
+        assert_eq!(code.capacity(), 1024);
+        assert_eq!(code.decode("BFFFBBFRRR"), Ok(vec![70, 7]));
+        assert_eq!(code.decode_id("BFFFBBFRRR"), Ok(567));
+        assert_eq!(code.formula(), "row * 8 + column");
+    }
+
+    #[test]
+    fn reject_unknown_symbols_and_lengths() {
+        let code = boarding_pass();
+        assert_eq!(code.decode("BFFFBBF"), Err(BspError::WrongLength(7)));
+        assert_eq!(
+            code.decode("BFFFBBFRRF"),
+            Err(BspError::UnknownSymbol {
+                position: 9,
+                symbol: 'F'
+            })
+        );
+    }
+
+    #[test]
+    fn three_dimensions() {
+        let code = BspCode::new(vec![
+            Segment::new("deck", 'D', 'U', 1),
+            Segment::new("row", 'F', 'B', 4),
+            Segment::new("seat", 'L', 'R', 2),
+        ])
+        .unwrap();
+        assert_eq!(code.decode("UBFFBRL"), Ok(vec![1, 9, 2]));
+        assert_eq!(code.decode_id("UBFFBRL"), Ok(64 + 9 * 4 + 2));
+        assert_eq!(code.formula(), "deck * 64 + row * 4 + seat");
+        assert_eq!(code.coordinates(102), Some(vec![1, 9, 2]));
+        assert_eq!(code.coordinates(128), None);
+        for id in 0..128 {
+            assert_eq!(code.decode_id(&code.encode(id).unwrap()), Ok(id));
+        }
+    }
+
+    #[test]
+    fn invalid_codes() {
+        assert_eq!(BspCode::new(vec![]), Err(BspError::NoSegments));
+        assert_eq!(
+            BspCode::new(vec![Segment::new("row", 'F', 'F', 7)]),
+            Err(BspError::SameSymbols("row".to_string()))
+        );
+        assert_eq!(
+            BspCode::new(vec![
+                Segment::new("row", 'F', 'B', usize::MAX),
+                Segment::new("column", 'L', 'R', 3),
+            ]),
+            Err(BspError::TooLong(usize::MAX))
+        );
+        let longest = BspCode::new(vec![Segment::new("row", 'F', 'B', MAX_CODE_LENGTH)]).unwrap();
+        assert_eq!(longest.capacity(), 1 << 32);
+        assert_eq!(longest.encode(u32::MAX), Some("B".repeat(MAX_CODE_LENGTH)));
+    }
+
+    #[test]
+    fn sizes_of_long_segments() {
+        assert_eq!(Segment::new("row", 'F', 'B', 63).size(), Some(1 << 63));
+        assert_eq!(Segment::new("row", 'F', 'B', 64).size(), None);
+    }
+}
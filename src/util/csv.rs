@@ -0,0 +1,137 @@
+/// Writes a record as a line of comma separated values, ending in `\n`.
+/// Fields with a comma, quote or line break are quoted.
+pub fn write_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// The line, counting from 1, where a quoted field is not closed
+/// or a closing quote is followed by something else than a comma.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct CsvError {
+    pub line: usize,
+}
+
+/// Reads records of comma separated values, with fields quoted as by
+/// `write_record`. Lines may end in `\n` or `\r\n`, and blank lines are skipped.
+/// Every record is returned with the line it starts on.
+pub fn read_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let mut records = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start_line = line;
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut blank = true;
+
+        loop {
+            if !matches!(chars.peek(), Some('\n') | Some('\r') | None) {
+                blank = false;
+            }
+            match chars.next() {
+                None => {
+                    record.push(field);
+                    break;
+                }
+                Some('"') if field.is_empty() => {
+                    loop {
+                        match chars.next() {
+                            None => return Err(CsvError { line: start_line }),
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                field.push(c);
+                            }
+                        }
+                    }
+                    match chars.peek() {
+                        None | Some(',') | Some('\n') | Some('\r') => {}
+                        Some(_) => return Err(CsvError { line }),
+                    }
+                }
+                Some(',') => record.push(std::mem::take(&mut field)),
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') => {
+                    line += 1;
+                    record.push(field);
+                    break;
+                }
+                Some(c) => field.push(c),
+            }
+        }
+
+        if !blank {
+            records.push((start_line, record));
+        }
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::csv::{read_records, write_record, CsvError};
+
+    #[test]
+    fn quote_fields_when_needed() {
+        assert_eq!(write_record(&["a", "", "b c"]), "a,,b c\n");
+        assert_eq!(
+            write_record(&["a,b", "say \"hi\"", "x\ny"]),
+            "\"a,b\",\"say \"\"hi\"\"\",\"x\ny\"\n"
+        );
+    }
+
+    #[test]
+    fn read_what_is_written() {
+        let rows = vec![
+            vec!["id", "text", ""],
+            vec!["1", "a,b", "say \"hi\""],
+            vec!["2", "x\ny", ""],
+        ];
+        let text: String = rows.iter().map(|r| write_record(r)).collect();
+        let records = read_records(&text).unwrap();
+
+        assert_eq!(
+            records.iter().map(|(_, r)| r.clone()).collect::<Vec<_>>(),
+            rows
+        );
+        let lines: Vec<usize> = records.iter().map(|&(line, _)| line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn crlf_and_blank_lines() {
+        let records = read_records("a,b\r\n\r\nc,\"\"\r\n").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (3, vec!["c".to_string(), "".to_string()])
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_quotes() {
+        assert_eq!(read_records("a\n\"b,c\n"), Err(CsvError { line: 2 }));
+        assert_eq!(read_records("\"b\"x,c\n"), Err(CsvError { line: 1 }));
+    }
+}
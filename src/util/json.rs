@@ -0,0 +1,436 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A JSON value. Numbers are limited to integers, and the members
+/// of an object keep their order.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The value of the first member named `key`, if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Writes the value on a single line, without any spaces.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write_string(f, s),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(members) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// The position is the index of the character, counting from 0.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct JsonError {
+    pub position: usize,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at {}", self.expected, self.position)
+    }
+}
+
+impl FromStr for Value {
+    type Err = JsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader {
+            chars: s.chars().collect(),
+            idx: 0,
+        };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.idx < reader.chars.len() {
+            return Err(reader.error("end of input"));
+        }
+        Ok(value)
+    }
+}
+
+struct Reader {
+    chars: Vec<char>,
+    idx: usize,
+}
+
+impl Reader {
+    fn error(&self, expected: &'static str) -> JsonError {
+        JsonError {
+            position: self.idx,
+            expected,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.idx).is_some_and(|c| c.is_whitespace()) {
+            self.idx += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.idx).copied()
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), JsonError> {
+        if self.peek() == Some(c) {
+            self.idx += 1;
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        let end = self.idx + word.len();
+        if end <= self.chars.len() && self.chars[self.idx..end].iter().copied().eq(word.chars()) {
+            self.idx = end;
+            Ok(value)
+        } else {
+            Err(self.error("a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        match self.peek() {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.idx;
+        if self.chars[self.idx] == '-' {
+            self.idx += 1;
+        }
+        while self.chars.get(self.idx).is_some_and(|c| c.is_ascii_digit()) {
+            self.idx += 1;
+        }
+        let number: String = self.chars[start..self.idx].iter().collect();
+        number.parse().map(Value::Number).map_err(|_| JsonError {
+            position: start,
+            expected: "an integer",
+        })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"', "a string")?;
+        let mut s = String::new();
+        loop {
+            let c = *self.chars.get(self.idx).ok_or(self.error("\""))?;
+            self.idx += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = *self.chars.get(self.idx).ok_or(self.error("an escape"))?;
+                    self.idx += 1;
+                    s.push(match escaped {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => self.unicode_escape()?,
+                        _ => {
+                            self.idx -= 1;
+                            return Err(self.error("an escape"));
+                        }
+                    });
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// The char of a `\u` escape, after the `u`. A high surrogate must be
+    /// followed by the escape of a low surrogate, and together they are one char.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let start = self.idx;
+        let code = self.hex_digits()?;
+        let code = if (0xd800..0xdc00).contains(&code) {
+            if self.chars.get(self.idx..self.idx + 2) != Some(&['\\', 'u']) {
+                return Err(self.error("a low surrogate"));
+            }
+            self.idx += 2;
+            let low = self.hex_digits()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                self.idx -= 4;
+                return Err(self.error("a low surrogate"));
+            }
+            0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            code
+        };
+        char::from_u32(code).ok_or(JsonError {
+            position: start,
+            expected: "a high surrogate",
+        })
+    }
+
+    fn hex_digits(&mut self) -> Result<u32, JsonError> {
+        let end = self.idx + 4;
+        let hex: String = self
+            .chars
+            .get(self.idx..end)
+            .filter(|digits| digits.iter().all(char::is_ascii_hexdigit))
+            .ok_or(self.error("four hex digits"))?
+            .iter()
+            .collect();
+        self.idx = end;
+        Ok(u32::from_str_radix(&hex, 16).expect("four hex digits fit in a u32"))
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.expect('[', "[")?;
+        let mut values = Vec::new();
+        if self.peek() == Some(']') {
+            self.idx += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(',') => self.idx += 1,
+                Some(']') => {
+                    self.idx += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error(", or ]")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.expect('{', "{")?;
+        let mut members = Vec::new();
+        if self.peek() == Some('}') {
+            self.idx += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':', ":")?;
+            members.push((key, self.value()?));
+            match self.peek() {
+                Some(',') => self.idx += 1,
+                Some('}') => {
+                    self.idx += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.error(", or }")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::json::{JsonError, Value};
+
+    #[test]
+    fn write_values() {
+        let value = Value::Object(vec![
+            ("name".to_string(), "a \"b\"\n".into()),
+            ("n".to_string(), (-12).into()),
+            (
+                "list".to_string(),
+                Value::Array(vec![true.into(), Value::Null]),
+            ),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"a \"b\"\n","n":-12,"list":[true,null]}"#
+        );
+    }
+
+    #[test]
+    fn read_values() {
+        let value: Value = r#" { "a" : [1, -2, {}], "b": "xA\\", "c": false } "#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::Array(vec![
+                1.into(),
+                (-2).into(),
+                Value::Object(vec![])
+            ]))
+        );
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("xA\\"));
+        assert_eq!(value.get("c").and_then(Value::as_bool), Some(false));
+        assert_eq!(value.get("d"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{"a":"\t\u0001é","b":[[],{"c":null}]}"#;
+        let value: Value = text.parse().unwrap();
+        assert_eq!(value.to_string(), text);
+    }
+
+    #[test]
+    fn surrogate_pairs_are_one_char() {
+        let value: Value = r#""\ud83d\ude00""#.parse().unwrap();
+        assert_eq!(value.as_str(), Some("\u{1f600}"));
+        assert_eq!(value.to_string().parse::<Value>(), Ok(value));
+    }
+
+    #[test]
+    fn lone_surrogates_are_an_error() {
+        let error = |text: &str| text.parse::<Value>().unwrap_err();
+        assert_eq!(
+            error(r#""\ud83d""#),
+            JsonError {
+                position: 7,
+                expected: "a low surrogate"
+            }
+        );
+        assert_eq!(error(r#""\ud83d\u0041""#).expected, "a low surrogate");
+        assert_eq!(
+            error(r#""\ude00""#),
+            JsonError {
+                position: 3,
+                expected: "a high surrogate"
+            }
+        );
+        assert_eq!(error(r#""\u12g4""#).expected, "four hex digits");
+    }
+
+    #[test]
+    fn malformed_json() {
+        assert_eq!(
+            "[1, 2".parse::<Value>(),
+            Err(JsonError {
+                position: 5,
+                expected: ", or ]"
+            })
+        );
+        assert_eq!(r#"{"a" 1}"#.parse::<Value>().unwrap_err().expected, ":");
+        assert_eq!("1.5".parse::<Value>().unwrap_err().expected, "end of input");
+        assert_eq!("nul".parse::<Value>().unwrap_err().expected, "a value");
+        assert_eq!("\"abc".parse::<Value>().unwrap_err().expected, "\"");
+    }
+}
@@ -1,6 +1,19 @@
+use crate::util::point::{Point2, Vector2};
 use core::fmt;
 use std::fmt::{Debug, Formatter};
 
+/// The offsets to the eight neighbours of a coordinate.
+pub const NEIGHBOUR_OFFSETS: [Vector2<isize>; 8] = [
+    Vector2::new(-1, -1),
+    Vector2::new(0, -1),
+    Vector2::new(1, -1),
+    Vector2::new(-1, 0),
+    Vector2::new(1, 0),
+    Vector2::new(-1, 1),
+    Vector2::new(0, 1),
+    Vector2::new(1, 1),
+];
+
 #[derive(Eq, PartialEq)]
 pub struct Map<Terrain> {
     map: Vec<Terrain>,
@@ -71,27 +84,16 @@ where
         }
     }
 
-    pub fn terrain_at_point(&self, p: (usize, usize)) -> Option<&T> {
-        Map::terrain_at(self, p.0, p.1)
+    pub fn terrain_at_point(&self, p: Point2<usize>) -> Option<&T> {
+        Map::terrain_at(self, p.x, p.y)
     }
 
     /// Get the neighbours of at the coordinate `(x, y)`
-    pub fn neighbours(&self, x: usize, y: usize) -> Vec<Coordinate<T>> {
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<Coordinate<'_, T>> {
         let mut result = Vec::with_capacity(8);
 
-        let offsets = [
-            (-1, -1),
-            (0, -1),
-            (1, -1),
-            (-1, 0),
-            (1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1),
-        ];
-
-        for &offset in &offsets {
-            if let Some(cord) = self.cord_at((x, y), offset) {
+        for &offset in &NEIGHBOUR_OFFSETS {
+            if let Some(cord) = self.cord_at(Point2::new(x, y), offset) {
                 result.push(cord);
             }
         }
@@ -101,10 +103,10 @@ where
 
     pub fn step_until<F>(
         &self,
-        start: (usize, usize),
-        step_size: (isize, isize),
+        start: Point2<usize>,
+        step_size: Vector2<isize>,
         is_end: F,
-    ) -> Option<Coordinate<T>>
+    ) -> Option<Coordinate<'_, T>>
     where
         F: Fn(&Coordinate<T>) -> bool,
     {
@@ -113,27 +115,21 @@ where
             if is_end(&c) {
                 return Some(c);
             }
-            current_step = (current_step.0 + step_size.0, current_step.1 + step_size.1);
+            current_step += step_size;
         }
         None
     }
 
-    pub fn cord_at(&self, p: (usize, usize), offset: (isize, isize)) -> Option<Coordinate<T>> {
-        let p = (p.0 as isize, p.1 as isize);
-        let new_point = (p.0 + offset.0, p.1 + offset.1);
+    /// Returns the coordinate at `p + offset` if it is within the bounds of the map.
+    pub fn cord_at(&self, p: Point2<usize>, offset: Vector2<isize>) -> Option<Coordinate<'_, T>> {
+        let new_point = (p.checked_cast::<isize>()? + offset).checked_cast::<usize>()?;
 
-        if new_point.0 >= 0 && new_point.1 >= 0 {
-            let x = new_point.0 as usize;
-            let y = new_point.1 as usize;
-            self.terrain_at_point((x, y))
-                .map(|t| Coordinate::from_point((x, y), t))
-        } else {
-            None
-        }
+        self.terrain_at_point(new_point)
+            .map(|t| Coordinate::from_point(new_point, t))
     }
 
     /// Iterate over the coordinates of the map
-    pub fn iter(&self) -> CoordinateIter<T> {
+    pub fn iter(&self) -> CoordinateIter<'_, T> {
         CoordinateIter::new(&self.map, self.width)
     }
 
@@ -206,8 +202,8 @@ where
         Coordinate { x, y, terrain }
     }
 
-    fn from_point(p: (usize, usize), terrain: &'a T) -> Self {
-        Coordinate::new(p.0, p.1, terrain)
+    fn from_point(p: Point2<usize>, terrain: &'a T) -> Self {
+        Coordinate::new(p.x, p.y, terrain)
     }
 
     pub fn x(&self) -> usize {
@@ -218,6 +214,10 @@ where
         self.y
     }
 
+    pub fn point(&self) -> Point2<usize> {
+        Point2::new(self.x, self.y)
+    }
+
     pub fn terrain(&self) -> &T {
         self.terrain
     }
@@ -240,6 +240,7 @@ pub enum TerrainErr {
 #[cfg(test)]
 mod tests {
     use crate::util::map::{Coordinate, Map, Terrain, TerrainErr};
+    use crate::util::point::{Point2, Vector2};
     use std::cmp::Ordering;
     use std::cmp::Ordering::{Equal, Greater, Less};
 
@@ -404,15 +405,19 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(map.cord_at((1, 0), (0, -1)), None);
-        assert_eq!(map.cord_at((1, 0), (-2, 0)), None);
+        assert_eq!(map.cord_at(Point2::new(1, 0), Vector2::new(0, -1)), None);
+        assert_eq!(map.cord_at(Point2::new(1, 0), Vector2::new(-2, 0)), None);
         assert_eq!(
-            map.cord_at((2, 1), (-1, -1)),
+            map.cord_at(Point2::new(2, 1), Vector2::new(-1, -1)),
             Some(Coordinate::new(1, 0, &TestTerrain::One))
         );
 
-        assert_eq!(map.cord_at((5, 2), (3, 0)), None);
-        assert_eq!(map.cord_at((7, 1), (-1, 2)), None);
+        assert_eq!(map.cord_at(Point2::new(5, 2), Vector2::new(3, 0)), None);
+        assert_eq!(map.cord_at(Point2::new(7, 1), Vector2::new(-1, 2)), None);
+        assert_eq!(
+            map.cord_at(Point2::new(usize::MAX, 0), Vector2::new(0, 0)),
+            None
+        );
     }
 
     #[test]
@@ -428,8 +433,13 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            map.step_until((6, 3), (-2, -1), |c| c.terrain.eq(&TestTerrain::Four)),
-            Some(Coordinate::from_point((2, 1), &TestTerrain::Four))
+            map.step_until(Point2::new(6, 3), Vector2::new(-2, -1), |c| c
+                .terrain
+                .eq(&TestTerrain::Four)),
+            Some(Coordinate::from_point(
+                Point2::new(2, 1),
+                &TestTerrain::Four
+            ))
         );
     }
 }
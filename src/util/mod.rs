@@ -1,76 +1,234 @@
+pub mod bigint;
+pub mod bitset;
+pub mod bsp;
+pub mod csv;
+pub mod json;
 pub mod map;
+pub mod parse;
+pub mod pattern;
+pub mod point;
 pub mod puzzle_input;
+pub mod render;
+pub mod sections;
+pub mod vm;
+
+use std::io::{self, BufRead};
+
 /// Represent a group of a larger text.
 #[derive(PartialOrd, PartialEq, Debug)]
 pub struct Group<'a> {
     str: &'a str,
+    line: usize,
 }
 
 impl<'a> Group<'a> {
     pub fn new(str: &'a str) -> Self {
-        Group { str }
+        Group::at_line(str, 1)
+    }
+
+    /// A group whose first line is line number `line` of the larger text, counting from 1.
+    pub fn at_line(str: &'a str, line: usize) -> Self {
+        Group { str, line }
     }
 
     pub fn as_str(&self) -> &'a str {
         self.str
     }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// A group read from a `BufRead`, owning its text.
+/// The lines of the group are joined with `\n`.
+#[derive(PartialOrd, PartialEq, Debug, Clone)]
+pub struct GroupBuf {
+    string: String,
+    line: usize,
+}
+
+impl GroupBuf {
+    pub fn at_line(string: String, line: usize) -> Self {
+        GroupBuf { string, line }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn as_group(&self) -> Group<'_> {
+        Group::at_line(&self.string, self.line)
+    }
+}
+
+/// Decides which lines separate the groups of a text.
+/// The separating lines are never part of a group.
+#[derive(Default)]
+pub enum Separator {
+    /// A line that is empty or only whitespace.
+    #[default]
+    BlankLine,
+    /// A line equal to the given text, ignoring surrounding whitespace.
+    Line(String),
+    /// Any line for which the predicate is true.
+    Matching(Box<dyn Fn(&str) -> bool>),
+}
+
+impl Separator {
+    pub fn line(line: &str) -> Self {
+        Separator::Line(line.trim().to_string())
+    }
+
+    pub fn matching<F>(predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        Separator::Matching(Box::new(predicate))
+    }
+
+    /// Whether `line`, without its line ending, separates two groups.
+    pub fn is_separator(&self, line: &str) -> bool {
+        match self {
+            Separator::BlankLine => line.trim().is_empty(),
+            Separator::Line(separator) => line.trim() == separator,
+            Separator::Matching(predicate) => predicate(line),
+        }
+    }
 }
 
 /// Allows for the iterations of groups in a larger texts.
+/// Consecutive separators do not give empty groups.
 pub struct GroupIterator<'a> {
     str: &'a str,
     idx: usize,
+    line: usize,
+    separator: Separator,
 }
 
 impl<'a> Iterator for GroupIterator<'a> {
     type Item = Group<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx >= self.str.len() {
-            return None;
-        }
-        let start = self.idx;
-        let mut offset = 0;
-        while GroupIterator::ends_with_double_newline(&self.str[start..start + offset]).is_none()
-            && start + offset < self.str.len()
-        {
-            offset += 1;
+        // start, end and line number of the group found so far
+        let mut group: Option<(usize, usize, usize)> = None;
+
+        while self.idx < self.str.len() {
+            let rest = &self.str[self.idx..];
+            let (line, next_idx) = match rest.find('\n') {
+                Some(end) => (&rest[..end], self.idx + end + 1),
+                None => (rest, self.str.len()),
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let line_start = self.idx;
+            let line_number = self.line;
+
+            self.idx = next_idx;
+            self.line += 1;
+
+            if self.separator.is_separator(line) {
+                if group.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            let line_end = line_start + line.len();
+            group = match group {
+                Some((start, _, first_line)) => Some((start, line_end, first_line)),
+                None => Some((line_start, line_end, line_number)),
+            };
         }
 
-        let end = match GroupIterator::ends_with_double_newline(&self.str[start..start + offset]) {
-            Some(size_of_end) => start + offset - size_of_end,
-            None => start + offset,
-        };
-        self.idx = start + offset;
-        Some(Group::new(&self.str[start..end]))
+        group.map(|(start, end, line)| Group::at_line(&self.str[start..end], line))
     }
 }
 
 impl<'a> GroupIterator<'a> {
     /// Creates the iterator. Groups are separated by a blank line.
     pub fn new(str: &'a str) -> Self {
-        GroupIterator { str, idx: 0 }
-    }
-
-    fn ends_with_double_newline(str: &str) -> Option<usize> {
-        if str.ends_with("\r\n\r\n") {
-            return Some(4);
-        } else if str.ends_with("\n\r\n") {
-            return Some(3);
-        } else if str.ends_with("\r\n\n") {
-            return Some(3);
-        } else if str.ends_with("\n\n") {
-            // must be last as it covers the previous case
-            return Some(2);
+        GroupIterator::with_separator(str, Separator::BlankLine)
+    }
+
+    pub fn with_separator(str: &'a str, separator: Separator) -> Self {
+        GroupIterator {
+            str,
+            idx: 0,
+            line: 1,
+            separator,
+        }
+    }
+}
+
+/// Reads the groups of a text one at a time, so the entire
+/// text never has to be in memory.
+pub struct GroupReader<R> {
+    reader: R,
+    line: usize,
+    separator: Separator,
+    buffer: String,
+}
+
+impl<R: BufRead> GroupReader<R> {
+    /// Creates the reader. Groups are separated by a blank line.
+    pub fn new(reader: R) -> Self {
+        GroupReader::with_separator(reader, Separator::BlankLine)
+    }
+
+    pub fn with_separator(reader: R, separator: Separator) -> Self {
+        GroupReader {
+            reader,
+            line: 1,
+            separator,
+            buffer: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for GroupReader<R> {
+    type Item = io::Result<GroupBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut group: Option<GroupBuf> = None;
+
+        loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let line = self.buffer.trim_end_matches(&['\n', '\r'][..]);
+            let line_number = self.line;
+            self.line += 1;
+
+            if self.separator.is_separator(line) {
+                if group.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            match group.as_mut() {
+                Some(group) => {
+                    group.string.push('\n');
+                    group.string.push_str(line);
+                }
+                None => group = Some(GroupBuf::at_line(line.to_string(), line_number)),
+            }
         }
 
-        None
+        group.map(Ok)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::util::{Group, GroupIterator};
+    use crate::util::{Group, GroupBuf, GroupIterator, GroupReader, Separator};
 
     #[test]
     fn grouping_of_simple_string() {
@@ -94,7 +252,10 @@ mod tests {
 
         assert_eq!(
             collection,
-            vec![Group::new("grp1-1"), Group::new("group2-1\ngroup2-2")]
+            vec![
+                Group::new("grp1-1"),
+                Group::at_line("group2-1\ngroup2-2", 3)
+            ]
         );
     }
 
@@ -106,11 +267,65 @@ mod tests {
             itr,
             vec![
                 Group::new("grp"),
-                Group::new("grp2"),
-                Group::new("grp3"),
-                Group::new("grp4"),
-                Group::new("grp5")
+                Group::at_line("grp2", 3),
+                Group::at_line("grp3", 5),
+                Group::at_line("grp4", 7),
+                Group::at_line("grp5", 9)
             ]
         )
     }
+
+    #[test]
+    fn consecutive_and_trailing_blank_lines_give_no_empty_groups() {
+        let itr: Vec<Group> = GroupIterator::new("\n\ngrp1\r\n\n\n  \ngrp2\r\n\r\n").collect();
+        assert_eq!(
+            itr,
+            vec![Group::at_line("grp1", 3), Group::at_line("grp2", 7)]
+        );
+    }
+
+    #[test]
+    fn groups_with_multi_byte_chars() {
+        let itr: Vec<&str> = GroupIterator::new("æøå\n\nü€\n")
+            .map(|g| g.as_str())
+            .collect();
+        assert_eq!(itr, vec!["æøå", "ü€"]);
+    }
+
+    #[test]
+    fn custom_separator_line() {
+        let itr: Vec<Group> =
+            GroupIterator::with_separator("a\nb\n---\nc\n\nd", Separator::line("---")).collect();
+        assert_eq!(itr, vec![Group::new("a\nb"), Group::at_line("c\n\nd", 4)]);
+    }
+
+    #[test]
+    fn separator_predicate() {
+        let separator = Separator::matching(|line| line.ends_with(':'));
+        let itr: Vec<Group> =
+            GroupIterator::with_separator("rules:\n1\n2\nticket:\n3", separator).collect();
+        assert_eq!(itr, vec![Group::at_line("1\n2", 2), Group::at_line("3", 5)]);
+    }
+
+    #[test]
+    fn large_group_is_split_in_one_pass() {
+        let text = format!("{}\nlast", "x\n".repeat(200_000));
+        let groups: Vec<Group> = GroupIterator::new(&text).collect();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1], Group::at_line("last", 200_002));
+    }
+
+    #[test]
+    fn read_groups_from_buf_read() {
+        let input = "grp1-1\r\ngrp1-2\r\n\r\n\r\ngrp2\n".as_bytes();
+        let groups: Vec<GroupBuf> = GroupReader::new(input).map(Result::unwrap).collect();
+        assert_eq!(
+            groups,
+            vec![
+                GroupBuf::at_line("grp1-1\ngrp1-2".to_string(), 1),
+                GroupBuf::at_line("grp2".to_string(), 5)
+            ]
+        );
+        assert_eq!(groups[1].as_group(), Group::at_line("grp2", 5));
+    }
 }
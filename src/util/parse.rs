@@ -0,0 +1,442 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The result of running a parser: the parsed value together with the
+/// input that is left, or a failure describing what was expected.
+pub type ParseResult<'a, T> = Result<(T, &'a str), Failure<'a>>;
+
+/// A parser that failed with the remaining input at the point of failure.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Failure<'a> {
+    rest: &'a str,
+    expected: String,
+}
+
+impl<'a> Failure<'a> {
+    pub fn new(rest: &'a str, expected: &str) -> Self {
+        Failure {
+            rest,
+            expected: expected.to_string(),
+        }
+    }
+}
+
+/// Where and why parsing an input failed.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ParseError {
+    line: usize,
+    column: usize,
+    expected: String,
+    found: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, expected: &str, found: &str) -> Self {
+        ParseError {
+            line,
+            column,
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+
+    /// Turns a failure of parsing `input` into an error. The column
+    /// is counted in chars from 1, and is relative to `input`.
+    pub fn from_failure(input: &str, line: usize, failure: &Failure) -> Self {
+        let consumed = &input[..input.len() - failure.rest.len()];
+        let found: String = failure.rest.chars().take(10).collect();
+        let found = if found.is_empty() {
+            "end of input".to_string()
+        } else {
+            format!("{:?}", found)
+        };
+        ParseError::new(
+            line,
+            consumed.chars().count() + 1,
+            &failure.expected,
+            &found,
+        )
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+/// Anything that can parse a prefix of its input.
+pub trait Parser<'a, T>: Fn(&'a str) -> ParseResult<'a, T> {}
+
+impl<'a, T, F> Parser<'a, T> for F where F: Fn(&'a str) -> ParseResult<'a, T> {}
+
+/// Matches exactly `expected`.
+pub fn literal<'a>(expected: &'static str) -> impl Parser<'a, &'a str> {
+    move |input: &'a str| match input.strip_prefix(expected) {
+        Some(rest) => Ok((&input[..expected.len()], rest)),
+        None => Err(Failure::new(input, &format!("{:?}", expected))),
+    }
+}
+
+/// Matches a single char satisfying the predicate.
+pub fn char_where<'a, F>(predicate: F, what: &'static str) -> impl Parser<'a, char>
+where
+    F: Fn(char) -> bool,
+{
+    move |input: &'a str| match input.chars().next() {
+        Some(c) if predicate(c) => Ok((c, &input[c.len_utf8()..])),
+        _ => Err(Failure::new(input, what)),
+    }
+}
+
+/// Matches any single char.
+pub fn any_char<'a>() -> impl Parser<'a, char> {
+    char_where(|_| true, "a character")
+}
+
+/// Matches the longest non-empty prefix of chars satisfying the predicate.
+pub fn take_while1<'a, F>(predicate: F, what: &'static str) -> impl Parser<'a, &'a str>
+where
+    F: Fn(char) -> bool,
+{
+    move |input: &'a str| {
+        let end = input
+            .char_indices()
+            .find(|&(_, c)| !predicate(c))
+            .map_or(input.len(), |(idx, _)| idx);
+        if end == 0 {
+            Err(Failure::new(input, what))
+        } else {
+            Ok((&input[..end], &input[end..]))
+        }
+    }
+}
+
+/// Matches the longest, possibly empty, prefix of chars satisfying the predicate.
+pub fn take_while<'a, F>(predicate: F) -> impl Parser<'a, &'a str>
+where
+    F: Fn(char) -> bool,
+{
+    move |input: &'a str| {
+        let end = input
+            .char_indices()
+            .find(|&(_, c)| !predicate(c))
+            .map_or(input.len(), |(idx, _)| idx);
+        Ok((&input[..end], &input[end..]))
+    }
+}
+
+/// Skips optional spaces and tabs.
+pub fn ws<'a>() -> impl Parser<'a, ()> {
+    map(take_while(|c| c == ' ' || c == '\t'), |_| ())
+}
+
+/// A sequence of letters, e.g. a single word of a sentence.
+pub fn word<'a>() -> impl Parser<'a, &'a str> {
+    take_while1(char::is_alphabetic, "a word")
+}
+
+/// An unsigned integer of the given type.
+pub fn unsigned<'a, T: FromStr>() -> impl Parser<'a, T> {
+    map_res(
+        take_while1(|c| c.is_ascii_digit(), "an integer"),
+        |digits: &str| digits.parse::<T>().map_err(|_| "an integer in range"),
+    )
+}
+
+/// An integer of the given type with an optional `+` or `-` sign.
+pub fn integer<'a, T: FromStr>() -> impl Parser<'a, T> {
+    move |input: &'a str| {
+        let unsigned_start = if input.starts_with('+') || input.starts_with('-') {
+            1
+        } else {
+            0
+        };
+        let (_, rest) = take_while1(|c| c.is_ascii_digit(), "an integer")(&input[unsigned_start..])
+            .map_err(|_| Failure::new(input, "an integer"))?;
+        let number = &input[..input.len() - rest.len()];
+        let number = number.strip_prefix('+').unwrap_or(number);
+
+        match number.parse::<T>() {
+            Ok(n) => Ok((n, rest)),
+            Err(_) => Err(Failure::new(input, "an integer in range")),
+        }
+    }
+}
+
+/// Succeeds only at the end of the input.
+pub fn eof<'a>() -> impl Parser<'a, ()> {
+    move |input: &'a str| {
+        if input.is_empty() {
+            Ok(((), input))
+        } else {
+            Err(Failure::new(input, "end of input"))
+        }
+    }
+}
+
+pub fn map<'a, A, B, P, F>(parser: P, f: F) -> impl Parser<'a, B>
+where
+    P: Parser<'a, A>,
+    F: Fn(A) -> B,
+{
+    move |input: &'a str| parser(input).map(|(a, rest)| (f(a), rest))
+}
+
+/// Runs the parser and returns the part of the input it consumed.
+pub fn recognize<'a, T, P>(parser: P) -> impl Parser<'a, &'a str>
+where
+    P: Parser<'a, T>,
+{
+    move |input: &'a str| {
+        let (_, rest) = parser(input)?;
+        Ok((&input[..input.len() - rest.len()], rest))
+    }
+}
+
+/// Maps the parsed value with a function that may fail. On failure the
+/// error describes what was expected, and points to the start of the value.
+pub fn map_res<'a, A, B, P, F>(parser: P, f: F) -> impl Parser<'a, B>
+where
+    P: Parser<'a, A>,
+    F: Fn(A) -> Result<B, &'static str>,
+{
+    move |input: &'a str| {
+        let (a, rest) = parser(input)?;
+        match f(a) {
+            Ok(b) => Ok((b, rest)),
+            Err(expected) => Err(Failure::new(input, expected)),
+        }
+    }
+}
+
+pub fn pair<'a, A, B, PA, PB>(first: PA, second: PB) -> impl Parser<'a, (A, B)>
+where
+    PA: Parser<'a, A>,
+    PB: Parser<'a, B>,
+{
+    move |input: &'a str| {
+        let (a, rest) = first(input)?;
+        let (b, rest) = second(rest)?;
+        Ok(((a, b), rest))
+    }
+}
+
+/// Runs both parsers and keeps the value of the second.
+pub fn preceded<'a, A, B, PA, PB>(first: PA, second: PB) -> impl Parser<'a, B>
+where
+    PA: Parser<'a, A>,
+    PB: Parser<'a, B>,
+{
+    map(pair(first, second), |(_, b)| b)
+}
+
+/// Runs both parsers and keeps the value of the first.
+pub fn terminated<'a, A, B, PA, PB>(first: PA, second: PB) -> impl Parser<'a, A>
+where
+    PA: Parser<'a, A>,
+    PB: Parser<'a, B>,
+{
+    map(pair(first, second), |(a, _)| a)
+}
+
+/// Runs all three parsers and keeps the value of the middle one.
+pub fn delimited<'a, A, B, C, PA, PB, PC>(left: PA, value: PB, right: PC) -> impl Parser<'a, B>
+where
+    PA: Parser<'a, A>,
+    PB: Parser<'a, B>,
+    PC: Parser<'a, C>,
+{
+    preceded(left, terminated(value, right))
+}
+
+/// Tries the first parser, and the second if the first fails.
+/// If both fail, the failure that got furthest is reported.
+pub fn alt<'a, T, PA, PB>(first: PA, second: PB) -> impl Parser<'a, T>
+where
+    PA: Parser<'a, T>,
+    PB: Parser<'a, T>,
+{
+    move |input: &'a str| match first(input) {
+        Ok(result) => Ok(result),
+        Err(e0) => match second(input) {
+            Ok(result) => Ok(result),
+            Err(e1) if e0.rest.len() < e1.rest.len() => Err(e0),
+            Err(e1) if e0.rest.len() == e1.rest.len() => Err(Failure {
+                rest: e0.rest,
+                expected: format!("{} or {}", e0.expected, e1.expected),
+            }),
+            Err(e1) => Err(e1),
+        },
+    }
+}
+
+/// Runs the parser if possible, without consuming anything when it fails.
+pub fn opt<'a, T, P>(parser: P) -> impl Parser<'a, Option<T>>
+where
+    P: Parser<'a, T>,
+{
+    move |input: &'a str| match parser(input) {
+        Ok((value, rest)) => Ok((Some(value), rest)),
+        Err(_) => Ok((None, input)),
+    }
+}
+
+/// One or more values separated by `separator`.
+pub fn separated_list1<'a, T, S, P, PS>(parser: P, separator: PS) -> impl Parser<'a, Vec<T>>
+where
+    P: Parser<'a, T>,
+    PS: Parser<'a, S>,
+{
+    move |input: &'a str| {
+        let (first, mut rest) = parser(input)?;
+        let mut values = vec![first];
+        while let Ok((_, after_separator)) = separator(rest) {
+            let (value, after_value) = parser(after_separator)?;
+            values.push(value);
+            rest = after_value;
+        }
+        Ok((values, rest))
+    }
+}
+
+/// Zero or more values separated by `separator`.
+pub fn separated_list0<'a, T, S, P, PS>(parser: P, separator: PS) -> impl Parser<'a, Vec<T>>
+where
+    P: Parser<'a, T>,
+    PS: Parser<'a, S>,
+{
+    let list = separated_list1(parser, separator);
+    move |input: &'a str| match list(input) {
+        Ok(result) => Ok(result),
+        Err(e) if e.rest.len() == input.len() => Ok((Vec::new(), input)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses all of `input` as line number `line`.
+pub fn parse_all<'a, T, P>(parser: P, input: &'a str, line: usize) -> Result<T, ParseError>
+where
+    P: Parser<'a, T>,
+{
+    match terminated(parser, eof())(input) {
+        Ok((value, _)) => Ok(value),
+        Err(failure) => Err(ParseError::from_failure(input, line, &failure)),
+    }
+}
+
+/// Parses every line of `input`, reporting the position of the first line that fails.
+pub fn parse_lines<'a, T, P>(parser: P, input: &'a str) -> Result<Vec<T>, ParseError>
+where
+    P: Parser<'a, T>,
+{
+    let parser = terminated(parser, eof());
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| match parser(line) {
+            Ok((value, _)) => Ok(value),
+            Err(failure) => Err(ParseError::from_failure(line, idx + 1, &failure)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::parse::{
+        alt, delimited, integer, literal, map, opt, pair, parse_all, parse_lines, preceded,
+        recognize, separated_list0, separated_list1, take_while1, unsigned, word, ws, ParseError,
+    };
+
+    #[test]
+    fn parse_literals_and_integers() {
+        assert_eq!(literal("mem")("mem[3]"), Ok(("mem", "[3]")));
+        assert!(literal("mask")("mem[3]").is_err());
+
+        assert_eq!(integer::<i64>()("+12 rest"), Ok((12, " rest")));
+        assert_eq!(integer::<i64>()("-42"), Ok((-42, "")));
+        assert!(integer::<i64>()("-").is_err());
+        assert!(integer::<u8>()("256").is_err());
+        assert_eq!(unsigned::<u64>()("123abc"), Ok((123, "abc")));
+        assert!(unsigned::<u64>()("-1").is_err());
+    }
+
+    #[test]
+    fn parse_sequences() {
+        let parser = pair(word(), preceded(ws(), integer::<i32>()));
+        assert_eq!(parser("acc   +3"), Ok((("acc", 3), "")));
+
+        let address = delimited(literal("["), unsigned::<u64>(), literal("]"));
+        assert_eq!(address("[42] = 1"), Ok((42, " = 1")));
+
+        let two_words = recognize(pair(word(), preceded(literal(" "), word())));
+        assert_eq!(two_words("shiny gold bag"), Ok(("shiny gold", " bag")));
+    }
+
+    #[test]
+    fn parse_alternatives_and_options() {
+        let bag = alt(literal("bags"), literal("bag"));
+        assert_eq!(bag("bags."), Ok(("bags", ".")));
+        assert_eq!(bag("bag."), Ok(("bag", ".")));
+
+        let signed = pair(opt(literal("-")), unsigned::<u32>());
+        assert_eq!(signed("-5"), Ok(((Some("-"), 5), "")));
+        assert_eq!(signed("5"), Ok(((None, 5), "")));
+    }
+
+    #[test]
+    fn parse_separated_lists() {
+        let list = separated_list1(unsigned::<u32>(), literal(","));
+        assert_eq!(list("1,2,3;"), Ok((vec![1, 2, 3], ";")));
+        assert!(list("1,;").is_err());
+
+        let list = separated_list0(unsigned::<u32>(), literal(","));
+        assert_eq!(list(";"), Ok((vec![], ";")));
+    }
+
+    #[test]
+    fn errors_report_line_and_column() {
+        let parser = pair(
+            take_while1(|c| c.is_ascii_lowercase(), "a name"),
+            integer::<i64>(),
+        );
+        let error = parse_lines(parser, "a1\nb2\ncc+x").unwrap_err();
+
+        assert_eq!(error, ParseError::new(3, 3, "an integer", "\"+x\""));
+        assert_eq!(
+            error.to_string(),
+            "line 3, column 3: expected an integer, found \"+x\""
+        );
+    }
+
+    #[test]
+    fn parse_all_requires_entire_input() {
+        let n = map(unsigned::<u32>(), |n| n * 2);
+        assert_eq!(parse_all(&n, "21", 1), Ok(42));
+        assert_eq!(
+            parse_all(&n, "21 ", 4),
+            Err(ParseError::new(4, 3, "end of input", "\" \""))
+        );
+    }
+}
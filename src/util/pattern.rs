@@ -0,0 +1,405 @@
+use std::str::FromStr;
+
+/// A small regular expression. It supports literal characters, `.`,
+/// classes such as `[a-z0-9]` and `[^abc]`, the repetitions `?`, `*`, `+`,
+/// `{n}`, `{n,}` and `{n,m}` with bounds up to `MAX_REPEAT`, the anchors `^` and `$`, and `\` to escape
+/// any of the special characters. There are no groups or alternatives.
+///
+/// Without anchors the pattern may match anywhere in the text.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Pattern {
+    anchored_start: bool,
+    anchored_end: bool,
+    items: Vec<(Atom, Repeat)>,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+enum Atom {
+    Any,
+    Char(char),
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class { negated, ranges } => {
+                ranges.iter().any(|&(low, high)| low <= c && c <= high) != *negated
+            }
+        }
+    }
+}
+
+/// The largest bound of a `{n,m}` repetition, as matching keeps a state per count.
+pub const MAX_REPEAT: usize = 1000;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Repeat {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl Repeat {
+    const ONCE: Repeat = Repeat {
+        min: 1,
+        max: Some(1),
+    };
+}
+
+/// The errors give the position of the offending character, counting from 0.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum PatternError {
+    UnclosedClass(usize),
+    EmptyClass(usize),
+    NothingToRepeat(usize),
+    BadRepetition(usize),
+    TrailingEscape(usize),
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut items: Vec<(Atom, Repeat)> = Vec::new();
+        let mut idx = 0;
+
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            idx += 1;
+        }
+        let mut end = chars.len();
+        let anchored_end = end > idx && chars[end - 1] == '$' && !is_escaped(&chars, end - 1);
+        if anchored_end {
+            end -= 1;
+        }
+
+        while idx < end {
+            let c = chars[idx];
+            match c {
+                '?' | '*' | '+' | '{' => {
+                    let repeat = match c {
+                        '?' => Repeat {
+                            min: 0,
+                            max: Some(1),
+                        },
+                        '*' => Repeat { min: 0, max: None },
+                        '+' => Repeat { min: 1, max: None },
+                        _ => {
+                            let (repeat, next) = parse_bounds(&chars[..end], idx)?;
+                            idx = next - 1;
+                            repeat
+                        }
+                    };
+                    match items.last_mut() {
+                        Some((_, last)) if *last == Repeat::ONCE => *last = repeat,
+                        _ => return Err(PatternError::NothingToRepeat(idx)),
+                    }
+                    idx += 1;
+                }
+                '[' => {
+                    let (atom, next) = parse_class(&chars[..end], idx)?;
+                    items.push((atom, Repeat::ONCE));
+                    idx = next;
+                }
+                '.' => {
+                    items.push((Atom::Any, Repeat::ONCE));
+                    idx += 1;
+                }
+                '\\' => {
+                    let escaped = *chars
+                        .get(idx + 1)
+                        .filter(|_| idx + 1 < end)
+                        .ok_or(PatternError::TrailingEscape(idx))?;
+                    items.push((Atom::Char(escaped), Repeat::ONCE));
+                    idx += 2;
+                }
+                _ => {
+                    items.push((Atom::Char(c), Repeat::ONCE));
+                    idx += 1;
+                }
+            }
+        }
+
+        Ok(Pattern {
+            anchored_start,
+            anchored_end,
+            items,
+        })
+    }
+}
+
+fn is_escaped(chars: &[char], idx: usize) -> bool {
+    let backslashes = chars[..idx]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == '\\')
+        .count();
+    backslashes % 2 == 1
+}
+
+/// Parses `{n}`, `{n,}` or `{n,m}` starting at `start`, returning the index after `}`.
+fn parse_bounds(chars: &[char], start: usize) -> Result<(Repeat, usize), PatternError> {
+    let close = chars[start..]
+        .iter()
+        .position(|&c| c == '}')
+        .map(|offset| start + offset)
+        .ok_or(PatternError::BadRepetition(start))?;
+    let inside: String = chars[start + 1..close].iter().collect();
+    let number = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| PatternError::BadRepetition(start))
+    };
+
+    let repeat = match inside.split_once(',') {
+        None => {
+            let n = number(&inside)?;
+            Repeat {
+                min: n,
+                max: Some(n),
+            }
+        }
+        Some((min, max)) if max.trim().is_empty() => Repeat {
+            min: number(min)?,
+            max: None,
+        },
+        Some((min, max)) => Repeat {
+            min: number(min)?,
+            max: Some(number(max)?),
+        },
+    };
+    if repeat
+        .max
+        .is_some_and(|max| max < repeat.min || max > MAX_REPEAT)
+        || repeat.min > MAX_REPEAT
+    {
+        return Err(PatternError::BadRepetition(start));
+    }
+    Ok((repeat, close + 1))
+}
+
+/// Parses a class starting at the `[` at `start`, returning the index after `]`.
+fn parse_class(chars: &[char], start: usize) -> Result<(Atom, usize), PatternError> {
+    let mut idx = start + 1;
+    let negated = chars.get(idx) == Some(&'^');
+    if negated {
+        idx += 1;
+    }
+
+    let mut ranges = Vec::new();
+    loop {
+        let c = match chars.get(idx) {
+            None => return Err(PatternError::UnclosedClass(start)),
+            Some(']') => break,
+            Some('\\') => {
+                idx += 1;
+                *chars
+                    .get(idx)
+                    .ok_or(PatternError::TrailingEscape(idx - 1))?
+            }
+            Some(&c) => c,
+        };
+        idx += 1;
+
+        match (chars.get(idx), chars.get(idx + 1)) {
+            (Some('-'), Some(&high)) if high != ']' => {
+                ranges.push((c, high));
+                idx += 2;
+            }
+            _ => ranges.push((c, c)),
+        }
+    }
+
+    if ranges.is_empty() {
+        return Err(PatternError::EmptyClass(start));
+    }
+    Ok((Atom::Class { negated, ranges }, idx + 1))
+}
+
+impl Pattern {
+    /// Whether the pattern matches the text. All ways of matching are
+    /// followed at once, so the time is linear in the length of the text.
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut states = States::new(&self.items);
+        let mut next = States::new(&self.items);
+        states.add(&self.items, 0, 0);
+
+        for c in text.chars() {
+            if states.accepts() && !self.anchored_end {
+                return true;
+            }
+            next.clear();
+            for &(item, count) in &states.list {
+                let (atom, repeat) = match self.items.get(item) {
+                    Some(item) => item,
+                    None => continue,
+                };
+                let below_max = match repeat.max {
+                    Some(max) => count < max,
+                    None => true,
+                };
+                if below_max && atom.matches(c) {
+                    // without a maximum, every count from the minimum on is the same state
+                    let count = match repeat.max {
+                        None => repeat.min.min(count + 1),
+                        Some(_) => count + 1,
+                    };
+                    next.add(&self.items, item, count);
+                }
+            }
+            if !self.anchored_start {
+                next.add(&self.items, 0, 0);
+            }
+            std::mem::swap(&mut states, &mut next);
+        }
+        states.accepts()
+    }
+}
+
+/// The positions within the pattern that a match may be at: an item and how
+/// often it has been repeated so far. The item after the last one accepts.
+struct States {
+    /// Per item, the index of its first state, followed by the accepting state.
+    offsets: Vec<usize>,
+    seen: Vec<bool>,
+    list: Vec<(usize, usize)>,
+}
+
+impl States {
+    fn new(items: &[(Atom, Repeat)]) -> Self {
+        let mut offsets = Vec::with_capacity(items.len() + 1);
+        let mut total = 0;
+        for (_, repeat) in items {
+            offsets.push(total);
+            total += repeat.max.unwrap_or(repeat.min) + 1;
+        }
+        offsets.push(total);
+        States {
+            offsets,
+            seen: vec![false; total + 1],
+            list: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        for &(item, count) in &self.list {
+            self.seen[self.offsets[item] + count] = false;
+        }
+        self.list.clear();
+    }
+
+    /// Adds the state, and the following items as long as they may be skipped.
+    fn add(&mut self, items: &[(Atom, Repeat)], mut item: usize, mut count: usize) {
+        loop {
+            let idx = self.offsets[item] + count;
+            if self.seen[idx] {
+                return;
+            }
+            self.seen[idx] = true;
+            self.list.push((item, count));
+            match items.get(item) {
+                Some((_, repeat)) if count >= repeat.min => {
+                    item += 1;
+                    count = 0;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn accepts(&self) -> bool {
+        self.seen[self.seen.len() - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::pattern::{Pattern, PatternError};
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        pattern.parse::<Pattern>().unwrap().is_match(text)
+    }
+
+    #[test]
+    fn literals_and_anchors() {
+        assert!(matches("bc", "abcd"));
+        assert!(!matches("^bc", "abcd"));
+        assert!(matches("^ab", "abcd"));
+        assert!(matches("cd$", "abcd"));
+        assert!(!matches("^abc$", "abcd"));
+        assert!(matches("", "anything"));
+        assert!(matches("^$", ""));
+    }
+
+    #[test]
+    fn classes_and_repetitions() {
+        assert!(matches("^[a-z]+$", "password"));
+        assert!(!matches("^[a-z]+$", "passw0rd"));
+        assert!(matches("[0-9]", "passw0rd"));
+        assert!(matches("^[^x]*$", "abc"));
+        assert!(!matches("^[^x]*$", "axc"));
+        assert!(matches("^a.?c$", "ac"));
+        assert!(matches("^a.?c$", "abc"));
+        assert!(matches("^a{2,3}b{2}$", "aaabb"));
+        assert!(!matches("^a{2,3}$", "aaaa"));
+        assert!(matches("^a{2,}$", "aaaa"));
+        assert!(matches("^a*ab$", "aaab"));
+        assert!(!matches("^a{2}b", "ab"));
+        assert!(matches("a{0}b", "b"));
+    }
+
+    #[test]
+    fn adjacent_repetitions_are_fast() {
+        let text = "a".repeat(10_000);
+        assert!(!matches("a*a*a*a*a*a*a*a*b", &text));
+        assert!(matches("a*a*a*a*a*a*a*a*$", &text));
+        assert!(matches("^a?a?a?a?a?a?a?a?a?a?a{10}$", &"a".repeat(10)));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches(r"^a\.b$", "a.b"));
+        assert!(!matches(r"^a\.b$", "axb"));
+        assert!(matches(r"\$$", "cost$"));
+        assert!(matches(r"[\]x]", "]"));
+    }
+
+    #[test]
+    fn malformed_patterns() {
+        assert_eq!(
+            "[abc".parse::<Pattern>(),
+            Err(PatternError::UnclosedClass(0))
+        );
+        assert_eq!(
+            "*a".parse::<Pattern>(),
+            Err(PatternError::NothingToRepeat(0))
+        );
+        assert_eq!(
+            "a**".parse::<Pattern>(),
+            Err(PatternError::NothingToRepeat(2))
+        );
+        assert_eq!(
+            "a{3,1}".parse::<Pattern>(),
+            Err(PatternError::BadRepetition(1))
+        );
+        assert_eq!(
+            "a{x}".parse::<Pattern>(),
+            Err(PatternError::BadRepetition(1))
+        );
+        assert_eq!(
+            "ab\\".parse::<Pattern>(),
+            Err(PatternError::TrailingEscape(2))
+        );
+        assert_eq!("[]".parse::<Pattern>(), Err(PatternError::EmptyClass(0)));
+        assert_eq!(
+            "a{1001}".parse::<Pattern>(),
+            Err(PatternError::BadRepetition(1))
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::convert::TryFrom;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A point in the plane.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Default)]
+pub struct Point2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+/// The difference between two points in the plane.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone, Default)]
+pub struct Vector2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Point2<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Point2 { x, y }
+    }
+
+    /// The vector from the origin to the point.
+    pub fn to_vector(self) -> Vector2<T> {
+        Vector2::new(self.x, self.y)
+    }
+
+    /// Converts the coordinates to another integer type,
+    /// returning `None` if either does not fit.
+    pub fn checked_cast<U: TryFrom<T>>(self) -> Option<Point2<U>> {
+        Some(Point2::new(
+            U::try_from(self.x).ok()?,
+            U::try_from(self.y).ok()?,
+        ))
+    }
+}
+
+impl<T> Point2<T>
+where
+    T: Ord + Sub<Output = T> + Add<Output = T> + Copy,
+{
+    pub fn manhattan_distance(&self, other: &Self) -> T {
+        abs_diff(self.x, other.x) + abs_diff(self.y, other.y)
+    }
+
+    pub fn chebyshev_distance(&self, other: &Self) -> T {
+        abs_diff(self.x, other.x).max(abs_diff(self.y, other.y))
+    }
+}
+
+fn abs_diff<T: Ord + Sub<Output = T> + Copy>(a: T, b: T) -> T {
+    a.max(b) - a.min(b)
+}
+
+impl<T> Vector2<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Vector2 { x, y }
+    }
+
+    /// Converts the components to another integer type,
+    /// returning `None` if either does not fit.
+    pub fn checked_cast<U: TryFrom<T>>(self) -> Option<Vector2<U>> {
+        Some(Vector2::new(
+            U::try_from(self.x).ok()?,
+            U::try_from(self.y).ok()?,
+        ))
+    }
+}
+
+impl<T> Vector2<T>
+where
+    T: Ord + Neg<Output = T> + Add<Output = T> + Copy,
+{
+    pub fn manhattan_length(&self) -> T {
+        self.x.max(-self.x) + self.y.max(-self.y)
+    }
+
+    pub fn chebyshev_length(&self) -> T {
+        self.x.max(-self.x).max(self.y.max(-self.y))
+    }
+}
+
+impl<T> Vector2<T>
+where
+    T: Neg<Output = T> + Copy,
+{
+    /// Rotates the vector clockwise by `quarter_turns` times 90 degrees,
+    /// with the y-axis pointing up. Negative turns rotate counter clockwise.
+    pub fn rotate_clockwise(self, quarter_turns: i64) -> Self {
+        match quarter_turns.rem_euclid(4) {
+            0 => self,
+            1 => Vector2::new(self.y, -self.x),
+            2 => Vector2::new(-self.x, -self.y),
+            _ => Vector2::new(-self.y, self.x),
+        }
+    }
+
+    pub fn rotate_counter_clockwise(self, quarter_turns: i64) -> Self {
+        self.rotate_clockwise(-quarter_turns)
+    }
+}
+
+impl<T: Add<Output = T>> Add<Vector2<T>> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn add(self, rhs: Vector2<T>) -> Self::Output {
+        Point2::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Add<Output = T> + Copy> AddAssign<Vector2<T>> for Point2<T> {
+    fn add_assign(&mut self, rhs: Vector2<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Sub<Output = T>> Sub<Vector2<T>> for Point2<T> {
+    type Output = Point2<T>;
+
+    fn sub(self, rhs: Vector2<T>) -> Self::Output {
+        Point2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Sub<Output = T> + Copy> SubAssign<Vector2<T>> for Point2<T> {
+    fn sub_assign(&mut self, rhs: Vector2<T>) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Point2<T> {
+    type Output = Vector2<T>;
+
+    fn sub(self, rhs: Point2<T>) -> Self::Output {
+        Vector2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn add(self, rhs: Vector2<T>) -> Self::Output {
+        Vector2::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Add<Output = T> + Copy> AddAssign for Vector2<T> {
+    fn add_assign(&mut self, rhs: Vector2<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn sub(self, rhs: Vector2<T>) -> Self::Output {
+        Vector2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn neg(self) -> Self::Output {
+        Vector2::new(-self.x, -self.y)
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Vector2<T> {
+    type Output = Vector2<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Vector2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl<T> From<(T, T)> for Point2<T> {
+    fn from(p: (T, T)) -> Self {
+        Point2::new(p.0, p.1)
+    }
+}
+
+impl<T> From<(T, T)> for Vector2<T> {
+    fn from(v: (T, T)) -> Self {
+        Vector2::new(v.0, v.1)
+    }
+}
+
+/// A point with `N` integer coordinates.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
+pub struct PointN<const N: usize> {
+    coordinates: [i64; N],
+}
+
+pub type Point3 = PointN<3>;
+pub type Point4 = PointN<4>;
+
+impl<const N: usize> PointN<N> {
+    pub const fn new(coordinates: [i64; N]) -> Self {
+        PointN { coordinates }
+    }
+
+    pub fn origin() -> Self {
+        PointN::new([0; N])
+    }
+
+    pub fn coordinates(&self) -> &[i64; N] {
+        &self.coordinates
+    }
+
+    pub fn manhattan_distance(&self, other: &Self) -> i64 {
+        self.zip(other).map(|(a, b)| (a - b).abs()).sum()
+    }
+
+    pub fn chebyshev_distance(&self, other: &Self) -> i64 {
+        self.zip(other)
+            .map(|(a, b)| (a - b).abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn zip<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = (i64, i64)> + 'a {
+        self.coordinates
+            .iter()
+            .zip(other.coordinates.iter())
+            .map(|(&a, &b)| (a, b))
+    }
+
+    /// The `3^N - 1` points that differ from this one by at most 1 in every coordinate.
+    pub fn neighbours(&self) -> Vec<Self> {
+        let mut result = Vec::with_capacity(3usize.pow(N as u32) - 1);
+        let mut offset = [-1; N];
+        loop {
+            if offset.iter().any(|&o| o != 0) {
+                result.push(*self + PointN::new(offset));
+            }
+
+            // count through the offsets as a base 3 number
+            let mut idx = 0;
+            while idx < N && offset[idx] == 1 {
+                offset[idx] = -1;
+                idx += 1;
+            }
+            if idx == N {
+                return result;
+            }
+            offset[idx] += 1;
+        }
+    }
+}
+
+impl From<Point2<i64>> for PointN<2> {
+    fn from(p: Point2<i64>) -> Self {
+        PointN::new([p.x, p.y])
+    }
+}
+
+impl<const N: usize> From<[i64; N]> for PointN<N> {
+    fn from(coordinates: [i64; N]) -> Self {
+        PointN::new(coordinates)
+    }
+}
+
+impl<const N: usize> Add for PointN<N> {
+    type Output = PointN<N>;
+
+    fn add(mut self, rhs: PointN<N>) -> Self::Output {
+        for (c, r) in self.coordinates.iter_mut().zip(rhs.coordinates.iter()) {
+            *c += r;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Sub for PointN<N> {
+    type Output = PointN<N>;
+
+    fn sub(mut self, rhs: PointN<N>) -> Self::Output {
+        for (c, r) in self.coordinates.iter_mut().zip(rhs.coordinates.iter()) {
+            *c -= r;
+        }
+        self
+    }
+}
+
+impl Point3 {
+    /// Adds a fourth coordinate with value `w`.
+    pub fn extend(&self, w: i64) -> Point4 {
+        let [x, y, z] = self.coordinates;
+        PointN::new([x, y, z, w])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::point::{Point2, Point3, PointN, Vector2};
+
+    #[test]
+    fn point_and_vector_arithmetic() {
+        let mut p = Point2::new(1, 2) + Vector2::new(3, -4);
+        assert_eq!(p, Point2::new(4, -2));
+
+        p -= Vector2::new(1, 1);
+        assert_eq!(p, Point2::new(3, -3));
+        assert_eq!(p - Point2::new(1, 1), Vector2::new(2, -4));
+        assert_eq!(-Vector2::new(2, -4) * 3, Vector2::new(-6, 12));
+    }
+
+    #[test]
+    fn distances() {
+        let p = Point2::new(-1, 4);
+        let q = Point2::new(3, 2);
+        assert_eq!(p.manhattan_distance(&q), 6);
+        assert_eq!(p.chebyshev_distance(&q), 4);
+
+        let p = Point2::<usize>::new(5, 1);
+        assert_eq!(p.manhattan_distance(&Point2::new(2, 3)), 5);
+
+        assert_eq!(Vector2::new(-3, 2).manhattan_length(), 5);
+        assert_eq!(Vector2::new(-3, 2).chebyshev_length(), 3);
+    }
+
+    #[test]
+    fn quarter_turns() {
+        let v = Vector2::new(10, 4);
+        assert_eq!(v.rotate_clockwise(1), Vector2::new(4, -10));
+        assert_eq!(v.rotate_clockwise(2), Vector2::new(-10, -4));
+        assert_eq!(v.rotate_clockwise(3), Vector2::new(-4, 10));
+        assert_eq!(v.rotate_clockwise(4), v);
+        assert_eq!(v.rotate_counter_clockwise(1), v.rotate_clockwise(3));
+        assert_eq!(v.rotate_clockwise(-5), v.rotate_counter_clockwise(1));
+    }
+
+    #[test]
+    fn checked_cast_between_signed_and_unsigned() {
+        assert_eq!(
+            Point2::<isize>::new(2, 3).checked_cast::<usize>(),
+            Some(Point2::new(2, 3))
+        );
+        assert_eq!(Point2::<isize>::new(-1, 3).checked_cast::<usize>(), None);
+        assert_eq!(
+            Vector2::<usize>::new(usize::MAX, 0).checked_cast::<isize>(),
+            None
+        );
+    }
+
+    #[test]
+    fn n_dimensional_points() {
+        let p = Point3::new([1, -2, 3]);
+        assert_eq!(p + PointN::new([1, 1, 1]), PointN::new([2, -1, 4]));
+        assert_eq!(p.manhattan_distance(&Point3::origin()), 6);
+        assert_eq!(p.chebyshev_distance(&Point3::origin()), 3);
+        assert_eq!(p.extend(7), PointN::new([1, -2, 3, 7]));
+    }
+
+    #[test]
+    fn neighbours_of_n_dimensional_points() {
+        let p = PointN::new([5, 5]);
+        let mut n = p.neighbours();
+        n.sort();
+        assert_eq!(
+            n,
+            vec![
+                PointN::new([4, 4]),
+                PointN::new([4, 5]),
+                PointN::new([4, 6]),
+                PointN::new([5, 4]),
+                PointN::new([5, 6]),
+                PointN::new([6, 4]),
+                PointN::new([6, 5]),
+                PointN::new([6, 6]),
+            ]
+        );
+        assert_eq!(PointN::new([0; 4]).neighbours().len(), 80);
+    }
+}
@@ -1,10 +1,388 @@
+use std::env;
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
+/// Directories to search for inputs, separated like `PATH`.
+/// They are searched before those of the config file.
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+/// The user whose inputs are read, overriding the config file.
+pub const USER_VAR: &str = "AOC_USER";
+/// The path of the config file, if not `CONFIG_FILE` in the working directory.
+pub const CONFIG_VAR: &str = "AOC_INPUT_CONFIG";
+pub const CONFIG_FILE: &str = "aoc-input.cfg";
+
+/// Whether to read the real puzzle input or the example from the puzzle text.
+/// Examples are stored next to the real inputs as `<puzzle>.example.txt`.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Kind {
+    Real,
+    Example,
+}
+
+impl Kind {
+    fn file_stem(&self, puzzle: &str) -> String {
+        match self {
+            Kind::Real => puzzle.to_string(),
+            Kind::Example => format!("{}.example", puzzle),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum InputError {
+    NotFound { file: String, tried: Vec<PathBuf> },
+    Read { path: PathBuf, kind: io::ErrorKind },
+    Config { line: usize, message: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::NotFound { file, tried } => {
+                write!(f, "Could not find puzzle file {}, tried", file)?;
+                for path in tried {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
+            InputError::Read { path, kind } => {
+                write!(
+                    f,
+                    "Could not read puzzle file {}: {:?}",
+                    path.display(),
+                    kind
+                )
+            }
+            InputError::Config { line, message } => {
+                write!(f, "Error in input config on line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+/// Where to look for puzzle inputs.
+///
+/// Every directory of the search path is tried in order. If a user
+/// is set, the subdirectory named by the user is tried before the
+/// directory itself, so several people can keep their inputs side by side:
+///
+/// ```text
+/// input/
+///   day01.txt
+///   day01.example.txt
+///   alice/
+///     day01.txt
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct InputConfig {
+    search_path: Vec<PathBuf>,
+    user: Option<String>,
+}
+
+impl Default for InputConfig {
+    /// `input` in the working directory, then `input` in the crate.
+    fn default() -> Self {
+        InputConfig::new(
+            vec![
+                PathBuf::from("input"),
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("input"),
+            ],
+            None,
+        )
+    }
+}
+
+impl InputConfig {
+    pub fn new(search_path: Vec<PathBuf>, user: Option<String>) -> Self {
+        InputConfig { search_path, user }
+    }
+
+    /// Reads a config file of `key = value` lines. `dir` adds a directory
+    /// to the search path and may be repeated, `user` sets the user.
+    /// Lines starting with `#` are comments. Without any `dir` the
+    /// default search path is used.
+    pub fn parse(contents: &str) -> Result<Self, InputError> {
+        let mut config = InputConfig::new(Vec::new(), None);
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| InputError::Config {
+                line: idx + 1,
+                message: message.to_string(),
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value"))?;
+            let value = value.trim();
+            if value.is_empty() {
+                return Err(error("missing value"));
+            }
+            match key.trim() {
+                "dir" => config.search_path.push(PathBuf::from(value)),
+                "user" => config.user = Some(value.to_string()),
+                _ => return Err(error("unknown key, expected dir or user")),
+            }
+        }
+
+        if config.search_path.is_empty() {
+            config.search_path = InputConfig::default().search_path;
+        }
+        Ok(config)
+    }
+
+    /// The config given by the environment: the config file, if there is
+    /// one, with the directories of `AOC_INPUT_DIR` put first and the
+    /// user replaced by `AOC_USER`.
+    pub fn from_env() -> Result<Self, InputError> {
+        let mut config = match env::var_os(CONFIG_VAR) {
+            Some(path) => InputConfig::read_config(Path::new(&path))?,
+            None if Path::new(CONFIG_FILE).is_file() => {
+                InputConfig::read_config(Path::new(CONFIG_FILE))?
+            }
+            None => InputConfig::default(),
+        };
+
+        if let Some(dirs) = env::var_os(INPUT_DIR_VAR) {
+            let mut search_path: Vec<PathBuf> = env::split_paths(&dirs).collect();
+            search_path.append(&mut config.search_path);
+            config.search_path = search_path;
+        }
+        if let Ok(user) = env::var(USER_VAR) {
+            config.user = Some(user);
+        }
+
+        Ok(config)
+    }
+
+    fn read_config(path: &Path) -> Result<Self, InputError> {
+        let contents = fs::read_to_string(path).map_err(|e| InputError::Read {
+            path: path.to_path_buf(),
+            kind: e.kind(),
+        })?;
+        InputConfig::parse(&contents)
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The paths tried for the input, in order.
+    pub fn candidates(&self, puzzle: &str, kind: Kind) -> Vec<PathBuf> {
+        let file = format!("{}.txt", kind.file_stem(puzzle));
+        let mut candidates = Vec::new();
+        for dir in &self.search_path {
+            if let Some(user) = &self.user {
+                candidates.push(dir.join(user).join(&file));
+            }
+            candidates.push(dir.join(&file));
+        }
+        candidates
+    }
+
+    /// Reads the first of the candidates that exists. With the
+    /// `embedded-inputs` feature, the embedded input is used when none do.
+    pub fn read(&self, puzzle: &str, kind: Kind) -> Result<String, InputError> {
+        let tried = self.candidates(puzzle, kind);
+        for path in &tried {
+            match fs::read_to_string(path) {
+                Ok(contents) => return Ok(contents),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(InputError::Read {
+                        path: path.clone(),
+                        kind: e.kind(),
+                    })
+                }
+            }
+        }
+
+        if let Some(contents) = embedded(&kind.file_stem(puzzle)) {
+            return Ok(contents.to_string());
+        }
+
+        Err(InputError::NotFound {
+            file: format!("{}.txt", kind.file_stem(puzzle)),
+            tried,
+        })
+    }
+}
+
+#[cfg(feature = "embedded-inputs")]
+macro_rules! embed {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_str!(concat!("../../input/", $name, ".txt")))),*]
+    };
+}
+
+/// The inputs built into the binary, by file name without `.txt`.
+#[cfg(feature = "embedded-inputs")]
+const EMBEDDED: &[(&str, &str)] = embed![
+    "day01",
+    "day01.example",
+    "day02",
+    "day03",
+    "day04",
+    "day05",
+    "day06",
+    "day07",
+    "day08",
+    "day09",
+    "day10",
+    "day11",
+    "day12",
+    "day13",
+    "day14",
+    "day15",
+    "day16",
+    "day17",
+    "day25",
+];
+
+#[cfg(feature = "embedded-inputs")]
+fn embedded(file_stem: &str) -> Option<&'static str> {
+    EMBEDDED
+        .iter()
+        .find(|(name, _)| *name == file_stem)
+        .map(|&(_, contents)| contents)
+}
+
+#[cfg(not(feature = "embedded-inputs"))]
+fn embedded(_file_stem: &str) -> Option<&'static str> {
+    None
+}
+
+/// Reads the real input of the puzzle, e.g. `day01`, as configured by the environment.
 pub fn read_input(puzzle: &str) -> String {
-    let mut filename = String::from("input/");
+    read(puzzle, Kind::Real)
+}
+
+/// Reads the example input of the puzzle, as configured by the environment.
+pub fn read_example(puzzle: &str) -> String {
+    read(puzzle, Kind::Example)
+}
+
+fn read(puzzle: &str, kind: Kind) -> String {
+    InputConfig::from_env()
+        .and_then(|config| config.read(puzzle, kind))
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::puzzle_input::{InputConfig, InputError, Kind};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc-input-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_config_file() {
+        let config = InputConfig::parse("# inputs\ndir = /a\n\ndir=b\nuser = alice\n").unwrap();
+        assert_eq!(
+            config.search_path(),
+            &[PathBuf::from("/a"), PathBuf::from("b")]
+        );
+        assert_eq!(config.user(), Some("alice"));
+
+        let config = InputConfig::parse("user = bob").unwrap();
+        assert_eq!(config.search_path(), InputConfig::default().search_path());
+    }
+
+    #[test]
+    fn malformed_config_file() {
+        assert_eq!(
+            InputConfig::parse("dir = a\ndirectory\n"),
+            Err(InputError::Config {
+                line: 2,
+                message: "expected key = value".to_string()
+            })
+        );
+        assert!(InputConfig::parse("path = a").is_err());
+        assert!(InputConfig::parse("user =").is_err());
+    }
+
+    #[test]
+    fn user_directories_are_tried_first() {
+        let config = InputConfig::new(
+            vec![PathBuf::from("a"), PathBuf::from("b")],
+            Some("alice".to_string()),
+        );
+        assert_eq!(
+            config.candidates("day01", Kind::Example),
+            vec![
+                Path::new("a/alice/day01.example.txt"),
+                Path::new("a/day01.example.txt"),
+                Path::new("b/alice/day01.example.txt"),
+                Path::new("b/day01.example.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_from_search_path() {
+        let shared = temp_dir("shared");
+        let mine = temp_dir("mine");
+        fs::create_dir_all(shared.join("alice")).unwrap();
+        fs::write(shared.join("day01.txt"), "shared").unwrap();
+        fs::write(shared.join("alice").join("day01.txt"), "alice").unwrap();
+        fs::write(mine.join("day02.txt"), "mine").unwrap();
+
+        let config = InputConfig::new(vec![mine.clone(), shared.clone()], None);
+        assert_eq!(config.read("day01", Kind::Real), Ok("shared".to_string()));
+        assert_eq!(config.read("day02", Kind::Real), Ok("mine".to_string()));
+
+        let config = InputConfig::new(vec![mine.clone(), shared.clone()], Some("alice".into()));
+        assert_eq!(config.read("day01", Kind::Real), Ok("alice".to_string()));
+
+        fs::remove_dir_all(shared).unwrap();
+        fs::remove_dir_all(mine).unwrap();
+    }
+
+    #[cfg(not(feature = "embedded-inputs"))]
+    #[test]
+    fn missing_input_lists_the_paths_tried() {
+        let config = InputConfig::new(vec![PathBuf::from("no-such-dir")], None);
+        let error = config.read("day01", Kind::Real).unwrap_err();
+        assert_eq!(
+            error,
+            InputError::NotFound {
+                file: "day01.txt".to_string(),
+                tried: vec![PathBuf::from("no-such-dir/day01.txt")]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Could not find puzzle file day01.txt, tried no-such-dir/day01.txt"
+        );
+    }
 
-    filename.push_str(puzzle);
-    filename.push_str(".txt");
+    #[cfg(feature = "embedded-inputs")]
+    #[test]
+    fn embedded_inputs_are_the_fallback() {
+        let config = InputConfig::new(vec![PathBuf::from("no-such-dir")], None);
+        assert_eq!(
+            config.read("day01", Kind::Example),
+            Ok(include_str!("../../input/day01.example.txt").to_string())
+        );
+    }
 
-    fs::read_to_string(filename).expect("Could not read puzzle file")
+    #[test]
+    fn example_input_next_to_real_input() {
+        let example = InputConfig::default().read("day01", Kind::Example).unwrap();
+        assert_eq!(example.lines().count(), 6);
+    }
 }
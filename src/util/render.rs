@@ -0,0 +1,434 @@
+use crate::util::map::{Map, Terrain};
+use crate::util::point::Point2;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A colour given by its red, green and blue components.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Colour {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Colour {
+    pub const BLACK: Colour = Colour::rgb(0, 0, 0);
+    pub const WHITE: Colour = Colour::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Colour { r, g, b }
+    }
+
+    /// The perceived brightness of the colour, used when writing grey scale images.
+    pub fn luma(&self) -> u8 {
+        let luma = 299 * self.r as u32 + 587 * self.g as u32 + 114 * self.b as u32;
+        (luma / 1000) as u8
+    }
+}
+
+/// Maps the terrain of a map to colours. Terrain is looked up by
+/// its `Terrain::to_char` representation.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colours: HashMap<char, Colour>,
+    default: Colour,
+}
+
+impl Palette {
+    /// Creates a palette where every terrain has the `default` colour.
+    pub fn new(default: Colour) -> Self {
+        Palette {
+            colours: HashMap::new(),
+            default,
+        }
+    }
+
+    /// Use `colour` for the terrain represented by `c`.
+    pub fn with(mut self, c: char, colour: Colour) -> Self {
+        self.colours.insert(c, colour);
+        self
+    }
+
+    pub fn colour_of(&self, c: char) -> Colour {
+        *self.colours.get(&c).unwrap_or(&self.default)
+    }
+
+    pub fn colour<T: Terrain>(&self, terrain: &T) -> Colour {
+        self.colour_of(terrain.to_char())
+    }
+}
+
+/// Renders the map as text where each terrain is drawn
+/// with its colour from the palette as the background.
+pub fn ansi_map<T: Terrain>(map: &Map<T>, palette: &Palette) -> String {
+    let mut s = String::with_capacity(map.size() * 20);
+    for cord in map.iter() {
+        let terrain = cord.terrain();
+        push_ansi_cell(&mut s, terrain.to_char(), palette.colour(terrain));
+        if cord.x() + 1 == map.width() {
+            s.push_str("\x1b[0m\n");
+        }
+    }
+    s
+}
+
+/// Renders a sparse set of points as text. The bounding box of the points
+/// is drawn, with `on` at every point and `off` everywhere else.
+pub fn ansi_points(points: &[Point2<i64>], on: (char, Colour), off: (char, Colour)) -> String {
+    let (min, max) = match bounds(points) {
+        Some(bounds) => bounds,
+        None => return String::new(),
+    };
+    let lookup: HashSet<&Point2<i64>> = points.iter().collect();
+
+    let mut s = String::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let (c, colour) = if lookup.contains(&Point2::new(x, y)) {
+                on
+            } else {
+                off
+            };
+            push_ansi_cell(&mut s, c, colour);
+        }
+        s.push_str("\x1b[0m\n");
+    }
+    s
+}
+
+fn push_ansi_cell(s: &mut String, c: char, colour: Colour) {
+    s.push_str(&format!(
+        "\x1b[48;2;{};{};{}m{}",
+        colour.r, colour.g, colour.b, c
+    ));
+}
+
+/// The smallest and largest coordinates of the points, if there are any.
+fn bounds(points: &[Point2<i64>]) -> Option<(Point2<i64>, Point2<i64>)> {
+    let first = *points.first()?;
+    let bounds = points.iter().fold((first, first), |(min, max), p| {
+        (
+            Point2::new(min.x.min(p.x), min.y.min(p.y)),
+            Point2::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    });
+    Some(bounds)
+}
+
+/// The largest number of pixels `Image::from_points` will allocate.
+pub const MAX_PIXELS: usize = 50_000_000;
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum ImageError {
+    /// The image would have more than `MAX_PIXELS` pixels.
+    TooLarge { pixels: u128 },
+}
+
+/// A raster image, which can be written as a PPM or PGM file.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Image {
+    pixels: Vec<Colour>,
+    width: usize,
+    height: usize,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Colour) -> Self {
+        Image {
+            pixels: vec![background; width * height],
+            width,
+            height,
+        }
+    }
+
+    /// Draws every coordinate of the map as a `scale` times `scale` block of pixels.
+    pub fn from_map<T: Terrain>(map: &Map<T>, palette: &Palette, scale: usize) -> Self {
+        let mut image = Image::new(map.width() * scale, map.height() * scale, palette.default);
+        for cord in map.iter() {
+            image.fill_block(cord.x(), cord.y(), scale, palette.colour(cord.terrain()));
+        }
+        image
+    }
+
+    /// Draws the bounding box of the points, where each point is a
+    /// `scale` times `scale` block of pixels.
+    pub fn from_points(
+        points: &[Point2<i64>],
+        on: Colour,
+        off: Colour,
+        scale: usize,
+    ) -> Result<Self, ImageError> {
+        let (min, max) = match bounds(points) {
+            Some(bounds) => bounds,
+            None => return Ok(Image::new(0, 0, off)),
+        };
+        let side = |low: i64, high: i64| (high as i128 - low as i128 + 1) as u128 * scale as u128;
+        let width = side(min.x, max.x);
+        let height = side(min.y, max.y);
+        let pixels = width.saturating_mul(height);
+        if pixels > MAX_PIXELS as u128 {
+            return Err(ImageError::TooLarge { pixels });
+        }
+
+        let mut image = Image::new(width as usize, height as usize, off);
+        for &p in points {
+            let x = (p.x as i128 - min.x as i128) as usize;
+            let y = (p.y as i128 - min.y as i128) as usize;
+            image.fill_block(x, y, scale, on);
+        }
+        Ok(image)
+    }
+
+    fn fill_block(&mut self, x: usize, y: usize, scale: usize, colour: Colour) {
+        for dy in 0..scale {
+            for dx in 0..scale {
+                self.set(x * scale + dx, y * scale + dy, colour);
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the colour at `(x, y)` if it is within the image.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Colour> {
+        if x >= self.width || y >= self.height {
+            None
+        } else {
+            Some(self.pixels[x + y * self.width])
+        }
+    }
+
+    /// Sets the colour at `(x, y)`. Pixels outside the image are ignored.
+    pub fn set(&mut self, x: usize, y: usize, colour: Colour) {
+        if x < self.width && y < self.height {
+            self.pixels[x + y * self.width] = colour;
+        }
+    }
+
+    /// Writes the image in the binary PPM (P6) format.
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let bytes: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|c| vec![c.r, c.g, c.b])
+            .collect();
+        out.write_all(&bytes)
+    }
+
+    /// Writes the image in the binary PGM (P5) format, using the luma of each colour.
+    pub fn write_pgm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let bytes: Vec<u8> = self.pixels.iter().map(Colour::luma).collect();
+        out.write_all(&bytes)
+    }
+
+    /// Saves the image to `path`. A path ending in `.pgm` is written
+    /// as grey scale, everything else as PPM.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut out = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("pgm") => self.write_pgm(&mut out)?,
+            _ => self.write_ppm(&mut out)?,
+        }
+        out.flush()
+    }
+}
+
+/// Writes numbered frames of a simulation into a directory,
+/// as `<prefix>_0000.ppm`, `<prefix>_0001.ppm` and so on.
+pub struct FrameWriter {
+    dir: PathBuf,
+    prefix: String,
+    extension: &'static str,
+    n_frames: usize,
+}
+
+impl FrameWriter {
+    /// Creates the directory if it does not exist.
+    pub fn create<P: AsRef<Path>>(dir: P, prefix: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(FrameWriter {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+            extension: "ppm",
+            n_frames: 0,
+        })
+    }
+
+    /// Write the frames as grey scale PGM files instead.
+    pub fn grey_scale(mut self) -> Self {
+        self.extension = "pgm";
+        self
+    }
+
+    /// Writes the next frame and returns the path it was written to.
+    pub fn write_frame(&mut self, image: &Image) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!(
+            "{}_{:04}.{}",
+            self.prefix, self.n_frames, self.extension
+        ));
+        image.save(&path)?;
+        self.n_frames += 1;
+        Ok(path)
+    }
+
+    /// Writes every frame and returns the number of frames written in total.
+    pub fn write_all<I>(&mut self, frames: I) -> io::Result<usize>
+    where
+        I: IntoIterator<Item = Image>,
+    {
+        for frame in frames {
+            self.write_frame(&frame)?;
+        }
+        Ok(self.n_frames)
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::map::{Map, Terrain, TerrainErr};
+    use crate::util::point::Point2;
+    use crate::util::render::{
+        ansi_map, ansi_points, Colour, FrameWriter, Image, ImageError, Palette, MAX_PIXELS,
+    };
+
+    #[derive(Eq, PartialEq, Debug)]
+    enum TestTerrain {
+        Wall,
+        Floor,
+    }
+
+    impl Terrain for TestTerrain {
+        fn from_char(c: char) -> Result<Self, TerrainErr> {
+            match c {
+                '#' => Ok(TestTerrain::Wall),
+                '.' => Ok(TestTerrain::Floor),
+                _ => Err(TerrainErr::UnknownTerrain(c)),
+            }
+        }
+
+        fn to_char(&self) -> char {
+            match self {
+                TestTerrain::Wall => '#',
+                TestTerrain::Floor => '.',
+            }
+        }
+    }
+
+    const RED: Colour = Colour::rgb(255, 0, 0);
+
+    fn palette() -> Palette {
+        Palette::new(Colour::WHITE).with('#', RED)
+    }
+
+    #[test]
+    fn palette_falls_back_to_default() {
+        let palette = palette();
+        assert_eq!(palette.colour(&TestTerrain::Wall), RED);
+        assert_eq!(palette.colour(&TestTerrain::Floor), Colour::WHITE);
+    }
+
+    #[test]
+    fn ansi_map_colours_each_cell() {
+        let map = Map::<TestTerrain>::configure("#.\n.#").unwrap();
+        let red = "\x1b[48;2;255;0;0m#";
+        let white = "\x1b[48;2;255;255;255m.";
+        assert_eq!(
+            ansi_map(&map, &palette()),
+            format!("{}{}\x1b[0m\n{}{}\x1b[0m\n", red, white, white, red)
+        );
+    }
+
+    #[test]
+    fn ansi_points_draws_bounding_box() {
+        let text = ansi_points(
+            &[Point2::new(-1, 0), Point2::new(0, 1)],
+            ('#', Colour::BLACK),
+            ('.', Colour::WHITE),
+        );
+        let on = "\x1b[48;2;0;0;0m#";
+        let off = "\x1b[48;2;255;255;255m.";
+        assert_eq!(
+            text,
+            format!("{}{}\x1b[0m\n{}{}\x1b[0m\n", on, off, off, on)
+        );
+        assert_eq!(ansi_points(&[], ('#', RED), ('.', RED)), "");
+    }
+
+    #[test]
+    fn scaled_image_from_map() {
+        let map = Map::<TestTerrain>::configure("#.").unwrap();
+        let image = Image::from_map(&map, &palette(), 2);
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(image.pixel(1, 1), Some(RED));
+        assert_eq!(image.pixel(2, 0), Some(Colour::WHITE));
+        assert_eq!(image.pixel(4, 0), None);
+    }
+
+    #[test]
+    fn image_from_negative_points() {
+        let image = Image::from_points(
+            &[Point2::new(-2, -2), Point2::new(0, -1)],
+            RED,
+            Colour::BLACK,
+            1,
+        )
+        .unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.pixel(0, 0), Some(RED));
+        assert_eq!(image.pixel(2, 1), Some(RED));
+        assert_eq!(image.pixel(1, 0), Some(Colour::BLACK));
+    }
+
+    #[test]
+    fn image_size_is_limited() {
+        let points = [Point2::new(i64::MIN, 0), Point2::new(i64::MAX, 0)];
+        match Image::from_points(&points, RED, Colour::BLACK, 1) {
+            Err(ImageError::TooLarge { pixels }) => assert!(pixels > MAX_PIXELS as u128),
+            _ => panic!("expected the image to be too large"),
+        }
+        let points = [Point2::new(0, 0), Point2::new(9, 9)];
+        assert!(Image::from_points(&points, RED, Colour::BLACK, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn write_ppm_and_pgm() {
+        let mut image = Image::new(2, 1, Colour::WHITE);
+        image.set(0, 0, RED);
+
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\xff\x00\x00\xff\xff\xff".to_vec());
+
+        let mut pgm = Vec::new();
+        image.write_pgm(&mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n2 1\n255\n\x4c\xff".to_vec());
+    }
+
+    #[test]
+    fn frames_are_numbered() {
+        let dir = std::env::temp_dir().join(format!("render-frames-{}", std::process::id()));
+        let mut writer = FrameWriter::create(&dir, "round").unwrap().grey_scale();
+        let frames = vec![Image::new(1, 1, RED), Image::new(1, 1, Colour::WHITE)];
+
+        assert_eq!(writer.write_all(frames).unwrap(), 2);
+        assert!(dir.join("round_0000.pgm").exists());
+        assert!(dir.join("round_0001.pgm").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
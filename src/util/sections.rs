@@ -0,0 +1,249 @@
+use crate::util::parse::{parse_all, ParseError, Parser};
+use std::fmt;
+use std::str::FromStr;
+
+/// A named part of a larger text. The name is the header line
+/// without its trailing `:`, and the body is every line up to the next header.
+#[derive(Eq, PartialEq, Debug)]
+pub struct Section<'a> {
+    name: &'a str,
+    lines: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Section<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The non-blank lines of the body.
+    pub fn lines(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.lines.iter().map(|&(_, line)| line)
+    }
+
+    /// The line number of the first line of the body, counting from 1.
+    pub fn line(&self) -> Option<usize> {
+        self.lines.first().map(|&(line, _)| line)
+    }
+
+    /// Parses every non-blank line of the body as a record. Errors
+    /// refer to the line number in the entire text.
+    pub fn records<T, P>(&self, parser: P) -> Result<Vec<T>, SectionError>
+    where
+        P: Parser<'a, T>,
+    {
+        self.lines
+            .iter()
+            .map(|&(line_number, line)| {
+                parse_all(&parser, line, line_number).map_err(|error| SectionError::Record {
+                    section: self.name.to_string(),
+                    error,
+                })
+            })
+            .collect()
+    }
+
+    /// Parses the body as a whole, with the lines joined by `\n`.
+    pub fn parse<T: FromStr>(&self) -> Result<T, SectionError> {
+        let body: Vec<&str> = self.lines().collect();
+        body.join("\n")
+            .parse::<T>()
+            .map_err(|_| SectionError::Malformed(self.name.to_string()))
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum SectionError {
+    Missing(String),
+    Unexpected(String),
+    Duplicate(String),
+    /// The body of the section could not be parsed as a whole.
+    Malformed(String),
+    /// A line of the section could not be parsed.
+    Record {
+        section: String,
+        error: ParseError,
+    },
+}
+
+impl fmt::Display for SectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SectionError::Missing(name) => write!(f, "missing section '{}'", name),
+            SectionError::Unexpected(name) => write!(f, "unexpected section '{}'", name),
+            SectionError::Duplicate(name) => write!(f, "duplicate section '{}'", name),
+            SectionError::Malformed(name) => write!(f, "malformed section '{}'", name),
+            SectionError::Record { section, error } => {
+                write!(f, "section '{}', {}", section, error)
+            }
+        }
+    }
+}
+
+/// A text split into sections by header lines, e.g.
+///
+/// ```text
+/// your ticket:
+/// 7,1,14
+/// ```
+///
+/// A header is a line ending in `:`. Anything before the first
+/// header is a section named by the `preamble` given on creation.
+#[derive(Eq, PartialEq, Debug)]
+pub struct Sections<'a> {
+    sections: Vec<Section<'a>>,
+}
+
+impl<'a> Sections<'a> {
+    pub fn new(input: &'a str, preamble: &'a str) -> Result<Self, SectionError> {
+        let mut sections = vec![Section {
+            name: preamble,
+            lines: Vec::new(),
+        }];
+
+        for (idx, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if let Some(name) = line.strip_suffix(':') {
+                if sections.iter().any(|s| s.name == name) {
+                    return Err(SectionError::Duplicate(name.to_string()));
+                }
+                sections.push(Section {
+                    name,
+                    lines: Vec::new(),
+                });
+            } else if !line.is_empty() {
+                let current = sections.last_mut().expect("There is always a section");
+                current.lines.push((idx + 1, line));
+            }
+        }
+
+        if sections[0].lines.is_empty() {
+            sections.remove(0);
+        }
+
+        Ok(Sections { sections })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Section<'a>, SectionError> {
+        self.sections
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| SectionError::Missing(name.to_string()))
+    }
+
+    /// The names of the sections in the order they appear.
+    pub fn names(&self) -> Vec<&'a str> {
+        self.sections.iter().map(Section::name).collect()
+    }
+
+    /// Checks that the sections are exactly those named, in any order.
+    pub fn expect_exactly(&self, names: &[&str]) -> Result<(), SectionError> {
+        for name in names {
+            self.get(name)?;
+        }
+        match self.sections.iter().find(|s| !names.contains(&s.name)) {
+            Some(section) => Err(SectionError::Unexpected(section.name.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::parse::{integer, literal, separated_list1, ParseError};
+    use crate::util::sections::{SectionError, Sections};
+
+    const NOTES: &str = "class: 1-3 or 5-7\n\
+row: 6-11 or 33-44\n\
+\n\
+your ticket:\n\
+7,1,14\n\
+\n\
+nearby tickets:\n\
+7,3,47\n\
+40,4,50";
+
+    #[test]
+    fn split_into_named_sections() {
+        let sections = Sections::new(NOTES, "rules").unwrap();
+
+        assert_eq!(
+            sections.names(),
+            vec!["rules", "your ticket", "nearby tickets"]
+        );
+        let rules: Vec<&str> = sections.get("rules").unwrap().lines().collect();
+        assert_eq!(rules, vec!["class: 1-3 or 5-7", "row: 6-11 or 33-44"]);
+        assert_eq!(sections.get("nearby tickets").unwrap().line(), Some(8));
+    }
+
+    #[test]
+    fn parse_records_of_a_section() {
+        let sections = Sections::new(NOTES, "rules").unwrap();
+        let ticket = separated_list1(integer::<i64>(), literal(","));
+
+        assert_eq!(
+            sections.get("nearby tickets").unwrap().records(&ticket),
+            Ok(vec![vec![7, 3, 47], vec![40, 4, 50]])
+        );
+        assert_eq!(
+            sections.get("rules").unwrap().records(&ticket),
+            Err(SectionError::Record {
+                section: "rules".to_string(),
+                error: ParseError::new(1, 1, "an integer", "\"class: 1-3\"")
+            })
+        );
+    }
+
+    #[test]
+    fn validate_section_names() {
+        let sections = Sections::new(NOTES, "rules").unwrap();
+
+        assert_eq!(
+            sections.expect_exactly(&["rules", "your ticket", "nearby tickets"]),
+            Ok(())
+        );
+        assert_eq!(
+            sections.expect_exactly(&["rules", "nearby tickets"]),
+            Err(SectionError::Unexpected("your ticket".to_string()))
+        );
+        assert_eq!(
+            sections.expect_exactly(&["rules", "your ticket", "nearby tickets", "notes"]),
+            Err(SectionError::Missing("notes".to_string()))
+        );
+    }
+
+    #[test]
+    fn duplicate_sections_are_an_error() {
+        assert_eq!(
+            Sections::new("a:\n1\nb:\n2\na:\n3", "").unwrap_err(),
+            SectionError::Duplicate("a".to_string())
+        );
+    }
+
+    #[test]
+    fn errors_name_the_section() {
+        assert_eq!(
+            SectionError::Missing("notes".to_string()).to_string(),
+            "missing section 'notes'"
+        );
+        let error = Sections::new(NOTES, "rules")
+            .unwrap()
+            .get("your ticket")
+            .unwrap()
+            .records(integer::<i64>())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "section 'your ticket', line 5, column 2: expected end of input, found \",1,14\""
+        );
+    }
+
+    #[test]
+    fn empty_preamble_is_not_a_section() {
+        let sections = Sections::new("\n\nheader:\nbody\n", "preamble").unwrap();
+        assert_eq!(sections.names(), vec!["header"]);
+        assert_eq!(
+            sections.get("preamble").unwrap_err(),
+            SectionError::Missing("preamble".to_string())
+        );
+    }
+}
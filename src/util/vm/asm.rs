@@ -0,0 +1,475 @@
+use crate::util::parse::{
+    alt, char_where, integer, literal, map, pair, parse_all, preceded, recognize, take_while,
+    take_while1, terminated, ws, Failure, ParseError, Parser,
+};
+use crate::util::vm::isa::{Flow, Instruction, Opcode};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    MissingArgument,
+    UnexpectedArgument(String),
+    /// A label given to an instruction that is not a jump.
+    BadArgument(String),
+    /// The line does not follow the syntax of the assembler.
+    Expected {
+        expected: String,
+        found: String,
+    },
+    DuplicateLabel(String),
+    UnknownLabel(String),
+    /// Text that should be a single instruction has this many.
+    NotOneInstruction(usize),
+}
+
+/// The line and the column count from 1, and the column in chars.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct AsmError {
+    line: usize,
+    column: usize,
+    kind: AsmErrorKind,
+}
+
+impl AsmError {
+    fn new(line: usize, column: usize, kind: AsmErrorKind) -> Self {
+        AsmError { line, column, kind }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn kind(&self) -> &AsmErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown instruction {}", m),
+            AsmErrorKind::MissingArgument => write!(f, "missing argument"),
+            AsmErrorKind::UnexpectedArgument(a) => write!(f, "unexpected argument {}", a),
+            AsmErrorKind::BadArgument(a) => write!(f, "bad argument {}", a),
+            AsmErrorKind::Expected { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            AsmErrorKind::DuplicateLabel(l) => write!(f, "label {} is defined twice", l),
+            AsmErrorKind::UnknownLabel(l) => write!(f, "unknown label {}", l),
+            AsmErrorKind::NotOneInstruction(n) => write!(f, "{} instructions instead of one", n),
+        }
+    }
+}
+
+impl From<ParseError> for AsmError {
+    fn from(error: ParseError) -> Self {
+        AsmError::new(
+            error.line(),
+            error.column(),
+            AsmErrorKind::Expected {
+                expected: error.expected().to_string(),
+                found: error.found().to_string(),
+            },
+        )
+    }
+}
+
+/// An argument that is a label until all labels are known.
+#[derive(Clone, Copy)]
+enum Argument<'a> {
+    Value(i64),
+    Label(&'a str),
+}
+
+/// A parsed value together with the length of the line from where it starts,
+/// which gives its column once the line is known.
+type Located<T> = (usize, T);
+
+/// The labels of a line and its instruction, if it has one.
+struct Line<'a> {
+    labels: Vec<Located<&'a str>>,
+    statement: Option<Statement<'a>>,
+}
+
+/// An instruction before its mnemonic and arguments are checked.
+struct Statement<'a> {
+    mnemonic: Located<&'a str>,
+    arguments: Vec<Located<Argument<'a>>>,
+}
+
+fn located<'a, T, P>(parser: P) -> impl Parser<'a, Located<T>>
+where
+    P: Parser<'a, T>,
+{
+    move |input: &'a str| parser(input).map(|(value, rest)| ((input.len(), value), rest))
+}
+
+/// A letter or `_` followed by letters, digits and `_`, e.g. `loop_2`.
+fn label<'a>() -> impl Parser<'a, &'a str> {
+    recognize(pair(
+        char_where(|c| c.is_ascii_alphabetic() || c == '_', "a label"),
+        take_while(|c| c.is_ascii_alphanumeric() || c == '_'),
+    ))
+}
+
+/// Everything after `#` or `;`.
+fn comment<'a>() -> impl Parser<'a, &'a str> {
+    preceded(
+        char_where(|c| c == '#' || c == ';', "a comment"),
+        take_while(|_| true),
+    )
+}
+
+fn at_line_end(input: &str) -> bool {
+    input.is_empty() || comment()(input).is_ok()
+}
+
+/// Succeeds, without consuming anything, where a mnemonic or an argument may end.
+fn token_end<'a>() -> impl Parser<'a, ()> {
+    move |input: &'a str| match input.chars().next() {
+        Some(c) if !c.is_whitespace() && !at_line_end(input) => Err(Failure::new(
+            input,
+            "a space, a comment or the end of the line",
+        )),
+        _ => Ok(((), input)),
+    }
+}
+
+/// An offset such as `+3` or `-2`, or a label.
+fn argument<'a>() -> impl Parser<'a, Argument<'a>> {
+    terminated(
+        alt(
+            map(integer::<i64>(), Argument::Value),
+            map(label(), Argument::Label),
+        ),
+        token_end(),
+    )
+}
+
+/// A mnemonic and any number of arguments separated by spaces.
+fn statement<'a>() -> impl Parser<'a, Statement<'a>> {
+    let mnemonic = located(terminated(
+        take_while1(|c| c.is_ascii_alphabetic(), "a label or an instruction"),
+        token_end(),
+    ));
+    let argument = preceded(ws(), located(argument()));
+    move |input: &'a str| {
+        let (mnemonic, mut rest) = mnemonic(input)?;
+        let mut arguments = Vec::new();
+        while !at_line_end(ws()(rest)?.1) {
+            let (argument, after) = argument(rest)?;
+            arguments.push(argument);
+            rest = after;
+        }
+        Ok((
+            Statement {
+                mnemonic,
+                arguments,
+            },
+            rest,
+        ))
+    }
+}
+
+/// `<label>:`* `<mnemonic> <argument>*`? `<comment>`?, with spaces between.
+fn line<'a>() -> impl Parser<'a, Line<'a>> {
+    let label_definition = terminated(located(label()), pair(literal(":"), ws()));
+    let statement = statement();
+    let line_end = pair(ws(), take_while(|_| true));
+    move |input: &'a str| {
+        let (_, mut rest) = ws()(input)?;
+        let mut labels = Vec::new();
+        while let Ok((label, after)) = label_definition(rest) {
+            labels.push(label);
+            rest = after;
+        }
+        let statement = if at_line_end(rest) {
+            None
+        } else {
+            let (statement, after) = statement(rest)?;
+            rest = after;
+            Some(statement)
+        };
+        let (_, rest) = line_end(rest)?;
+        Ok((Line { labels, statement }, rest))
+    }
+}
+
+/// Reads a program of one instruction per line, such as `acc +3` or `jmp -2`.
+///
+/// Everything after `#` or `;` is a comment. A line may start with labels
+/// such as `loop:`, and a jump may use a label instead of an offset.
+/// A label after the last instruction is the end of the program.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AsmError> {
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut parsed: Vec<(usize, usize, Opcode, Argument)> = Vec::new();
+
+    for (idx, text) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let column = |from_end: usize| text[..text.len() - from_end].chars().count() + 1;
+        let error = |from_end, kind| AsmError::new(line_number, column(from_end), kind);
+        let line = parse_all(line(), text, line_number)?;
+
+        for (from_end, label) in line.labels {
+            if labels.insert(label, parsed.len()).is_some() {
+                return Err(error(
+                    from_end,
+                    AsmErrorKind::DuplicateLabel(label.to_string()),
+                ));
+            }
+        }
+
+        let statement = match line.statement {
+            None => continue,
+            Some(statement) => statement,
+        };
+        let (from_end, mnemonic) = statement.mnemonic;
+        let opcode = Opcode::from_mnemonic(mnemonic).ok_or_else(|| {
+            error(
+                from_end,
+                AsmErrorKind::UnknownMnemonic(mnemonic.to_string()),
+            )
+        })?;
+        let info = opcode.info();
+
+        let mut arguments = statement.arguments.into_iter();
+        let (from_end, argument) = if info.takes_argument {
+            let end_of_mnemonic = from_end - mnemonic.len();
+            arguments
+                .next()
+                .ok_or_else(|| error(end_of_mnemonic, AsmErrorKind::MissingArgument))?
+        } else {
+            (from_end, Argument::Value(0))
+        };
+        if let Some((from_end, _)) = arguments.next() {
+            let extra = text[text.len() - from_end..]
+                .split(|c: char| c.is_whitespace() || c == '#' || c == ';')
+                .next()
+                .unwrap_or_default();
+            return Err(error(
+                from_end,
+                AsmErrorKind::UnexpectedArgument(extra.to_string()),
+            ));
+        }
+        if let Argument::Label(label) = argument {
+            if info.flow != Flow::Jump {
+                return Err(error(
+                    from_end,
+                    AsmErrorKind::BadArgument(label.to_string()),
+                ));
+            }
+        }
+        parsed.push((line_number, column(from_end), opcode, argument));
+    }
+
+    parsed
+        .into_iter()
+        .enumerate()
+        .map(|(address, (line, column, opcode, argument))| {
+            let argument = match argument {
+                Argument::Value(value) => value,
+                Argument::Label(label) => {
+                    let target = *labels.get(label).ok_or_else(|| {
+                        AsmError::new(line, column, AsmErrorKind::UnknownLabel(label.to_string()))
+                    })?;
+                    target as i64 - address as i64
+                }
+            };
+            Ok(Instruction::new(opcode, argument))
+        })
+        .collect()
+}
+
+/// Writes one instruction per line, so that `assemble` gives the same program.
+pub fn disassemble(program: &[Instruction]) -> String {
+    program
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
+        .collect()
+}
+
+/// Like `disassemble`, but jumps within the program go to labels
+/// such as `L12` for the address 12, and the instructions are indented.
+pub fn disassemble_with_labels(program: &[Instruction]) -> String {
+    let target = |address: usize, instruction: &Instruction| {
+        let target = address as i64 + instruction.argument();
+        (instruction.info().flow == Flow::Jump && 0 <= target && target <= program.len() as i64)
+            .then_some(target as usize)
+    };
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(address, instruction)| target(address, instruction))
+        .collect();
+
+    let mut text = String::new();
+    for (address, instruction) in program.iter().enumerate() {
+        if targets.contains(&address) {
+            text.push_str(&format!("L{}:\n", address));
+        }
+        match target(address, instruction) {
+            Some(to) => text.push_str(&format!(
+                "    {} L{}\n",
+                instruction.opcode().mnemonic(),
+                to
+            )),
+            None => text.push_str(&format!("    {}\n", instruction)),
+        }
+    }
+    if targets.contains(&program.len()) {
+        text.push_str(&format!("L{}:\n", program.len()));
+    }
+    text
+}
+
+/// A single instruction without labels.
+impl FromStr for Instruction {
+    type Err = AsmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut program = assemble(s)?;
+        match program.len() {
+            1 => Ok(program.remove(0)),
+            n => Err(AsmError::new(1, 1, AsmErrorKind::NotOneInstruction(n))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::asm::{assemble, disassemble, disassemble_with_labels, AsmErrorKind};
+    use crate::util::vm::isa::{Instruction, Opcode};
+
+    #[test]
+    fn labels_and_comments() {
+        let program = assemble(
+            "# add one forever
+start:  acc +1      ; one more
+        jmp check
+back:   nop +0
+check:
+        mul 1
+        jmp start   # again
+end: hlt",
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            vec![
+                Instruction::new(Opcode::Acc, 1),
+                Instruction::new(Opcode::Jmp, 2),
+                Instruction::new(Opcode::Nop, 0),
+                Instruction::new(Opcode::Mul, 1),
+                Instruction::new(Opcode::Jmp, -4),
+                Instruction::new(Opcode::Hlt, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn label_after_the_last_instruction() {
+        let program = assemble("jmp end\nacc +1\nend:").unwrap();
+        assert_eq!(program[0], Instruction::new(Opcode::Jmp, 2));
+    }
+
+    #[test]
+    fn assembler_errors() {
+        let kind = |source: &str| assemble(source).unwrap_err().kind().clone();
+        assert_eq!(kind("mov +1"), AsmErrorKind::UnknownMnemonic("mov".into()));
+        assert_eq!(kind("acc"), AsmErrorKind::MissingArgument);
+        assert_eq!(
+            kind("acc 1 2"),
+            AsmErrorKind::UnexpectedArgument("2".into())
+        );
+        assert_eq!(kind("hlt 2"), AsmErrorKind::UnexpectedArgument("2".into()));
+        assert_eq!(kind("acc x"), AsmErrorKind::BadArgument("x".into()));
+        assert_eq!(
+            kind("jmp 1x"),
+            AsmErrorKind::Expected {
+                expected: "a space, a comment or the end of the line".into(),
+                found: "\"x\"".into()
+            }
+        );
+        assert_eq!(
+            kind("jmp nowhere"),
+            AsmErrorKind::UnknownLabel("nowhere".into())
+        );
+        assert_eq!(
+            kind("a: nop 0\na: nop 0"),
+            AsmErrorKind::DuplicateLabel("a".into())
+        );
+        assert_eq!(
+            kind("1a: nop 0"),
+            AsmErrorKind::Expected {
+                expected: "a label or an instruction".into(),
+                found: "\"1a: nop 0\"".into()
+            }
+        );
+
+        let error = assemble("nop +0\n\n# fine\njmp x").unwrap_err();
+        assert_eq!((error.line(), error.column()), (4, 5));
+        assert_eq!(error.to_string(), "line 4, column 5: unknown label x");
+    }
+
+    #[test]
+    fn errors_point_at_their_column() {
+        let error = |source: &str| assemble(source).unwrap_err().to_string();
+        assert_eq!(error("  acc"), "line 1, column 6: missing argument");
+        assert_eq!(
+            error("nop +0\nl:  acc +1 -2 # two"),
+            "line 2, column 12: unexpected argument -2"
+        );
+        assert_eq!(
+            error("jmp +99999999999999999999"),
+            "line 1, column 5: expected an integer in range or a label, found \"+999999999\""
+        );
+        assert_eq!(
+            error("acc +1 ! comment"),
+            "line 1, column 8: expected an integer or a label, found \"! comment\""
+        );
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let source = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\nmul -2\nhlt\n";
+        let program = assemble(source).unwrap();
+        assert_eq!(disassemble(&program), source);
+        assert_eq!(
+            assemble(&disassemble_with_labels(&program)).unwrap(),
+            program
+        );
+    }
+
+    #[test]
+    fn disassemble_with_labels_for_jumps() {
+        let program = assemble("jmp +2\nacc +1\njmp -2\njmp +1\njmp +7").unwrap();
+        assert_eq!(
+            disassemble_with_labels(&program),
+            "L0:\n    jmp L2\n    acc +1\nL2:\n    jmp L0\n    jmp L4\nL4:\n    jmp +7\n"
+        );
+    }
+
+    #[test]
+    fn parse_single_instructions() {
+        assert_eq!(
+            "jmp -42".parse::<Instruction>(),
+            Ok(Instruction::new(Opcode::Jmp, -42))
+        );
+        assert_eq!(
+            "jmp -42\nnop +0".parse::<Instruction>().unwrap_err().kind(),
+            &AsmErrorKind::NotOneInstruction(2)
+        );
+        assert_eq!(
+            "".parse::<Instruction>().unwrap_err().kind(),
+            &AsmErrorKind::NotOneInstruction(0)
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use crate::util::vm::isa::{Flow, Instruction};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+/// Where execution from an instruction ends up, ignoring overflows of the accumulator.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Fate {
+    /// Execution reaches the end or a stopping instruction.
+    Terminates,
+    /// Execution runs into a loop.
+    Loops,
+    /// Execution jumps outside the program, to the address.
+    Leaves(i64),
+}
+
+/// A problem found in a program without running it.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Lint {
+    /// Execution from the start loops, first repeating the instruction at the address.
+    Loop(usize),
+    /// The instructions are never reached from the start.
+    DeadCode(Range<usize>),
+    /// The jump at the address leaves the program.
+    OutOfRange { address: usize, target: i64 },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::Loop(address) => write!(f, "the program loops at {}", address),
+            Lint::DeadCode(range) if range.len() == 1 => {
+                write!(f, "instruction {} is never reached", range.start)
+            }
+            Lint::DeadCode(range) => write!(
+                f,
+                "instructions {} to {} are never reached",
+                range.start,
+                range.end - 1
+            ),
+            Lint::OutOfRange { address, target } => {
+                write!(f, "jump at {} leaves the program to {}", address, target)
+            }
+        }
+    }
+}
+
+/// The control flow graph of a program. Every instruction has at most one
+/// successor, and the address just past the last instruction is the end.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ControlFlowGraph {
+    program: Vec<Instruction>,
+    /// Per address, where execution continues, which may be outside the program.
+    successors: Vec<Option<i64>>,
+    /// Per address and the end, the instructions that continue there.
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &[Instruction]) -> Self {
+        let successors: Vec<Option<i64>> = program
+            .iter()
+            .enumerate()
+            .map(|(address, instruction)| instruction.successor(address as i64))
+            .collect();
+        let mut predecessors = vec![Vec::new(); program.len() + 1];
+        for (address, &successor) in successors.iter().enumerate() {
+            if let Some(next) =
+                successor.filter(|&next| 0 <= next && next as usize <= program.len())
+            {
+                predecessors[next as usize].push(address);
+            }
+        }
+        ControlFlowGraph {
+            program: program.to_vec(),
+            successors,
+            predecessors,
+        }
+    }
+
+    /// The number of instructions.
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// Where execution continues after the instruction, or `None` if it stops.
+    pub fn successor(&self, address: usize) -> Option<i64> {
+        self.successors[address]
+    }
+
+    /// The successor, if it is an instruction or the end of the program.
+    pub fn next(&self, address: usize) -> Option<usize> {
+        self.successors[address]
+            .filter(|&next| 0 <= next && next as usize <= self.len())
+            .map(|next| next as usize)
+    }
+
+    /// The instructions that continue at the address, which may be the end.
+    pub fn predecessors(&self, address: usize) -> &[usize] {
+        &self.predecessors[address]
+    }
+
+    /// Per address, whether execution from there terminates, either at the end
+    /// or at a stopping instruction. Overflows of the accumulator are not considered.
+    pub fn terminating(&self) -> Vec<bool> {
+        let mut terminating = vec![false; self.len() + 1];
+        let mut queue: VecDeque<usize> = (0..self.len())
+            .filter(|&address| self.successors[address].is_none())
+            .collect();
+        queue.push_back(self.len());
+        while let Some(address) = queue.pop_front() {
+            if !terminating[address] {
+                terminating[address] = true;
+                queue.extend(&self.predecessors[address]);
+            }
+        }
+        terminating.pop();
+        terminating
+    }
+
+    /// The addresses executed from 0, in order, until execution
+    /// ends, stops, leaves the program or repeats an instruction.
+    pub fn path_from_start(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.len()];
+        let mut path = Vec::new();
+        let mut address = 0;
+        while address < self.len() && !seen[address] {
+            seen[address] = true;
+            path.push(address);
+            match self.next(address) {
+                Some(next) => address = next,
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// Per address, whether it is executed when running from 0.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        for address in self.path_from_start() {
+            reachable[address] = true;
+        }
+        reachable
+    }
+
+    /// The ranges of instructions that are never executed when running from 0.
+    pub fn dead_code(&self) -> Vec<Range<usize>> {
+        let mut dead: Vec<Range<usize>> = Vec::new();
+        for (address, reachable) in self.reachable().into_iter().enumerate() {
+            if reachable {
+                continue;
+            }
+            match dead.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => dead.push(address..address + 1),
+            }
+        }
+        dead
+    }
+
+    /// The jumps that leave the program, with their targets.
+    pub fn out_of_range(&self) -> Vec<(usize, i64)> {
+        (0..self.len())
+            .filter_map(|address| match self.successors[address] {
+                Some(next) if self.next(address).is_none() => Some((address, next)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Per address, where execution from there ends up.
+    pub fn fates(&self) -> Vec<Fate> {
+        let mut fates: Vec<Option<Fate>> = vec![None; self.len()];
+        let mut on_path = vec![false; self.len()];
+        for start in 0..self.len() {
+            let mut path = Vec::new();
+            let mut address = start;
+            let fate = loop {
+                if address == self.len() {
+                    break Fate::Terminates;
+                }
+                if let Some(fate) = fates[address] {
+                    break fate;
+                }
+                if on_path[address] {
+                    break Fate::Loops;
+                }
+                on_path[address] = true;
+                path.push(address);
+                match self.successors[address] {
+                    None => break Fate::Terminates,
+                    Some(next) if self.next(address).is_some() => address = next as usize,
+                    Some(next) => break Fate::Leaves(next),
+                }
+            };
+            for address in path {
+                fates[address] = Some(fate);
+            }
+        }
+        fates.into_iter().flatten().collect()
+    }
+
+    /// The basic blocks: runs of instructions that are only entered at their
+    /// first instruction and only left after their last one.
+    pub fn basic_blocks(&self) -> Vec<Range<usize>> {
+        let leaders: Vec<bool> = (0..self.len())
+            .map(|address| {
+                address == 0
+                    || self.program[address - 1].info().flow != Flow::Next
+                    || self.predecessors[address]
+                        .iter()
+                        .any(|&from| from + 1 != address)
+            })
+            .collect();
+        let mut blocks: Vec<Range<usize>> = Vec::new();
+        for (address, leader) in leaders.into_iter().enumerate() {
+            match blocks.last_mut() {
+                Some(block) if !leader => block.end = address + 1,
+                _ => blocks.push(address..address + 1),
+            }
+        }
+        blocks
+    }
+
+    /// The problems found without running the program: a loop from the start,
+    /// dead code and jumps out of range.
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        if let Some(&last) = self.path_from_start().last() {
+            if self.fates()[0] == Fate::Loops {
+                if let Some(next) = self.next(last) {
+                    lints.push(Lint::Loop(next));
+                }
+            }
+        }
+        lints.extend(self.dead_code().into_iter().map(Lint::DeadCode));
+        lints.extend(
+            self.out_of_range()
+                .into_iter()
+                .map(|(address, target)| Lint::OutOfRange { address, target }),
+        );
+        lints
+    }
+
+    /// The graph of basic blocks in the Graphviz DOT language. Blocks are
+    /// named after their first address, dead blocks are dashed, and jumps out of
+    /// range lead to octagons.
+    pub fn to_dot(&self) -> String {
+        let reachable = self.reachable();
+        let mut dot = String::from("digraph program {\n");
+        let mut edges = String::new();
+        for block in self.basic_blocks() {
+            let label: String = block
+                .clone()
+                .map(|address| format!("{}: {}\\l", address, self.program[address]))
+                .collect();
+            let style = if reachable[block.start] {
+                ""
+            } else {
+                ", style=dashed"
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [shape=box{}, label=\"{}\"];\n",
+                block.start, style, label
+            ));
+
+            let last = block.end - 1;
+            match self.successors[last] {
+                None => {}
+                Some(next) if next == self.len() as i64 => {
+                    edges.push_str(&format!("    \"{}\" -> \"end\";\n", block.start))
+                }
+                Some(next) if self.next(last).is_some() => {
+                    edges.push_str(&format!("    \"{}\" -> \"{}\";\n", block.start, next))
+                }
+                Some(next) => {
+                    dot.push_str(&format!("    \"outside {}\" [shape=octagon];\n", next));
+                    edges.push_str(&format!(
+                        "    \"{}\" -> \"outside {}\";\n",
+                        block.start, next
+                    ));
+                }
+            }
+        }
+        dot.push_str("    \"end\" [shape=doublecircle];\n");
+        dot.push_str(&edges);
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::asm::assemble;
+    use crate::util::vm::cfg::{ControlFlowGraph, Fate, Lint};
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn successors_and_predecessors() {
+        let cfg = ControlFlowGraph::new(&assemble("jmp +2\nhlt\njmp -1\njmp -9").unwrap());
+        assert_eq!(cfg.len(), 4);
+        assert_eq!(cfg.next(0), Some(2));
+        assert_eq!(cfg.successor(1), None);
+        assert_eq!(cfg.successor(3), Some(-6));
+        assert_eq!(cfg.next(3), None);
+        assert_eq!(cfg.predecessors(1), &[2]);
+        assert_eq!(cfg.predecessors(4), &[] as &[usize]);
+    }
+
+    #[test]
+    fn terminating_instructions() {
+        let cfg = ControlFlowGraph::new(
+            &assemble("nop +0\njmp +3\nhlt\njmp -1\njmp +0\nacc +1\njmp -2").unwrap(),
+        );
+        assert_eq!(
+            cfg.terminating(),
+            vec![false, false, true, true, false, false, false]
+        );
+
+        let cfg = ControlFlowGraph::new(&assemble("nop +0\njmp +2\njmp -2\nacc +1").unwrap());
+        assert_eq!(cfg.terminating(), vec![true, true, true, true]);
+        assert_eq!(cfg.path_from_start(), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn path_until_a_repeat() {
+        let cfg = ControlFlowGraph::new(&assemble("nop +0\njmp +2\nacc +1\njmp -2").unwrap());
+        assert_eq!(cfg.path_from_start(), vec![0, 1, 3]);
+        let cfg = ControlFlowGraph::new(&assemble("nop +0\njmp -2").unwrap());
+        assert_eq!(cfg.path_from_start(), vec![0, 1]);
+    }
+
+    #[test]
+    fn blocks_and_fates_of_the_example() {
+        let cfg = ControlFlowGraph::new(&assemble(EXAMPLE).unwrap());
+        assert_eq!(cfg.basic_blocks(), vec![0..1, 1..3, 3..5, 5..6, 6..8, 8..9]);
+        let mut fates = vec![Fate::Loops; 8];
+        fates.push(Fate::Terminates);
+        assert_eq!(cfg.fates(), fates);
+        assert_eq!(cfg.dead_code(), vec![5..6, 8..9]);
+        assert_eq!(
+            cfg.lint(),
+            vec![Lint::Loop(1), Lint::DeadCode(5..6), Lint::DeadCode(8..9)]
+        );
+    }
+
+    #[test]
+    fn jumps_out_of_range() {
+        let cfg = ControlFlowGraph::new(&assemble("acc +1\njmp +5\nhlt\njmp -1").unwrap());
+        assert_eq!(
+            cfg.fates(),
+            vec![
+                Fate::Leaves(6),
+                Fate::Leaves(6),
+                Fate::Terminates,
+                Fate::Terminates
+            ]
+        );
+        assert_eq!(cfg.out_of_range(), vec![(1, 6)]);
+        assert_eq!(cfg.basic_blocks(), vec![0..2, 2..3, 3..4]);
+
+        let lints: Vec<String> = cfg.lint().iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            lints,
+            vec![
+                "instructions 2 to 3 are never reached",
+                "jump at 1 leaves the program to 6"
+            ]
+        );
+        assert_eq!(
+            cfg.to_dot(),
+            r#"digraph program {
+    "0" [shape=box, label="0: acc +1\l1: jmp +5\l"];
+    "outside 6" [shape=octagon];
+    "2" [shape=box, style=dashed, label="2: hlt\l"];
+    "3" [shape=box, style=dashed, label="3: jmp -1\l"];
+    "end" [shape=doublecircle];
+    "0" -> "outside 6";
+    "3" -> "2";
+}
+"#
+        );
+    }
+
+    #[test]
+    fn dead_code_and_clean_programs() {
+        let cfg = ControlFlowGraph::new(&assemble("acc +1\njmp +2\nacc +5\nnop +0").unwrap());
+        assert_eq!(cfg.lint(), vec![Lint::DeadCode(2..3)]);
+        assert_eq!(Lint::Loop(4).to_string(), "the program loops at 4");
+        assert_eq!(
+            Lint::DeadCode(2..3).to_string(),
+            "instruction 2 is never reached"
+        );
+
+        let cfg = ControlFlowGraph::new(&assemble("acc +1\njmp +1\nnop +0").unwrap());
+        assert_eq!(cfg.lint(), vec![]);
+        assert!(ControlFlowGraph::new(&[]).lint().is_empty());
+        assert_eq!(
+            cfg.to_dot(),
+            "digraph program {
+    \"0\" [shape=box, label=\"0: acc +1\\l1: jmp +1\\l\"];
+    \"2\" [shape=box, label=\"2: nop +0\\l\"];
+    \"end\" [shape=doublecircle];
+    \"0\" -> \"2\";
+    \"2\" -> \"end\";
+}
+"
+        );
+    }
+}
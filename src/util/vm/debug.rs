@@ -0,0 +1,366 @@
+use crate::util::json::Value;
+use crate::util::vm::isa::Opcode;
+use crate::util::vm::{Halt, Machine, Step};
+use std::convert::TryFrom;
+
+/// The steps a machine executed, and why it halted if it did.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Trace {
+    steps: Vec<Step>,
+    halt: Option<Halt>,
+}
+
+impl Trace {
+    /// Runs the machine until it halts, recording every step.
+    pub fn record(machine: &mut Machine) -> Trace {
+        let mut trace = Trace::default();
+        loop {
+            match machine.step() {
+                Ok(step) => trace.steps.push(step),
+                Err(halt) => {
+                    trace.halt = Some(halt);
+                    return trace;
+                }
+            }
+        }
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn halt(&self) -> Option<Halt> {
+        self.halt
+    }
+
+    /// A line per step with its number, address, instruction and the
+    /// accumulator before and after it, followed by the reason to halt.
+    pub fn to_table(&self) -> String {
+        let mut table = format!(
+            "{:>6} {:>6}  {:<12} {:>8} {:>8}\n",
+            "step", "pc", "instruction", "before", "after"
+        );
+        for (n, step) in self.steps.iter().enumerate() {
+            table.push_str(&format!(
+                "{:>6} {:>6}  {:<12} {:>8} {:>8}\n",
+                n,
+                step.pc(),
+                step.instruction().to_string(),
+                step.before(),
+                step.after()
+            ));
+        }
+        if let Some(halt) = self.halt {
+            table.push_str(&format!("halted: {}\n", halt));
+        }
+        table
+    }
+
+    /// An object with the steps and the reason to halt, or `null` if the machine
+    /// has not halted, e.g. `{"steps":[{"step":0,"pc":0,"instruction":"acc +1",
+    /// "before":0,"after":1}],"halt":"end of program"}`.
+    pub fn to_json(&self) -> Value {
+        let number = |n: usize| Value::from(i64::try_from(n).unwrap_or(i64::MAX));
+        let steps = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(n, step)| {
+                Value::Object(vec![
+                    ("step".to_string(), number(n)),
+                    ("pc".to_string(), number(step.pc())),
+                    (
+                        "instruction".to_string(),
+                        step.instruction().to_string().into(),
+                    ),
+                    ("before".to_string(), step.before().into()),
+                    ("after".to_string(), step.after().into()),
+                ])
+            })
+            .collect();
+        let halt = self
+            .halt
+            .map_or(Value::Null, |halt| halt.to_string().into());
+        Value::Object(vec![
+            ("steps".to_string(), Value::Array(steps)),
+            ("halt".to_string(), halt),
+        ])
+    }
+}
+
+/// Stops the debugger before an instruction runs.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Breakpoint {
+    Address(usize),
+    Opcode(Opcode),
+}
+
+/// Stops the debugger after an instruction changes the accumulator,
+/// or makes a condition on the accumulator true that was false before.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Watchpoint {
+    Changes,
+    Equals(i64),
+    AtLeast(i64),
+    AtMost(i64),
+}
+
+impl Watchpoint {
+    fn is_hit(&self, step: &Step) -> bool {
+        match *self {
+            Watchpoint::Changes => step.before() != step.after(),
+            Watchpoint::Equals(value) => step.before() != value && step.after() == value,
+            Watchpoint::AtLeast(value) => step.before() < value && step.after() >= value,
+            Watchpoint::AtMost(value) => step.before() > value && step.after() <= value,
+        }
+    }
+}
+
+/// Why the debugger gave back control.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Stop {
+    /// The breakpoint is hit at the address, before its instruction runs.
+    Breakpoint(Breakpoint, usize),
+    /// The watchpoint is hit by the step.
+    Watchpoint(Watchpoint, Step),
+    /// The instruction at the address is about to run a second time.
+    Loop(usize),
+    Halt(Halt),
+}
+
+/// Runs a machine step by step, stopping at breakpoints and watchpoints.
+/// Every executed step is kept in the trace.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    trace: Trace,
+    /// The address of the breakpoint the debugger stopped at last.
+    stopped_at: Option<usize>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Debugger {
+            machine,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            trace: Trace::default(),
+            stopped_at: None,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|&b| b != breakpoint);
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.retain(|&w| w != watchpoint);
+    }
+
+    /// Executes one instruction, ignoring breakpoints and watchpoints.
+    /// A breakpoint reached afterwards is hit by `resume`, even at the
+    /// instruction the debugger stopped at before.
+    pub fn step(&mut self) -> Result<Step, Halt> {
+        self.stopped_at = None;
+        let result = self.machine.step();
+        match result {
+            Ok(step) => self.trace.steps.push(step),
+            Err(halt) => self.trace.halt = Some(halt),
+        }
+        result
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or the machine halts.
+    /// A breakpoint at the instruction the debugger stopped at is not hit
+    /// again, so running continues past it.
+    pub fn resume(&mut self) -> Stop {
+        let mut skip = self.stopped_at.take();
+        loop {
+            if let Ok((pc, instruction)) = self.machine.peek() {
+                let hit = self.breakpoints.iter().find(|b| match b {
+                    Breakpoint::Address(address) => *address == pc,
+                    Breakpoint::Opcode(opcode) => *opcode == instruction.opcode(),
+                });
+                if let (Some(&breakpoint), true) = (hit, skip != Some(pc)) {
+                    self.stopped_at = Some(pc);
+                    return Stop::Breakpoint(breakpoint, pc);
+                }
+            }
+            skip = None;
+
+            let step = match self.step() {
+                Ok(step) => step,
+                Err(halt) => return Stop::Halt(halt),
+            };
+            if let Some(&watchpoint) = self.watchpoints.iter().find(|w| w.is_hit(&step)) {
+                return Stop::Watchpoint(watchpoint, step);
+            }
+        }
+    }
+
+    /// Runs until an instruction is about to run a second time, even if the
+    /// machine does not detect loops, or until the machine halts.
+    /// Breakpoints and watchpoints are ignored.
+    pub fn run_until_loop(&mut self) -> Stop {
+        loop {
+            if let Ok((pc, _)) = self.machine.peek() {
+                if self.machine.visited(pc) {
+                    return Stop::Loop(pc);
+                }
+            }
+            if let Err(halt) = self.step() {
+                return Stop::Halt(halt);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::asm::assemble;
+    use crate::util::vm::debug::{Breakpoint, Debugger, Stop, Trace, Watchpoint};
+    use crate::util::vm::isa::Opcode;
+    use crate::util::vm::{Halt, Machine};
+
+    const EXAMPLE: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    fn machine(source: &str) -> Machine {
+        Machine::new(assemble(source).unwrap())
+    }
+
+    #[test]
+    fn trace_as_table() {
+        let trace = Trace::record(&mut machine("acc +2\njmp -1"));
+        assert_eq!(trace.steps().len(), 2);
+        assert_eq!(trace.halt(), Some(Halt::Loop(0)));
+        assert_eq!(
+            trace.to_table(),
+            "  step     pc  instruction    before    after
+     0      0  acc +2              0        2
+     1      1  jmp -1              2        2
+halted: loop at 0
+"
+        );
+    }
+
+    #[test]
+    fn trace_as_json() {
+        let trace = Trace::record(&mut machine("acc -2"));
+        assert_eq!(
+            trace.to_json().to_string(),
+            r#"{"steps":[{"step":0,"pc":0,"instruction":"acc -2","before":0,"after":-2}],"halt":"end of program"}"#
+        );
+        assert_eq!(
+            Trace::default().to_json().to_string(),
+            r#"{"steps":[],"halt":null}"#
+        );
+    }
+
+    #[test]
+    fn breakpoints_on_addresses_and_opcodes() {
+        let mut debugger = Debugger::new(machine(EXAMPLE));
+        debugger.add_breakpoint(Breakpoint::Address(6));
+        debugger.add_breakpoint(Breakpoint::Opcode(Opcode::Jmp));
+
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Opcode(Opcode::Jmp), 2)
+        );
+        assert_eq!(debugger.machine().accumulator(), 1);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Address(6), 6)
+        );
+
+        debugger.remove_breakpoint(Breakpoint::Opcode(Opcode::Jmp));
+        debugger.remove_breakpoint(Breakpoint::Address(6));
+        assert_eq!(debugger.resume(), Stop::Halt(Halt::Loop(1)));
+        assert_eq!(debugger.trace().steps().len(), 7);
+        assert_eq!(debugger.trace().halt(), Some(Halt::Loop(1)));
+    }
+
+    #[test]
+    fn watchpoints_on_the_accumulator() {
+        let mut debugger = Debugger::new(machine(EXAMPLE));
+        debugger.add_watchpoint(Watchpoint::Changes);
+        match debugger.resume() {
+            Stop::Watchpoint(Watchpoint::Changes, step) => {
+                assert_eq!((step.pc(), step.before(), step.after()), (1, 0, 1));
+            }
+            stop => panic!("unexpected stop {:?}", stop),
+        }
+
+        debugger.remove_watchpoint(Watchpoint::Changes);
+        debugger.add_watchpoint(Watchpoint::AtLeast(3));
+        match debugger.resume() {
+            Stop::Watchpoint(Watchpoint::AtLeast(3), step) => {
+                assert_eq!((step.pc(), step.before(), step.after()), (3, 2, 5));
+            }
+            stop => panic!("unexpected stop {:?}", stop),
+        }
+        debugger.add_watchpoint(Watchpoint::Equals(-1));
+        assert_eq!(debugger.resume(), Stop::Halt(Halt::Loop(1)));
+    }
+
+    #[test]
+    fn breakpoint_at_the_start() {
+        let mut debugger = Debugger::new(machine("acc +1\njmp -1"));
+        debugger.add_breakpoint(Breakpoint::Address(0));
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Address(0), 0)
+        );
+        assert_eq!(debugger.step().map(|s| s.after()), Ok(1));
+        assert_eq!(debugger.resume(), Stop::Halt(Halt::Loop(0)));
+    }
+
+    #[test]
+    fn breakpoint_is_hit_again_after_a_step() {
+        let mut debugger = Debugger::new(machine("jmp +0").with_loop_detection(false));
+        debugger.add_breakpoint(Breakpoint::Opcode(Opcode::Jmp));
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Opcode(Opcode::Jmp), 0)
+        );
+        assert_eq!(debugger.step().map(|s| s.pc()), Ok(0));
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Opcode(Opcode::Jmp), 0)
+        );
+        assert_eq!(debugger.trace().steps().len(), 1);
+    }
+
+    #[test]
+    fn run_until_loop_without_loop_detection() {
+        let mut debugger = Debugger::new(machine(EXAMPLE).with_loop_detection(false));
+        assert_eq!(debugger.run_until_loop(), Stop::Loop(1));
+        assert_eq!(debugger.machine().accumulator(), 5);
+        let pcs: Vec<usize> = debugger.trace().steps().iter().map(|s| s.pc()).collect();
+        assert_eq!(pcs, vec![0, 1, 2, 6, 7, 3, 4]);
+
+        let mut debugger = Debugger::new(machine("acc +1\nacc +2"));
+        assert_eq!(debugger.run_until_loop(), Stop::Halt(Halt::End));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash, PartialOrd, Ord)]
+pub enum Opcode {
+    Nop,
+    Acc,
+    Jmp,
+    Mul,
+    Hlt,
+}
+
+/// How execution continues after an instruction.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Flow {
+    /// With the next instruction.
+    Next,
+    /// With the instruction at the argument's offset.
+    Jump,
+    /// Not at all, the program ends.
+    Stop,
+}
+
+/// The description of an instruction. Adding an instruction
+/// only needs an `Opcode` and an entry in `INSTRUCTION_SET`.
+pub struct OpInfo {
+    pub opcode: Opcode,
+    pub mnemonic: &'static str,
+    pub takes_argument: bool,
+    pub flow: Flow,
+    /// The accumulator after the instruction, given the accumulator and
+    /// the argument, or `None` if it overflows.
+    pub execute: fn(i64, i64) -> Option<i64>,
+}
+
+pub static INSTRUCTION_SET: &[OpInfo] = &[
+    OpInfo {
+        opcode: Opcode::Nop,
+        mnemonic: "nop",
+        takes_argument: true,
+        flow: Flow::Next,
+        execute: |acc, _| Some(acc),
+    },
+    OpInfo {
+        opcode: Opcode::Acc,
+        mnemonic: "acc",
+        takes_argument: true,
+        flow: Flow::Next,
+        execute: i64::checked_add,
+    },
+    OpInfo {
+        opcode: Opcode::Jmp,
+        mnemonic: "jmp",
+        takes_argument: true,
+        flow: Flow::Jump,
+        execute: |acc, _| Some(acc),
+    },
+    OpInfo {
+        opcode: Opcode::Mul,
+        mnemonic: "mul",
+        takes_argument: true,
+        flow: Flow::Next,
+        execute: i64::checked_mul,
+    },
+    OpInfo {
+        opcode: Opcode::Hlt,
+        mnemonic: "hlt",
+        takes_argument: false,
+        flow: Flow::Stop,
+        execute: |acc, _| Some(acc),
+    },
+];
+
+impl Opcode {
+    pub fn info(self) -> &'static OpInfo {
+        INSTRUCTION_SET
+            .iter()
+            .find(|info| info.opcode == self)
+            .expect("Every opcode is in the instruction set")
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        INSTRUCTION_SET
+            .iter()
+            .find(|info| info.mnemonic == mnemonic)
+            .map(|info| info.opcode)
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        self.info().mnemonic
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+pub struct Instruction {
+    opcode: Opcode,
+    argument: i64,
+}
+
+impl Instruction {
+    /// The argument of an opcode without one is always 0.
+    pub fn new(opcode: Opcode, argument: i64) -> Self {
+        let argument = if opcode.info().takes_argument {
+            argument
+        } else {
+            0
+        };
+        Instruction { opcode, argument }
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn argument(&self) -> i64 {
+        self.argument
+    }
+
+    pub fn info(&self) -> &'static OpInfo {
+        self.opcode.info()
+    }
+
+    /// The instruction with another opcode and the same argument.
+    pub fn with_opcode(&self, opcode: Opcode) -> Self {
+        Instruction::new(opcode, self.argument)
+    }
+
+    /// The address execution continues at after this instruction at `pc`,
+    /// or `None` if the program stops. The address may be outside the program,
+    /// and saturates at the bounds of `i64`.
+    pub fn successor(&self, pc: i64) -> Option<i64> {
+        match self.info().flow {
+            Flow::Next => Some(pc.saturating_add(1)),
+            Flow::Jump => Some(pc.saturating_add(self.argument)),
+            Flow::Stop => None,
+        }
+    }
+}
+
+/// Writes the instruction as the assembler reads it, e.g. `jmp -4` or `hlt`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.info().takes_argument {
+            write!(f, "{} {:+}", self.opcode.mnemonic(), self.argument)
+        } else {
+            write!(f, "{}", self.opcode.mnemonic())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::isa::{Instruction, Opcode, INSTRUCTION_SET};
+
+    #[test]
+    fn every_opcode_has_one_entry() {
+        for info in INSTRUCTION_SET {
+            assert_eq!(info.opcode.info().mnemonic, info.mnemonic);
+            assert_eq!(Opcode::from_mnemonic(info.mnemonic), Some(info.opcode));
+        }
+        assert_eq!(Opcode::from_mnemonic("mov"), None);
+    }
+
+    #[test]
+    fn successors() {
+        assert_eq!(Instruction::new(Opcode::Acc, 5).successor(3), Some(4));
+        assert_eq!(Instruction::new(Opcode::Jmp, -5).successor(3), Some(-2));
+        assert_eq!(Instruction::new(Opcode::Hlt, 0).successor(3), None);
+        assert_eq!(
+            Instruction::new(Opcode::Jmp, 1).successor(i64::MAX),
+            Some(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Instruction::new(Opcode::Jmp, -4).to_string(), "jmp -4");
+        assert_eq!(Instruction::new(Opcode::Nop, 0).to_string(), "nop +0");
+        assert_eq!(Instruction::new(Opcode::Hlt, 7).to_string(), "hlt");
+    }
+}
@@ -0,0 +1,294 @@
+pub mod asm;
+pub mod cfg;
+pub mod debug;
+pub mod isa;
+pub mod repair;
+
+use crate::util::vm::isa::Instruction;
+use std::fmt;
+
+/// Why a machine stopped.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Halt {
+    /// The program counter is just past the last instruction.
+    End,
+    /// The stopping instruction at the address was executed.
+    Stopped(usize),
+    /// The instruction at the address would run a second time.
+    Loop(usize),
+    /// The step budget is used up.
+    StepBudget,
+    /// A jump left the program counter outside the program, other than just past the end.
+    OutOfRange(i64),
+    /// The instruction at the address would overflow the accumulator.
+    Overflow(usize),
+}
+
+impl Halt {
+    /// Whether the program finished, either at its end or by a stopping instruction.
+    pub fn is_termination(&self) -> bool {
+        matches!(self, Halt::End | Halt::Stopped(_))
+    }
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Halt::End => write!(f, "end of program"),
+            Halt::Stopped(pc) => write!(f, "stopped at {}", pc),
+            Halt::Loop(pc) => write!(f, "loop at {}", pc),
+            Halt::StepBudget => write!(f, "step budget used up"),
+            Halt::OutOfRange(pc) => write!(f, "jump out of range to {}", pc),
+            Halt::Overflow(pc) => write!(f, "overflow at {}", pc),
+        }
+    }
+}
+
+/// An executed instruction, with the accumulator before and after it.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Step {
+    pc: usize,
+    instruction: Instruction,
+    before: i64,
+    after: i64,
+}
+
+impl Step {
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        self.instruction
+    }
+
+    pub fn before(&self) -> i64 {
+        self.before
+    }
+
+    pub fn after(&self) -> i64 {
+        self.after
+    }
+}
+
+/// Runs a program. By default it halts at the end of the program, before an
+/// instruction runs a second time, and on a jump outside the program.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Machine {
+    program: Vec<Instruction>,
+    pc: i64,
+    accumulator: i64,
+    steps: usize,
+    visited: Vec<bool>,
+    detect_loops: bool,
+    step_budget: Option<usize>,
+    halt: Option<Halt>,
+}
+
+impl Machine {
+    pub fn new(program: Vec<Instruction>) -> Self {
+        let visited = vec![false; program.len()];
+        Machine {
+            program,
+            pc: 0,
+            accumulator: 0,
+            steps: 0,
+            visited,
+            detect_loops: true,
+            step_budget: None,
+            halt: None,
+        }
+    }
+
+    /// Whether to halt before an instruction runs a second time.
+    pub fn with_loop_detection(mut self, detect_loops: bool) -> Self {
+        self.detect_loops = detect_loops;
+        self
+    }
+
+    /// Halts after this many steps.
+    pub fn with_step_budget(mut self, steps: usize) -> Self {
+        self.step_budget = Some(steps);
+        self
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn pc(&self) -> i64 {
+        self.pc
+    }
+
+    pub fn accumulator(&self) -> i64 {
+        self.accumulator
+    }
+
+    /// The number of instructions executed.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Whether the instruction at the address has been executed.
+    pub fn visited(&self, address: usize) -> bool {
+        self.visited.get(address).copied().unwrap_or(false)
+    }
+
+    /// Why the machine halted, if it did.
+    pub fn halt(&self) -> Option<Halt> {
+        self.halt
+    }
+
+    /// Starts the program again, keeping the halting conditions.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.accumulator = 0;
+        self.steps = 0;
+        self.visited.iter_mut().for_each(|v| *v = false);
+        self.halt = None;
+    }
+
+    /// The address and instruction that runs next, or why none does.
+    pub fn peek(&self) -> Result<(usize, Instruction), Halt> {
+        if let Some(halt) = self.halt {
+            return Err(halt);
+        }
+        let len = self.program.len() as i64;
+        if self.pc == len {
+            return Err(Halt::End);
+        }
+        if self.pc < 0 || self.pc > len {
+            return Err(Halt::OutOfRange(self.pc));
+        }
+        let pc = self.pc as usize;
+        if self.detect_loops && self.visited[pc] {
+            return Err(Halt::Loop(pc));
+        }
+        if self.step_budget.is_some_and(|budget| self.steps >= budget) {
+            return Err(Halt::StepBudget);
+        }
+        Ok((pc, self.program[pc]))
+    }
+
+    /// Executes one instruction, or halts.
+    pub fn step(&mut self) -> Result<Step, Halt> {
+        let (pc, instruction) = match self.peek() {
+            Ok(next) => next,
+            Err(halt) => {
+                self.halt = Some(halt);
+                return Err(halt);
+            }
+        };
+
+        let before = self.accumulator;
+        let after = match (instruction.info().execute)(before, instruction.argument()) {
+            Some(after) => after,
+            None => {
+                self.halt = Some(Halt::Overflow(pc));
+                return Err(Halt::Overflow(pc));
+            }
+        };
+
+        self.accumulator = after;
+        self.visited[pc] = true;
+        self.steps += 1;
+        match instruction.successor(self.pc) {
+            Some(next) => self.pc = next,
+            None => self.halt = Some(Halt::Stopped(pc)),
+        }
+        Ok(Step {
+            pc,
+            instruction,
+            before,
+            after,
+        })
+    }
+
+    /// Executes instructions until the machine halts.
+    pub fn run(&mut self) -> Halt {
+        loop {
+            if let Err(halt) = self.step() {
+                return halt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::asm::assemble;
+    use crate::util::vm::{Halt, Machine};
+
+    fn machine(source: &str) -> Machine {
+        Machine::new(assemble(source).unwrap())
+    }
+
+    #[test]
+    fn halts_at_the_end() {
+        let mut m = machine("acc +2\nmul -3\nnop +9");
+        assert_eq!(m.run(), Halt::End);
+        assert_eq!(m.accumulator(), -6);
+        assert_eq!(m.steps(), 3);
+        assert_eq!(m.halt(), Some(Halt::End));
+        assert!(m.step().is_err());
+    }
+
+    #[test]
+    fn halts_before_a_loop() {
+        let mut m = machine("acc +1\njmp -1");
+        assert_eq!(m.run(), Halt::Loop(0));
+        assert_eq!(m.accumulator(), 1);
+
+        let mut m = machine("acc +1\njmp -1")
+            .with_loop_detection(false)
+            .with_step_budget(9);
+        assert_eq!(m.run(), Halt::StepBudget);
+        assert_eq!(m.accumulator(), 5);
+        assert_eq!(m.steps(), 9);
+    }
+
+    #[test]
+    fn halts_on_jumps_outside_the_program() {
+        assert_eq!(machine("acc +1\njmp -2").run(), Halt::OutOfRange(-1));
+        assert_eq!(machine("jmp +3\nnop +0").run(), Halt::OutOfRange(3));
+        assert_eq!(machine("jmp +2\nnop +0").run(), Halt::End);
+        assert_eq!(
+            machine(&format!("jmp {}", i64::MAX)).run(),
+            Halt::OutOfRange(i64::MAX)
+        );
+        assert_eq!(
+            machine(&format!("nop +0\njmp {}", i64::MIN)).run(),
+            Halt::OutOfRange(i64::MIN + 1)
+        );
+    }
+
+    #[test]
+    fn stop_instruction_and_overflow() {
+        let mut m = machine("acc +3\nhlt\nacc +1");
+        assert_eq!(m.run(), Halt::Stopped(1));
+        assert_eq!(m.accumulator(), 3);
+        assert!(m.run().is_termination());
+
+        let mut m = machine(&format!("acc {}\nacc +1", i64::MAX));
+        assert_eq!(m.run(), Halt::Overflow(1));
+        assert_eq!(m.accumulator(), i64::MAX);
+        assert!(!Halt::Overflow(1).is_termination());
+    }
+
+    #[test]
+    fn steps_and_reset() {
+        let mut m = machine("acc +4\njmp +2\nacc +100\nacc -1");
+        let step = m.step().unwrap();
+        assert_eq!((step.pc(), step.before(), step.after()), (0, 0, 4));
+        let step = m.step().unwrap();
+        assert_eq!(step.instruction().to_string(), "jmp +2");
+        assert_eq!(m.peek().map(|(pc, _)| pc), Ok(3));
+        assert!(m.visited(1));
+        assert!(!m.visited(2));
+
+        m.reset();
+        assert_eq!((m.pc(), m.accumulator(), m.steps()), (0, 0, 0));
+        assert_eq!(m.run(), Halt::End);
+        assert_eq!(m.accumulator(), 3);
+    }
+}
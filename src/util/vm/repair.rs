@@ -0,0 +1,365 @@
+use crate::util::vm::cfg::ControlFlowGraph;
+use crate::util::vm::isa::{Instruction, Opcode};
+use crate::util::vm::Machine;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A program that terminates after replacing the instruction at the address.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Repair {
+    address: usize,
+    replacement: Instruction,
+    accumulator: i64,
+}
+
+impl Repair {
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn replacement(&self) -> Instruction {
+        self.replacement
+    }
+
+    /// The accumulator when the repaired program terminates.
+    pub fn accumulator(&self) -> i64 {
+        self.accumulator
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum RepairError {
+    /// The program terminates as it is, with the accumulator.
+    AlreadyTerminates(i64),
+    NoRepair,
+}
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairError::AlreadyTerminates(acc) => {
+                write!(f, "the program already terminates with {}", acc)
+            }
+            RepairError::NoRepair => write!(f, "no single swap makes the program terminate"),
+        }
+    }
+}
+
+/// The opcode a `nop` or `jmp` is swapped with.
+pub fn swapped(opcode: Opcode) -> Option<Opcode> {
+    match opcode {
+        Opcode::Nop => Some(Opcode::Jmp),
+        Opcode::Jmp => Some(Opcode::Nop),
+        _ => None,
+    }
+}
+
+/// Where execution from an address that reaches the end of the program
+/// ends up: the accumulators it may start with without overflowing,
+/// `low..=high`, and the final accumulator for them, which is affine in
+/// the starting one because instructions only add and multiply.
+#[derive(Debug, Copy, Clone)]
+struct Suffix {
+    low: i64,
+    high: i64,
+    slope: i128,
+    at_low: i64,
+}
+
+impl Suffix {
+    const END: Suffix = Suffix {
+        low: i64::MIN,
+        high: i64::MAX,
+        slope: 1,
+        at_low: i64::MIN,
+    };
+
+    /// The final accumulator when starting with `acc`, or `None` on an overflow.
+    fn apply(&self, acc: i64) -> Option<i64> {
+        if acc < self.low || acc > self.high {
+            return None;
+        }
+        let offset = self.slope.checked_mul(acc as i128 - self.low as i128)?;
+        i64::try_from(offset.checked_add(self.at_low as i128)?).ok()
+    }
+
+    /// The suffix of running the instruction first, or `None` if that
+    /// overflows for every accumulator.
+    fn after(&self, instruction: Instruction) -> Option<Suffix> {
+        let (min, max) = (i64::MIN as i128, i64::MAX as i128);
+        let (low, high) = (self.low as i128, self.high as i128);
+        let k = instruction.argument() as i128;
+        let (slope, low, high) = match instruction.opcode() {
+            Opcode::Nop | Opcode::Jmp | Opcode::Hlt => (1, low, high),
+            Opcode::Acc => (1, low - k, high - k),
+            Opcode::Mul if k == 0 && low <= 0 && 0 <= high => (0, min, max),
+            Opcode::Mul if k == 0 => return None,
+            Opcode::Mul if k > 0 => (k, ceil_div(low, k), floor_div(high, k)),
+            Opcode::Mul => (k, ceil_div(high, k), floor_div(low, k)),
+        };
+        let (low, high) = (low.max(min), high.min(max));
+        if low > high {
+            return None;
+        }
+
+        let low = low as i64;
+        let at_low = self.apply((instruction.info().execute)(low, instruction.argument())?)?;
+        // with a single start the slope does not matter, and it may be too large
+        let slope = if low == high as i64 {
+            0
+        } else {
+            self.slope.checked_mul(slope)?
+        };
+        Some(Suffix {
+            low,
+            high: high as i64,
+            slope,
+            at_low,
+        })
+    }
+}
+
+fn floor_div(a: i128, b: i128) -> i128 {
+    if b < 0 {
+        (-a).div_euclid(-b)
+    } else {
+        a.div_euclid(b)
+    }
+}
+
+fn ceil_div(a: i128, b: i128) -> i128 {
+    -floor_div(-a, b)
+}
+
+/// The suffix of every address that reaches the end of the program, found
+/// backwards from the end and the stopping instructions.
+fn suffixes(program: &[Instruction], cfg: &ControlFlowGraph) -> Vec<Option<Suffix>> {
+    let mut suffixes: Vec<Option<Suffix>> = vec![None; program.len() + 1];
+    suffixes[program.len()] = Some(Suffix::END);
+    let mut queue: VecDeque<usize> = (0..program.len())
+        .filter(|&address| cfg.successor(address).is_none())
+        .collect();
+    queue.extend(cfg.predecessors(program.len()));
+    while let Some(address) = queue.pop_front() {
+        let next = match cfg.next(address) {
+            Some(next) => suffixes[next],
+            None => Some(Suffix::END),
+        };
+        suffixes[address] = next.and_then(|next| next.after(program[address]));
+        queue.extend(cfg.predecessors(address));
+    }
+    suffixes
+}
+
+/// Every swap of a single `nop` and `jmp` that makes the program terminate,
+/// in order of address.
+///
+/// Only instructions on the path from the start can matter, and a swap works
+/// exactly when the new successor terminates in the original program. The
+/// path is run once, and the effect of the rest of the program is found once
+/// per address, so the repairs are found in linear time. A repair whose
+/// accumulator overflows is left out, as running it would halt.
+pub fn repairs(program: &[Instruction]) -> Result<Vec<Repair>, RepairError> {
+    // the control flow alone misses programs that overflow on their way to the end
+    let mut machine = Machine::new(program.to_vec());
+    if machine.run().is_termination() {
+        return Err(RepairError::AlreadyTerminates(machine.accumulator()));
+    }
+    let cfg = ControlFlowGraph::new(program);
+    let suffixes = suffixes(program, &cfg);
+
+    let mut found = Vec::new();
+    let mut machine = Machine::new(program.to_vec());
+    while let Ok(step) = machine.step() {
+        let address = step.pc();
+        let replacement = match swapped(step.instruction().opcode()) {
+            Some(opcode) => step.instruction().with_opcode(opcode),
+            None => continue,
+        };
+        let suffix = match replacement.successor(address as i64) {
+            Some(next) if 0 <= next && next <= program.len() as i64 => suffixes[next as usize],
+            _ => None,
+        };
+        if let Some(accumulator) = suffix.and_then(|suffix| suffix.apply(step.before())) {
+            found.push(Repair {
+                address,
+                replacement,
+                accumulator,
+            });
+        }
+    }
+    found.sort_by_key(|repair| repair.address);
+
+    if found.is_empty() {
+        Err(RepairError::NoRepair)
+    } else {
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::vm::asm::assemble;
+    use crate::util::vm::isa::{Instruction, Opcode};
+    use crate::util::vm::repair::{repairs, swapped, RepairError};
+    use crate::util::vm::Machine;
+
+    fn brute_force(program: &[Instruction]) -> Vec<(usize, i64)> {
+        let mut found = Vec::new();
+        for address in 0..program.len() {
+            let opcode = match swapped(program[address].opcode()) {
+                Some(opcode) => opcode,
+                None => continue,
+            };
+            let mut repaired = program.to_vec();
+            repaired[address] = repaired[address].with_opcode(opcode);
+            let mut machine = Machine::new(repaired);
+            if machine.run().is_termination() {
+                found.push((address, machine.accumulator()));
+            }
+        }
+        found
+    }
+
+    fn found(program: &[Instruction]) -> Vec<(usize, i64)> {
+        repairs(program)
+            .unwrap_or_default()
+            .iter()
+            .map(|r| (r.address(), r.accumulator()))
+            .collect()
+    }
+
+    #[test]
+    fn repair_the_example() {
+        let program =
+            assemble("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6")
+                .unwrap();
+        let found = repairs(&program).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address(), 7);
+        assert_eq!(found[0].replacement(), Instruction::new(Opcode::Nop, -4));
+        assert_eq!(found[0].accumulator(), 8);
+    }
+
+    #[test]
+    fn every_repair_is_found() {
+        // swapping the first instruction skips the loop at 2, and so does swapping the loop
+        let program = assemble("nop +3\njmp +1\njmp +0\nacc +7\nacc +1").unwrap();
+        let found: Vec<(usize, i64)> = repairs(&program)
+            .unwrap()
+            .iter()
+            .map(|r| (r.address(), r.accumulator()))
+            .collect();
+        assert_eq!(found, vec![(0, 8), (2, 8)]);
+    }
+
+    #[test]
+    fn programs_without_repair() {
+        assert_eq!(
+            repairs(&assemble("acc +1\nacc +2").unwrap()),
+            Err(RepairError::AlreadyTerminates(3))
+        );
+        assert_eq!(repairs(&[]), Err(RepairError::AlreadyTerminates(0)));
+        assert_eq!(
+            repairs(&assemble("acc +1\njmp +0\nacc +2\njmp -1").unwrap()),
+            Err(RepairError::NoRepair)
+        );
+        assert_eq!(
+            repairs(&assemble("jmp +0\njmp -1").unwrap()),
+            Err(RepairError::NoRepair)
+        );
+    }
+
+    #[test]
+    fn repairs_agree_with_brute_force() {
+        let program = assemble(
+            "acc +3\njmp +3\nnop +2\njmp +5\nacc -1\nnop +4\njmp -3\nacc +2\nnop -4\njmp -8\nacc +9",
+        )
+        .unwrap();
+        assert_eq!(found(&program), brute_force(&program));
+        assert!(!found(&program).is_empty());
+    }
+
+    #[test]
+    fn repairs_that_overflow_are_left_out() {
+        let big = i64::MAX / 2 + 1;
+        // the repair at 0 doubles 1 into an overflow, the one at 1 multiplies 0
+        let source = format!(
+            "nop +3\nnop +4\njmp +0\nacc +1\nmul {}\nmul 2\nacc -5\nhlt",
+            big
+        );
+        let program = assemble(&source).unwrap();
+        assert_eq!(found(&program), vec![(1, -5)]);
+        assert_eq!(found(&program), brute_force(&program));
+
+        let source = format!("nop +2\njmp +0\nacc {}\nacc +1", i64::MAX);
+        let program = assemble(&source).unwrap();
+        assert_eq!(found(&program), vec![]);
+        assert_eq!(found(&program), brute_force(&program));
+    }
+
+    #[test]
+    fn programs_that_overflow_on_the_way_to_the_end_are_repaired() {
+        let source = format!("nop +2\nacc {}\nacc +1", i64::MAX);
+        let program = assemble(&source).unwrap();
+        assert_eq!(found(&program), vec![(0, 1)]);
+        assert_eq!(found(&program), brute_force(&program));
+
+        let source = format!("acc {}\nacc +1", i64::MAX);
+        let program = assemble(&source).unwrap();
+        assert_eq!(repairs(&program), Err(RepairError::NoRepair));
+    }
+
+    #[test]
+    fn random_programs_agree_with_brute_force() {
+        let mut seed: u64 = 7;
+        let mut random = |n: u64| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) % n
+        };
+        let arguments = [0, 1, -1, 2, -3, 7, i64::MAX / 3, i64::MIN / 5, i64::MAX];
+        let mut with_repairs = 0;
+        for _ in 0..2000 {
+            let len = 1 + random(12) as i64;
+            let program: Vec<Instruction> = (0..len)
+                .map(|_| {
+                    let opcode = [
+                        Opcode::Nop,
+                        Opcode::Jmp,
+                        Opcode::Acc,
+                        Opcode::Mul,
+                        Opcode::Hlt,
+                    ][random(5) as usize];
+                    let argument = match opcode {
+                        Opcode::Nop | Opcode::Jmp => random(2 * len as u64 + 1) as i64 - len,
+                        _ => arguments[random(arguments.len() as u64) as usize],
+                    };
+                    Instruction::new(opcode, argument)
+                })
+                .collect();
+            if let Err(RepairError::AlreadyTerminates(accumulator)) = repairs(&program) {
+                let mut machine = Machine::new(program.clone());
+                assert!(machine.run().is_termination(), "{:?}", program);
+                assert_eq!(machine.accumulator(), accumulator);
+            } else {
+                assert_eq!(found(&program), brute_force(&program), "{:?}", program);
+                with_repairs += !found(&program).is_empty() as usize;
+            }
+        }
+        assert!(with_repairs > 200, "{}", with_repairs);
+    }
+
+    #[test]
+    fn many_repairs_in_linear_time() {
+        let n = 20_000;
+        let mut source: String = (0..n).map(|i| format!("nop +{}\n", n + 1 - i)).collect();
+        source.push_str("jmp +0\nacc +1\nmul 3\n");
+        let found = found(&assemble(&source).unwrap());
+        // every `nop` and the loop itself
+        assert_eq!(found.len(), n + 1);
+        assert!(found.iter().all(|&(_, acc)| acc == 3));
+    }
+}